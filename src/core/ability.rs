@@ -17,46 +17,270 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use bevy::prelude::*;
-use derive_more::From;
+use bevy::{prelude::*, utils::HashSet};
+use derive_more::{Display, From};
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::*;
+use serde::Deserialize;
 
-use super::{control_actions::ControlAction, cooldown::Cooldown, game_state::GameState};
+use super::{
+    control_actions::ControlAction,
+    cooldown::Cooldown,
+    effect::status_effect::{Disarmed, Silenced, Stunned},
+    game_state::GameState,
+    network::client,
+    pool::{Energy, Mana, PoolValue, UltimateCharge},
+    simulation::{SimulationAppExt, SimulationTime},
+    Authority,
+};
+
+const GLOBAL_COOLDOWN_SECS: f32 = 0.5;
 
 pub(super) struct AbilityPlugin;
 
 impl Plugin for AbilityPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::activation_system.run_in_state(GameState::InGame))
-            .add_system(Self::abilities_to_children_system.run_in_state(GameState::InGame));
+        app.add_tick_event::<AbilityFailed>()
+            .add_tick_event::<AbilityRequested>()
+            .add_tick_system(Self::cooldown_system.run_in_state(GameState::InGame))
+            .add_system(
+                Self::input_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_not(client::connected),
+            )
+            .add_tick_system(
+                Self::activation_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_not(client::connected)
+                    .after(Self::cooldown_system),
+            )
+            .add_tick_system(
+                Self::casting_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_not(client::connected)
+                    .after(Self::activation_system),
+            )
+            .add_tick_system(Self::abilities_to_children_system.run_in_state(GameState::InGame));
     }
 }
 
 impl AbilityPlugin {
+    fn cooldown_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut abilities: Query<(&mut Cooldown, Option<&mut AbilityCharges>), With<ControlAction>>,
+        mut characters: Query<(Entity, &mut GlobalCooldown)>,
+    ) {
+        for (mut cooldown, charges) in abilities.iter_mut() {
+            cooldown.tick(time.delta());
+            if let Some(mut charges) = charges {
+                if cooldown.just_finished() && charges.current < charges.max {
+                    charges.current += 1;
+                    if charges.current < charges.max {
+                        cooldown.reset();
+                    }
+                }
+            }
+        }
+
+        for (character, mut global_cooldown) in characters.iter_mut() {
+            global_cooldown.tick(time.delta());
+            if global_cooldown.finished() {
+                commands.entity(character).remove::<GlobalCooldown>();
+            }
+        }
+    }
+
+    /// Sends [`AbilityRequested`] for actions pressed by the local character.
+    fn input_system(
+        mut request_events: EventWriter<AbilityRequested>,
+        characters: Query<(Entity, &ActionState<ControlAction>), With<Authority>>,
+    ) {
+        for (character, action_state) in characters.iter() {
+            for action in ControlAction::ABILITIES {
+                if action_state.just_pressed(action) {
+                    request_events.send(AbilityRequested {
+                        caster: character,
+                        action,
+                    });
+                }
+            }
+        }
+    }
+
     fn activation_system(
         mut commands: Commands,
-        time: Res<Time>,
-        characters: Query<(Entity, &Abilities, &ActionState<ControlAction>)>,
-        mut abilities: Query<(&ControlAction, Option<&mut Cooldown>)>,
+        mut request_events: EventReader<AbilityRequested>,
+        mut failed_events: EventWriter<AbilityFailed>,
+        mut characters: Query<(
+            &Abilities,
+            Option<&Casting>,
+            Option<&GlobalCooldown>,
+            Option<&Stunned>,
+            Option<&Silenced>,
+            Option<&Disarmed>,
+            Option<&mut Mana>,
+            Option<&mut Energy>,
+            Option<&mut UltimateCharge>,
+        )>,
+        mut abilities: Query<(
+            &ControlAction,
+            Option<&mut Cooldown>,
+            Option<&mut AbilityCharges>,
+            Option<&AbilityCost>,
+            Option<&ActivationKind>,
+        )>,
     ) {
-        for (character, character_abilities, action_state) in characters.iter() {
-            for ability in character_abilities.iter() {
-                let (action, cooldown) = abilities.get_mut(*ability).unwrap();
-
-                if let Some(mut cooldown) = cooldown {
-                    cooldown.tick(time.delta());
-                    if action_state.just_pressed(*action) {
-                        if !cooldown.finished() {
-                            break;
+        let mut activated_casters = HashSet::new();
+        for request in request_events.iter() {
+            let character = request.caster;
+            let (
+                character_abilities,
+                casting,
+                global_cooldown,
+                stunned,
+                silenced,
+                disarmed,
+                mut mana,
+                mut energy,
+                mut ultimate_charge,
+            ) = match characters.get_mut(character) {
+                Ok(result) => result,
+                Err(_) => continue,
+            };
+
+            for &ability in character_abilities.iter() {
+                let (&action, mut cooldown, mut charges, cost, activation_kind) =
+                    match abilities.get_mut(ability) {
+                        Ok(result) => result,
+                        Err(_) => {
+                            warn!("Ability {:?} of {:?} doesn't exist", ability, character);
+                            continue;
                         }
-                        cooldown.reset();
+                    };
+                if action != request.action {
+                    continue;
+                }
+
+                let failure = if casting.is_some() || activated_casters.contains(&character) {
+                    Some(AbilityFailReason::Busy)
+                } else if action_blocked(action, stunned, silenced, disarmed) {
+                    Some(AbilityFailReason::Blocked)
+                } else if global_cooldown.is_some() && action != ControlAction::BaseAttack {
+                    Some(AbilityFailReason::GlobalCooldown)
+                } else if !ready(cooldown.as_deref(), charges.as_deref()) {
+                    Some(AbilityFailReason::Cooldown)
+                } else {
+                    None
+                };
+                if let Some(reason) = failure {
+                    failed_events.send(AbilityFailed {
+                        caster: character,
+                        ability,
+                        reason,
+                    });
+                    continue;
+                }
+
+                if let Some(&cost) = cost {
+                    let pool = match cost {
+                        AbilityCost::Mana(_) => mana.as_deref_mut().map(|mana| &mut **mana),
+                        AbilityCost::Energy(_) => energy.as_deref_mut().map(|energy| &mut **energy),
+                        AbilityCost::UltimateCharge => ultimate_charge
+                            .as_deref_mut()
+                            .map(|ultimate_charge| &mut **ultimate_charge),
+                    };
+                    if !cost.try_spend(pool) {
+                        failed_events.send(AbilityFailed {
+                            caster: character,
+                            ability,
+                            reason: AbilityFailReason::Cost,
+                        });
+                        continue;
                     }
                 }
 
-                if action_state.just_pressed(*action) {
-                    commands.entity(*ability).insert(Activator(character));
-                    break;
+                if let Some(cooldown) = &mut cooldown {
+                    match &mut charges {
+                        Some(charges) => {
+                            charges.current -= 1;
+                            if cooldown.finished() {
+                                cooldown.reset();
+                            }
+                        }
+                        None => cooldown.reset(),
+                    }
+                }
+                if action != ControlAction::BaseAttack {
+                    commands.entity(character).insert(GlobalCooldown::default());
+                }
+                activated_casters.insert(character);
+
+                if matches!(activation_kind, None | Some(ActivationKind::Channel { .. })) {
+                    commands.entity(ability).insert(Activator(character));
+                }
+                if let Some(&kind) = activation_kind {
+                    commands
+                        .entity(character)
+                        .insert(Casting::new(ability, action, kind));
+                }
+            }
+        }
+    }
+
+    /// Progresses casts, channels and holds.
+    fn casting_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut failed_events: EventWriter<AbilityFailed>,
+        mut characters: Query<(
+            Entity,
+            &mut Casting,
+            &ActionState<ControlAction>,
+            Option<&Stunned>,
+            Option<&Silenced>,
+            Option<&Disarmed>,
+        )>,
+    ) {
+        for (character, mut casting, action_state, stunned, silenced, disarmed) in
+            characters.iter_mut()
+        {
+            casting.timer.tick(time.delta());
+
+            let interrupted = action_blocked(casting.action, stunned, silenced, disarmed)
+                || (matches!(casting.kind, ActivationKind::Channel { .. }) && moving(action_state));
+            if interrupted {
+                commands.entity(character).remove::<Casting>();
+                failed_events.send(AbilityFailed {
+                    caster: character,
+                    ability: casting.ability,
+                    reason: AbilityFailReason::Interrupted,
+                });
+                continue;
+            }
+
+            match casting.kind {
+                ActivationKind::Cast { .. } => {
+                    if casting.timer.finished() {
+                        commands.entity(character).remove::<Casting>();
+                        commands
+                            .entity(casting.ability)
+                            .insert(Activator(character));
+                    }
+                }
+                ActivationKind::Channel { .. } => {
+                    if casting.timer.finished() {
+                        commands.entity(character).remove::<Casting>();
+                    }
+                }
+                ActivationKind::Hold { .. } => {
+                    if casting.timer.finished() || action_state.released(casting.action) {
+                        commands.entity(character).remove::<Casting>();
+                        commands
+                            .entity(casting.ability)
+                            .insert(Activator(character))
+                            .insert(HoldPower(casting.timer.percent()));
+                    }
                 }
             }
         }
@@ -72,9 +296,180 @@ impl AbilityPlugin {
     }
 }
 
+/// Returns `true` if the action can't be activated because of the character statuses.
+fn action_blocked(
+    action: ControlAction,
+    stunned: Option<&Stunned>,
+    silenced: Option<&Silenced>,
+    disarmed: Option<&Disarmed>,
+) -> bool {
+    if stunned.is_some() {
+        return true;
+    }
+
+    if action == ControlAction::BaseAttack {
+        disarmed.is_some()
+    } else {
+        silenced.is_some()
+    }
+}
+
+/// Returns `true` if the ability is not on cooldown or has at least one charge.
+fn ready(cooldown: Option<&Cooldown>, charges: Option<&AbilityCharges>) -> bool {
+    match (cooldown, charges) {
+        (_, Some(charges)) => charges.current > 0,
+        (Some(cooldown), None) => cooldown.finished(),
+        (None, None) => true,
+    }
+}
+
+fn moving(action_state: &ActionState<ControlAction>) -> bool {
+    ControlAction::MOVEMENT
+        .into_iter()
+        .any(|action| action_state.pressed(action))
+}
+
+/// How the ability is activated.
+/// Abilities without this component are activated instantly.
+#[derive(Component, Clone, Copy, Deserialize)]
+pub(crate) enum ActivationKind {
+    /// Activated after the cast time.
+    /// Interrupted by statuses that block the ability.
+    Cast { secs: f32 },
+    /// Activated instantly and continues while [`Casting`] is present.
+    /// Interrupted by statuses that block the ability and movement.
+    Channel { secs: f32 },
+    /// Activated on release or after `max_secs` with [`HoldPower`] based on the hold duration.
+    Hold { max_secs: f32 },
+}
+
+impl ActivationKind {
+    fn secs(self) -> f32 {
+        match self {
+            ActivationKind::Cast { secs } | ActivationKind::Channel { secs } => secs,
+            ActivationKind::Hold { max_secs } => max_secs,
+        }
+    }
+}
+
+/// Ability activation in progress on the character.
+#[derive(Component)]
+pub(crate) struct Casting {
+    pub(crate) ability: Entity,
+    action: ControlAction,
+    kind: ActivationKind,
+    timer: Timer,
+}
+
+impl Casting {
+    fn new(ability: Entity, action: ControlAction, kind: ActivationKind) -> Self {
+        Self {
+            ability,
+            action,
+            kind,
+            timer: Timer::from_seconds(kind.secs(), false),
+        }
+    }
+
+    /// Returns casting progress from 0.0 to 1.0.
+    pub(crate) fn progress(&self) -> f32 {
+        self.timer.percent()
+    }
+}
+
+/// Hold duration fraction of the [`ActivationKind::Hold`] ability, from 0.0 to 1.0.
+/// Inserted alongside with [`Activator`].
+#[derive(Component)]
+pub(crate) struct HoldPower(pub(crate) f32);
+
+/// Allows to store several activations of the ability.
+/// The ability [`Cooldown`] restores one charge at a time.
+#[derive(Component)]
+pub(crate) struct AbilityCharges {
+    max: u8,
+    current: u8,
+}
+
+impl AbilityCharges {
+    pub(crate) fn new(max: u8) -> Self {
+        Self { max, current: max }
+    }
+}
+
+/// Prevents activation of all abilities except the base attack for a short time after ability activation.
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct GlobalCooldown(Timer);
+
+impl Default for GlobalCooldown {
+    fn default() -> Self {
+        Self(Timer::from_seconds(GLOBAL_COOLDOWN_SECS, false))
+    }
+}
+
+/// Sent to request the ability activation bound to the action.
+/// Emitted from the local input or from the validated client requests on server.
+pub(crate) struct AbilityRequested {
+    pub(crate) caster: Entity,
+    pub(crate) action: ControlAction,
+}
+
+/// Sent when the ability activation fails.
+pub(crate) struct AbilityFailed {
+    pub(crate) caster: Entity,
+    pub(crate) ability: Entity,
+    pub(crate) reason: AbilityFailReason,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Display)]
+pub(crate) enum AbilityFailReason {
+    #[display(fmt = "Another ability is in progress")]
+    Busy,
+    #[display(fmt = "Ability is blocked")]
+    Blocked,
+    #[display(fmt = "Global cooldown")]
+    GlobalCooldown,
+    #[display(fmt = "Ability is on cooldown")]
+    Cooldown,
+    #[display(fmt = "Not enough resource")]
+    Cost,
+    #[display(fmt = "Interrupted")]
+    Interrupted,
+}
+
+/// Resource required to activate the ability.
+#[derive(Component, Clone, Copy, Deserialize)]
+pub(crate) enum AbilityCost {
+    Mana(f32),
+    Energy(f32),
+    /// Requires and consumes full ultimate charge.
+    UltimateCharge,
+}
+
+impl AbilityCost {
+    /// Spends the cost from the `pool`.
+    /// Returns `false` if the character doesn't have the pool or it doesn't contain enough resource.
+    fn try_spend(self, pool: Option<&mut PoolValue>) -> bool {
+        let pool = match pool {
+            Some(pool) => pool,
+            None => return false,
+        };
+
+        let amount = match self {
+            AbilityCost::Mana(amount) | AbilityCost::Energy(amount) => amount,
+            AbilityCost::UltimateCharge => pool.max,
+        };
+        if pool.current < amount {
+            return false;
+        }
+
+        pool.current -= amount;
+        true
+    }
+}
+
 /// Path to icon resource.
 #[derive(Component, From)]
-pub(crate) struct IconPath(pub(crate) &'static str);
+pub(crate) struct IconPath(pub(crate) String);
 
 /// Indicates that the ability has been activated and contains the hero that activated it
 #[derive(Component)]
@@ -85,11 +480,10 @@ pub(crate) struct Abilities(pub(crate) Vec<Entity>);
 
 #[cfg(test)]
 mod tests {
-    use bevy::input::InputPlugin;
+    use bevy::{ecs::event::Events, input::InputPlugin};
     use std::time::Duration;
 
     use super::*;
-    use crate::core::Authority;
 
     #[test]
     fn ability_ignores_unrelated_action() {
@@ -201,6 +595,462 @@ mod tests {
         );
     }
 
+    #[test]
+    fn ability_blocked_by_status() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .insert(Silenced)
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Ability shouldn't be triggered while silenced"
+        );
+    }
+
+    #[test]
+    fn action_blocking() {
+        assert!(action_blocked(
+            ControlAction::BaseAttack,
+            Some(&Stunned),
+            None,
+            None
+        ));
+        assert!(action_blocked(
+            ControlAction::Ability1,
+            Some(&Stunned),
+            None,
+            None
+        ));
+        assert!(!action_blocked(
+            ControlAction::BaseAttack,
+            None,
+            Some(&Silenced),
+            None
+        ));
+        assert!(action_blocked(
+            ControlAction::Ability1,
+            None,
+            Some(&Silenced),
+            None
+        ));
+        assert!(action_blocked(
+            ControlAction::BaseAttack,
+            None,
+            None,
+            Some(&Disarmed)
+        ));
+        assert!(!action_blocked(
+            ControlAction::Ability1,
+            None,
+            None,
+            Some(&Disarmed)
+        ));
+    }
+
+    #[test]
+    fn ability_requires_cost() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        const COST: f32 = 30.0;
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(AbilityCost::Mana(COST))
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .insert(Mana::default())
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            app.world.entity(ability).contains::<Activator>(),
+            "Ability should be activated"
+        );
+        assert_eq!(
+            app.world.get::<Mana>(character).unwrap().current,
+            Mana::default().current - COST,
+            "Ability cost should be spent"
+        );
+
+        app.world.entity_mut(ability).remove::<Activator>();
+        let mut cooldown = app.world.get_mut::<Cooldown>(ability).unwrap();
+        let duration = cooldown.duration();
+        cooldown.tick(duration);
+        app.world.get_mut::<Mana>(character).unwrap().current = COST - 1.0;
+        app.world.entity_mut(character).remove::<GlobalCooldown>();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.release(ControlAction::Ability1);
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Ability shouldn't be activated without enough mana"
+        );
+        assert_eq!(
+            last_fail_reason(&app),
+            Some(AbilityFailReason::Cost),
+            "Activation should fail because of cost"
+        );
+    }
+
+    #[test]
+    fn cooldown_ticks_for_all_abilities() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let first_ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .id();
+        let second_ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(ControlAction::Ability2)
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(first_ability))
+            .id();
+        app.world
+            .get_mut::<Abilities>(character)
+            .unwrap()
+            .push(second_ability);
+
+        for ability in [first_ability, second_ability] {
+            app.world.get_mut::<Cooldown>(ability).unwrap().reset();
+        }
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+        app.update();
+
+        for ability in [first_ability, second_ability] {
+            let cooldown = app.world.get::<Cooldown>(ability).unwrap();
+            assert!(
+                cooldown.elapsed() > Duration::default(),
+                "Cooldown should tick for every ability"
+            );
+        }
+    }
+
+    #[test]
+    fn global_cooldown() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .insert(GlobalCooldown::default())
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Ability shouldn't be activated during global cooldown"
+        );
+        assert_eq!(
+            last_fail_reason(&app),
+            Some(AbilityFailReason::GlobalCooldown)
+        );
+
+        let mut global_cooldown = app.world.get_mut::<GlobalCooldown>(character).unwrap();
+        global_cooldown.set_elapsed(Duration::from_secs_f32(GLOBAL_COOLDOWN_SECS));
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<GlobalCooldown>(),
+            "Global cooldown should be removed after finishing"
+        );
+    }
+
+    #[test]
+    fn ability_charges() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(AbilityCharges::new(2))
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .id();
+
+        for _ in 0..2 {
+            app.world.entity_mut(ability).remove::<Activator>();
+            app.world.entity_mut(character).remove::<GlobalCooldown>();
+            let mut action_state = app
+                .world
+                .get_mut::<ActionState<ControlAction>>(character)
+                .unwrap();
+            action_state.release(ControlAction::Ability1);
+            action_state.press(ControlAction::Ability1);
+
+            app.update();
+
+            assert!(
+                app.world.entity(ability).contains::<Activator>(),
+                "Ability should be activated while it has charges"
+            );
+        }
+
+        let charges = app.world.get::<AbilityCharges>(ability).unwrap();
+        assert_eq!(charges.current, 0, "All charges should be spent");
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.release(ControlAction::Ability1);
+        let mut cooldown = app.world.get_mut::<Cooldown>(ability).unwrap();
+        let duration = cooldown.duration();
+        cooldown.set_elapsed(duration);
+
+        app.update();
+
+        let charges = app.world.get::<AbilityCharges>(ability).unwrap();
+        assert_eq!(charges.current, 1, "Cooldown should restore one charge");
+        let cooldown = app.world.get::<Cooldown>(ability).unwrap();
+        assert!(
+            !cooldown.finished(),
+            "Cooldown should restart to restore the next charge"
+        );
+    }
+
+    #[test]
+    fn cast_activates_after_delay() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(ActivationKind::Cast { secs: 1.0 })
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Ability shouldn't be activated before cast finishes"
+        );
+        let mut casting = app
+            .world
+            .get_mut::<Casting>(character)
+            .expect("Character should start casting");
+        let duration = casting.timer.duration();
+        casting.timer.set_elapsed(duration);
+
+        app.update();
+
+        assert!(
+            app.world.entity(ability).contains::<Activator>(),
+            "Ability should be activated after cast"
+        );
+        assert!(
+            !app.world.entity(character).contains::<Casting>(),
+            "Casting should be finished"
+        );
+    }
+
+    #[test]
+    fn channel_interrupted_by_movement() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(ActivationKind::Channel { secs: 1.0 })
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            app.world.entity(ability).contains::<Activator>(),
+            "Channel should be activated instantly"
+        );
+        assert!(
+            app.world.entity(character).contains::<Casting>(),
+            "Character should be channeling"
+        );
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Forward);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<Casting>(),
+            "Channel should be interrupted by movement"
+        );
+        assert_eq!(last_fail_reason(&app), Some(AbilityFailReason::Interrupted));
+    }
+
+    #[test]
+    fn hold_activates_on_release() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityPlugin);
+
+        const MAX_SECS: f32 = 2.0;
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(DummyAbilityBundle::default())
+            .insert(ActivationKind::Hold { max_secs: MAX_SECS })
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(ability))
+            .id();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Ability shouldn't be activated while holding"
+        );
+
+        let mut casting = app.world.get_mut::<Casting>(character).unwrap();
+        casting
+            .timer
+            .set_elapsed(Duration::from_secs_f32(MAX_SECS / 2.0));
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.release(ControlAction::Ability1);
+
+        app.update();
+
+        assert!(
+            app.world.entity(ability).contains::<Activator>(),
+            "Ability should be activated on release"
+        );
+        let hold_power = app.world.get::<HoldPower>(ability).unwrap();
+        assert!(
+            hold_power.0 >= 0.5 && hold_power.0 < 1.0,
+            "Hold power should depend on hold duration"
+        );
+    }
+
+    #[test]
+    fn ultimate_cost() {
+        let mut ultimate_charge = UltimateCharge::default();
+        assert!(
+            !AbilityCost::UltimateCharge.try_spend(Some(&mut *ultimate_charge)),
+            "Ultimate shouldn't be activated without full charge"
+        );
+
+        ultimate_charge.current = ultimate_charge.max;
+        assert!(
+            AbilityCost::UltimateCharge.try_spend(Some(&mut *ultimate_charge)),
+            "Ultimate should be activated with full charge"
+        );
+        assert_eq!(
+            ultimate_charge.current, 0.0,
+            "Ultimate should consume full charge"
+        );
+    }
+
     #[test]
     fn abilities_are_children() {
         let mut app = App::new();
@@ -219,6 +1069,15 @@ mod tests {
         );
     }
 
+    fn last_fail_reason(app: &App) -> Option<AbilityFailReason> {
+        let failed_events = app.world.resource::<Events<AbilityFailed>>();
+        let mut event_reader = failed_events.get_reader();
+        event_reader
+            .iter(failed_events)
+            .last()
+            .map(|event| event.reason)
+    }
+
     struct TestAbilityPlugin;
 
     impl Plugin for TestAbilityPlugin {
@@ -71,6 +71,12 @@ pub(crate) enum ControlAction {
     Left,
     Right,
     Jump,
+    Sprint,
+    Crouch,
+    Dodge,
+
+    // Camera
+    SwapShoulder,
 
     // Abilities activation
     BaseAttack,
@@ -80,6 +86,25 @@ pub(crate) enum ControlAction {
     Ultimate,
 }
 
+impl ControlAction {
+    pub(crate) const MOVEMENT: [ControlAction; 6] = [
+        ControlAction::Forward,
+        ControlAction::Backward,
+        ControlAction::Left,
+        ControlAction::Right,
+        ControlAction::Jump,
+        ControlAction::Dodge,
+    ];
+
+    pub(crate) const ABILITIES: [ControlAction; 5] = [
+        ControlAction::BaseAttack,
+        ControlAction::Ability1,
+        ControlAction::Ability2,
+        ControlAction::Ability3,
+        ControlAction::Ultimate,
+    ];
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::ecs::event::Events;
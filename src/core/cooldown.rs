@@ -25,8 +25,15 @@ pub(crate) struct Cooldown(Timer);
 
 impl Cooldown {
     pub(super) fn from_secs(secs: u64) -> Self {
-        // Setup timer in finished state
-        let duration = Duration::from_secs(secs);
+        Self::new_finished(Duration::from_secs(secs))
+    }
+
+    pub(super) fn from_secs_f32(secs: f32) -> Self {
+        Self::new_finished(Duration::from_secs_f32(secs))
+    }
+
+    /// Setups timer in finished state.
+    fn new_finished(duration: Duration) -> Self {
         let mut timer = Timer::new(duration, false);
         timer.tick(duration);
 
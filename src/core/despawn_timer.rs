@@ -21,20 +21,23 @@ use bevy::prelude::*;
 use iyes_loopless::prelude::*;
 use std::time::Duration;
 
-use super::game_state::GameState;
+use super::{
+    game_state::GameState,
+    simulation::{SimulationAppExt, SimulationTime},
+};
 
 pub(super) struct DespawnTimerPlugin;
 
 impl Plugin for DespawnTimerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::despawn_timer_system.run_in_state(GameState::InGame));
+        app.add_tick_system(Self::despawn_timer_system.run_in_state(GameState::InGame));
     }
 }
 
 impl DespawnTimerPlugin {
     fn despawn_timer_system(
         mut commands: Commands,
-        time: Res<Time>,
+        time: Res<SimulationTime>,
         mut timers: Query<(Entity, &mut DespawnTimer)>,
     ) {
         for (entity, mut despawn_timer) in timers.iter_mut() {
@@ -54,6 +57,10 @@ impl DespawnTimer {
         let duration = Duration::from_secs(secs);
         Self(Timer::new(duration, false))
     }
+
+    pub(super) fn from_secs_f32(secs: f32) -> Self {
+        Self(Timer::from_seconds(secs, false))
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,395 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+use rand::{rngs::StdRng, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    hash::{Hash, Hasher},
+};
+
+use super::{
+    ability::Abilities,
+    cooldown::Cooldown,
+    game_state::GameState,
+    health::Health,
+    network::server::ServerSettings,
+    player::ClientId,
+    simulation::{SimulationAppExt, SimulationTime},
+    Authority,
+};
+
+/// Number of ticks to keep hashes for comparison with remote ones.
+const HASH_HISTORY_TICKS: usize = 1024;
+
+/// Makes simulation runs reproducible and hashes the state each tick when verification is enabled.
+pub(super) struct DeterminismPlugin;
+
+impl Plugin for DeterminismPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<StateHashed>()
+            .add_enter_system(GameState::InGame, Self::reset_system)
+            .add_exit_system(GameState::InGame, Self::cleanup_system)
+            .add_post_tick_system(
+                Self::hash_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<StateHashes>(),
+            );
+    }
+}
+
+impl DeterminismPlugin {
+    /// Seeds random number generator.
+    /// Ticks are aligned with server separately since clients may join in the middle of the game.
+    fn reset_system(mut commands: Commands, server_settings: Res<ServerSettings>) {
+        commands.insert_resource(GameRng::new(server_settings.seed));
+        if server_settings.verify_determinism {
+            commands.insert_resource(StateHashes::default());
+        }
+    }
+
+    fn cleanup_system(mut commands: Commands) {
+        commands.remove_resource::<StateHashes>();
+    }
+
+    /// Hashes bodies simulated by all peers and each character separately,
+    /// since clients simulate only their own characters.
+    /// Transforms always contain simulated values, rendering interpolation doesn't affect them.
+    fn hash_system(
+        simulation_time: Res<SimulationTime>,
+        mut hash_events: EventWriter<StateHashed>,
+        mut state_hashes: ResMut<StateHashes>,
+        bodies: Query<&Transform, (With<RigidBody>, Without<ClientId>)>,
+        characters: Query<(
+            &ClientId,
+            &Transform,
+            &Health,
+            Option<&Abilities>,
+            Option<&Authority>,
+        )>,
+        cooldowns: Query<&Cooldown>,
+    ) {
+        let bodies = unordered_hash(bodies.iter().map(transform_bits));
+        let mut local = StateHash {
+            bodies,
+            character: 0,
+        };
+        let mut clients = Vec::new();
+        for (&client_id, transform, health, abilities, authority) in characters.iter() {
+            let cooldowns: Vec<_> = abilities
+                .into_iter()
+                .flat_map(|abilities| abilities.0.iter())
+                .filter_map(|&ability| cooldowns.get(ability).ok())
+                .map(|cooldown| (cooldown.elapsed(), cooldown.duration()))
+                .collect();
+            let mut hasher = DefaultHasher::new();
+            (
+                transform_bits(transform),
+                health.current,
+                health.max,
+                cooldowns,
+            )
+                .hash(&mut hasher);
+            let hash = StateHash {
+                bodies,
+                character: hasher.finish(),
+            };
+
+            if authority.is_some() {
+                local = hash;
+            }
+            clients.push((client_id.0, hash));
+        }
+
+        let tick = simulation_time.tick();
+        state_hashes.insert_local(tick, local);
+        hash_events.send(StateHashed { tick, clients });
+    }
+}
+
+fn transform_bits(transform: &Transform) -> ([u32; 3], [u32; 4], [u32; 3]) {
+    (
+        transform.translation.to_array().map(f32::to_bits),
+        transform.rotation.to_array().map(f32::to_bits),
+        transform.scale.to_array().map(f32::to_bits),
+    )
+}
+
+/// Combines hashes of items regardless of their order since peers may iterate entities differently.
+fn unordered_hash<T: Hash>(items: impl Iterator<Item = T>) -> u64 {
+    items
+        .map(|item| {
+            let mut hasher = DefaultHasher::new();
+            item.hash(&mut hasher);
+            hasher.finish()
+        })
+        .fold(0, u64::wrapping_add)
+}
+
+/// Random number generator for gameplay.
+/// Seeded from [`ServerSettings`] on the game start to make runs reproducible.
+pub(crate) struct GameRng(pub(crate) StdRng);
+
+impl GameRng {
+    fn new(seed: Option<u64>) -> Self {
+        match seed {
+            Some(seed) => Self(StdRng::seed_from_u64(seed)),
+            None => Self(StdRng::from_entropy()),
+        }
+    }
+}
+
+/// Hashes of the simulation state as seen by a single peer split to locate the divergence.
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub(crate) struct StateHash {
+    /// Transforms of bodies that are not characters.
+    bodies: u64,
+    /// Transform, health and ability cooldowns of the peer's character.
+    character: u64,
+}
+
+/// An event indicating that the state was hashed at the tick.
+pub(crate) struct StateHashed {
+    pub(crate) tick: u64,
+    /// State hashes for characters of each client.
+    pub(crate) clients: Vec<(u64, StateHash)>,
+}
+
+/// Local and remote state hashes waiting for comparison.
+/// Exists only when determinism verification is enabled.
+#[derive(Default)]
+pub(crate) struct StateHashes {
+    local: BTreeMap<u64, StateHash>,
+    remote: BTreeMap<u64, StateHash>,
+    desynced: bool,
+}
+
+impl StateHashes {
+    fn insert_local(&mut self, tick: u64, hash: StateHash) {
+        self.local.insert(tick, hash);
+        trim_history(&mut self.local);
+    }
+
+    pub(crate) fn insert_remote(&mut self, tick: u64, hash: StateHash) {
+        self.remote.insert(tick, hash);
+        trim_history(&mut self.remote);
+    }
+
+    /// Compares hashes of ticks known to both sides and returns the first divergence.
+    /// Reported only once because the state stays diverged after it.
+    pub(crate) fn verify(&mut self) -> Option<Desync> {
+        let local = &self.local;
+        let mut desync = None;
+        self.remote
+            .retain(|&tick, &mut remote| match local.get(&tick) {
+                Some(&local) => {
+                    if desync.is_none() && local != remote {
+                        desync = Some(Desync {
+                            tick,
+                            local,
+                            remote,
+                        });
+                    }
+                    false
+                }
+                None => true,
+            });
+
+        if self.desynced {
+            return None;
+        }
+        self.desynced = desync.is_some();
+        desync
+    }
+}
+
+fn trim_history(hashes: &mut BTreeMap<u64, StateHash>) {
+    while hashes.len() > HASH_HISTORY_TICKS {
+        let oldest_tick = *hashes.keys().next().unwrap();
+        hashes.remove(&oldest_tick);
+    }
+}
+
+/// Diverging state hashes at the tick.
+#[derive(Debug, Serialize)]
+pub(crate) struct Desync {
+    pub(crate) tick: u64,
+    local: StateHash,
+    remote: StateHash,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::Events;
+    use rand::Rng;
+
+    use super::*;
+
+    #[test]
+    fn seeded_rng() {
+        let mut numbers = Vec::new();
+        for _ in 0..2 {
+            let mut app = App::new();
+            app.add_plugin(TestDeterminismPlugin);
+            app.world.resource_mut::<ServerSettings>().seed = Some(0);
+
+            app.update();
+
+            numbers.push(app.world.resource_mut::<GameRng>().0.gen::<u64>());
+        }
+
+        assert_eq!(
+            numbers[0], numbers[1],
+            "Random numbers should be the same with the same seed"
+        );
+    }
+
+    #[test]
+    fn state_hashing() {
+        let mut app = App::new();
+        app.add_plugin(TestDeterminismPlugin);
+        app.world
+            .resource_mut::<ServerSettings>()
+            .verify_determinism = true;
+
+        app.world
+            .spawn()
+            .insert(Transform::default())
+            .insert(RigidBody::Dynamic);
+        const LOCAL_ID: u64 = 0;
+        let character = app
+            .world
+            .spawn()
+            .insert(ClientId(LOCAL_ID))
+            .insert(Transform::default())
+            .insert(RigidBody::Dynamic)
+            .insert(Health::default())
+            .insert(Authority)
+            .id();
+        const REMOTE_ID: u64 = 1;
+        let remote_character = app
+            .world
+            .spawn()
+            .insert(ClientId(REMOTE_ID))
+            .insert(Transform::default())
+            .insert(RigidBody::Dynamic)
+            .insert(Health::default())
+            .id();
+
+        app.update();
+
+        let event = take_hash_event(&mut app);
+        assert_eq!(event.tick, 1, "Ticks should be counted from the game start");
+        let local_hash = app.world.resource::<StateHashes>().local[&event.tick];
+        assert_eq!(
+            client_hash(&event, LOCAL_ID),
+            local_hash,
+            "Local hash should be calculated for the character with authority"
+        );
+
+        app.world
+            .get_mut::<Health>(remote_character)
+            .unwrap()
+            .current -= 1;
+
+        app.update();
+
+        let remote_changed_event = take_hash_event(&mut app);
+        assert_eq!(
+            app.world.resource::<StateHashes>().local[&remote_changed_event.tick],
+            local_hash,
+            "Remote characters shouldn't affect the local hash"
+        );
+        assert_ne!(
+            client_hash(&remote_changed_event, REMOTE_ID),
+            client_hash(&event, REMOTE_ID),
+            "Remote character change should be reflected in its client hash"
+        );
+
+        app.world.get_mut::<Health>(character).unwrap().current -= 1;
+
+        app.update();
+
+        let changed_event = take_hash_event(&mut app);
+        let changed_hash = app.world.resource::<StateHashes>().local[&changed_event.tick];
+        assert_eq!(changed_hash.bodies, local_hash.bodies);
+        assert_ne!(
+            changed_hash.character, local_hash.character,
+            "Health change should be reflected in the hash"
+        );
+    }
+
+    #[test]
+    fn desync_detection() {
+        let mut state_hashes = StateHashes::default();
+        let hash = StateHash::default();
+        let diverged_hash = StateHash {
+            character: 1,
+            ..Default::default()
+        };
+        state_hashes.insert_local(1, hash);
+        state_hashes.insert_local(2, diverged_hash);
+        state_hashes.insert_remote(1, hash);
+        state_hashes.insert_remote(2, hash);
+        state_hashes.insert_remote(3, hash);
+
+        let desync = state_hashes.verify().expect("Desync should be detected");
+        assert_eq!(desync.tick, 2, "Diverging tick should be reported");
+        assert_eq!(
+            state_hashes.remote.len(),
+            1,
+            "Remote hash without local one should wait for comparison"
+        );
+
+        state_hashes.insert_local(3, diverged_hash);
+        assert!(
+            state_hashes.verify().is_none(),
+            "Desync should be reported only once"
+        );
+    }
+
+    fn take_hash_event(app: &mut App) -> StateHashed {
+        let mut hash_events = app.world.resource_mut::<Events<StateHashed>>();
+        hash_events
+            .drain()
+            .next()
+            .expect("State should be hashed each tick")
+    }
+
+    fn client_hash(event: &StateHashed, client_id: u64) -> StateHash {
+        event
+            .clients
+            .iter()
+            .find_map(|&(id, hash)| (id == client_id).then(|| hash))
+            .expect("Each character should be hashed")
+    }
+
+    struct TestDeterminismPlugin;
+
+    impl Plugin for TestDeterminismPlugin {
+        fn build(&self, app: &mut App) {
+            app.init_resource::<ServerSettings>()
+                .add_loopless_state(GameState::InGame)
+                .add_plugin(DeterminismPlugin);
+        }
+    }
+}
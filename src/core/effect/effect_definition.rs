@@ -0,0 +1,243 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::system::EntityCommands,
+    prelude::*,
+    reflect::TypeUuid,
+};
+use serde::Deserialize;
+
+use super::{
+    modifier_effect::Multiplier,
+    periodic_effect::{OwnerScaling, PeriodicEffectTimer, PeriodicHealthChange},
+    stacking::{EffectKind, EffectStrength, Stacking},
+    status_effect::{Disarmed, Rooted, Silenced, Stunned},
+    EffectTimer,
+};
+use crate::core::hero::{DamageModifier, DamageTakenModifier, HealingModifier, SpeedModifier};
+
+/// Registers [`EffectDefinition`] assets.
+pub(crate) struct EffectDefinitionPlugin;
+
+impl Plugin for EffectDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<EffectDefinition>()
+            .init_asset_loader::<EffectDefinitionLoader>();
+    }
+}
+
+/// Effect description loaded from `*.effect.json` files.
+/// Definitions are referred by their asset path and read on each application,
+/// so changes from hot-reloading affect newly applied effects.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "1bf6c6f6-6d14-41a0-983b-2d24a4e2184b"]
+pub(crate) struct EffectDefinition {
+    name: String,
+    kind: Option<EffectKind>,
+    /// Overrides default stacking policy of the kind.
+    stacking: Option<Stacking>,
+    strength: Option<f32>,
+    /// Duration in seconds, the effect is permanent if not specified.
+    duration: Option<f32>,
+    #[serde(default)]
+    modifiers: Vec<ModifierDefinition>,
+    periodic: Option<PeriodicDefinition>,
+    #[serde(default)]
+    statuses: Vec<Status>,
+}
+
+impl EffectDefinition {
+    /// Inserts components of the defined effect.
+    /// Target and owner should be inserted separately.
+    pub(crate) fn insert_into(&self, effect: &mut EntityCommands) {
+        effect.insert(Name::new(self.name.clone()));
+        if let Some(kind) = self.kind {
+            effect.insert(kind);
+        }
+        if let Some(stacking) = self.stacking {
+            effect.insert(stacking);
+        }
+        if let Some(strength) = self.strength {
+            effect.insert(EffectStrength(strength));
+        }
+        if let Some(duration) = self.duration {
+            effect.insert(EffectTimer::from(Timer::from_seconds(duration, false)));
+        }
+        for modifier in &self.modifiers {
+            modifier.insert_into(effect);
+        }
+        if let Some(periodic) = &self.periodic {
+            periodic.insert_into(effect);
+        }
+        for status in &self.statuses {
+            match status {
+                Status::Stunned => effect.insert(Stunned),
+                Status::Rooted => effect.insert(Rooted),
+                Status::Silenced => effect.insert(Silenced),
+                Status::Disarmed => effect.insert(Disarmed),
+            };
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ModifierDefinition {
+    stat: Stat,
+    /// Value added to the base stat value.
+    #[serde(default)]
+    add: f32,
+    /// Value by which the stat is multiplied after addition.
+    multiply: Option<f32>,
+}
+
+impl ModifierDefinition {
+    fn insert_into(&self, effect: &mut EntityCommands) {
+        match self.stat {
+            Stat::Speed => self.insert_modifier::<SpeedModifier>(effect),
+            Stat::Damage => self.insert_modifier::<DamageModifier>(effect),
+            Stat::DamageTaken => self.insert_modifier::<DamageTakenModifier>(effect),
+            Stat::Healing => self.insert_modifier::<HealingModifier>(effect),
+        }
+    }
+
+    fn insert_modifier<T: Component + From<f32>>(&self, effect: &mut EntityCommands) {
+        if self.add != 0.0 {
+            effect.insert(T::from(self.add));
+        }
+        if let Some(multiply) = self.multiply {
+            effect.insert(Multiplier::<T>::from(multiply));
+        }
+    }
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum Stat {
+    Speed,
+    Damage,
+    DamageTaken,
+    Healing,
+}
+
+#[derive(Deserialize, Clone, Copy)]
+enum Status {
+    Stunned,
+    Rooted,
+    Silenced,
+    Disarmed,
+}
+
+#[derive(Deserialize)]
+struct PeriodicDefinition {
+    /// Tick interval in seconds.
+    interval: f32,
+    #[serde(default)]
+    tick_on_apply: bool,
+    health_change: i32,
+    /// Scale health change by the owner's modifiers at the moment of application.
+    #[serde(default)]
+    owner_scaling: bool,
+}
+
+impl PeriodicDefinition {
+    fn insert_into(&self, effect: &mut EntityCommands) {
+        effect
+            .insert(PeriodicEffectTimer::new(self.interval, self.tick_on_apply))
+            .insert(PeriodicHealthChange::from(self.health_change));
+        if self.owner_scaling {
+            effect.insert(OwnerScaling);
+        }
+    }
+}
+
+#[derive(Default)]
+struct EffectDefinitionLoader;
+
+impl AssetLoader for EffectDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: EffectDefinition = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["effect.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    #[test]
+    fn definition_inserts_components() {
+        let definition: EffectDefinition = serde_json::from_str(
+            r#"{
+                "name": "Dummy Effect",
+                "kind": "Rage",
+                "duration": 2.0,
+                "modifiers": [
+                    { "stat": "Damage", "add": 0.5 },
+                    { "stat": "Speed", "multiply": 2.0 }
+                ],
+                "periodic": { "interval": 0.5, "health_change": -5 },
+                "statuses": ["Stunned"]
+            }"#,
+        )
+        .expect("Definition should be deserializable");
+
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let mut effect = commands.spawn();
+        definition.insert_into(&mut effect);
+        let effect = effect.id();
+        queue.apply(&mut world);
+
+        let effect = world.entity(effect);
+        assert_eq!(effect.get::<Name>().unwrap().as_str(), "Dummy Effect");
+        assert_eq!(*effect.get::<EffectKind>().unwrap(), EffectKind::Rage);
+        assert_eq!(effect.get::<DamageModifier>().unwrap().0, 0.5);
+        assert!(
+            !effect.contains::<SpeedModifier>(),
+            "Zero additive modifiers shouldn't be inserted"
+        );
+        assert!(effect.contains::<Multiplier<SpeedModifier>>());
+        assert!(effect.contains::<PeriodicHealthChange>());
+        assert!(effect.contains::<Stunned>());
+        assert!(!effect.contains::<Rooted>());
+        assert_eq!(
+            effect
+                .get::<EffectTimer>()
+                .unwrap()
+                .duration()
+                .as_secs_f32(),
+            2.0
+        );
+    }
+}
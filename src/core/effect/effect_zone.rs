@@ -0,0 +1,408 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{ecs::system::EntityCommands, prelude::*, utils::HashMap};
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use super::{effect_definition::EffectDefinition, Dispelled, EffectTarget};
+use crate::core::{
+    game_state::{GameState, InGameOnly},
+    health::Health,
+    player::Team,
+    simulation::SimulationAppExt,
+    CollisionMask, Owner,
+};
+
+/// Applies effects to characters inside [`EffectZone`].
+pub(super) struct EffectZonePlugin;
+
+impl Plugin for EffectZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(Self::enter_leave_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::dispell_orphaned_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl EffectZonePlugin {
+    fn enter_leave_system(
+        mut commands: Commands,
+        definitions: Res<Assets<EffectDefinition>>,
+        mut zones: Query<
+            (Entity, &mut EffectZone, &CollidingEntities, Option<&Owner>),
+            Changed<CollidingEntities>,
+        >,
+        characters: Query<Option<&Team>, With<Health>>,
+        effects: Query<(), (With<EffectTarget>, Without<Dispelled>)>,
+    ) {
+        for (zone, mut effect_zone, colliding_entities, owner) in zones.iter_mut() {
+            effect_zone.affected.retain(|&character, &mut effect| {
+                if colliding_entities.contains(character) {
+                    return true;
+                }
+                if effects.get(effect).is_ok() {
+                    commands.entity(effect).insert(Dispelled);
+                }
+                false
+            });
+
+            let owner = owner.map(|owner| owner.0);
+            let owner_team = owner.and_then(|owner| characters.get(owner).ok().flatten());
+            for character in colliding_entities.iter() {
+                if effect_zone.affected.contains_key(&character) {
+                    continue;
+                }
+                let team = match characters.get(character) {
+                    Ok(team) => team,
+                    Err(_) => continue,
+                };
+                let ally = Some(character) == owner || (team.is_some() && team == owner_team);
+                if !effect_zone.filter.matches(ally) {
+                    continue;
+                }
+                if let ZoneEffect::Definition(handle) = &effect_zone.effect {
+                    if !definitions.contains(handle) {
+                        continue;
+                    }
+                }
+
+                let mut effect = commands.spawn();
+                effect
+                    .insert(EffectTarget(character))
+                    .insert(ZoneSource(zone));
+                if let Some(owner) = owner {
+                    effect.insert(Owner(owner));
+                }
+                effect_zone.effect.insert_into(&mut effect, &definitions);
+                effect_zone.affected.insert(character, effect.id());
+            }
+        }
+    }
+
+    /// Dispells effects whose zone no longer exists.
+    fn dispell_orphaned_system(
+        mut commands: Commands,
+        effects: Query<(Entity, &ZoneSource), Without<Dispelled>>,
+        zones: Query<(), With<EffectZone>>,
+    ) {
+        for (effect, zone_source) in effects.iter() {
+            if zones.get(zone_source.0).is_err() {
+                commands.entity(effect).insert(Dispelled);
+            }
+        }
+    }
+}
+
+/// A zone that applies an effect to characters while they are inside.
+/// Lifetime and owner could be specified by inserting [`DespawnTimer`](crate::core::despawn_timer::DespawnTimer) and [`Owner`].
+#[derive(Component)]
+pub(crate) struct EffectZone {
+    effect: ZoneEffect,
+    filter: ZoneFilter,
+    /// Characters inside the zone with their applied effects.
+    affected: HashMap<Entity, Entity>,
+}
+
+impl EffectZone {
+    pub(crate) fn new(effect: fn(&mut EntityCommands), filter: ZoneFilter) -> Self {
+        Self {
+            effect: ZoneEffect::Components(effect),
+            filter,
+            affected: HashMap::default(),
+        }
+    }
+
+    /// Creates a zone that applies effect from the definition.
+    /// Characters are skipped until the definition is loaded.
+    pub(crate) fn from_definition(
+        definition: Handle<EffectDefinition>,
+        filter: ZoneFilter,
+    ) -> Self {
+        Self {
+            effect: ZoneEffect::Definition(definition),
+            filter,
+            affected: HashMap::default(),
+        }
+    }
+}
+
+enum ZoneEffect {
+    /// Inserts effect components into the spawned effect entity.
+    Components(fn(&mut EntityCommands)),
+    Definition(Handle<EffectDefinition>),
+}
+
+impl ZoneEffect {
+    fn insert_into(&self, effect: &mut EntityCommands, definitions: &Assets<EffectDefinition>) {
+        match self {
+            ZoneEffect::Components(insert) => insert(effect),
+            ZoneEffect::Definition(handle) => {
+                if let Some(definition) = definitions.get(handle) {
+                    definition.insert_into(effect);
+                }
+            }
+        }
+    }
+}
+
+/// Determines which characters are affected by [`EffectZone`] relative to the zone owner.
+/// Characters without a team are allies only to themselves.
+#[derive(Clone, Copy)]
+pub(crate) enum ZoneFilter {
+    All,
+    Allies,
+    Enemies,
+}
+
+impl ZoneFilter {
+    pub(crate) fn matches(self, ally: bool) -> bool {
+        match self {
+            ZoneFilter::All => true,
+            ZoneFilter::Allies => ally,
+            ZoneFilter::Enemies => !ally,
+        }
+    }
+}
+
+/// Zone that applied the effect.
+#[derive(Component)]
+struct ZoneSource(Entity);
+
+#[derive(Bundle)]
+pub(crate) struct EffectZoneBundle {
+    name: Name,
+    effect_zone: EffectZone,
+    sensor: Sensor,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+    colliding_entities: CollidingEntities,
+    active_events: ActiveEvents,
+    ingame_only: InGameOnly,
+
+    #[bundle]
+    transform: TransformBundle,
+}
+
+impl EffectZoneBundle {
+    /// Creates a new [`EffectZoneBundle`] with `collider` shape at `translation`.
+    pub(crate) fn new(
+        name: &'static str,
+        effect_zone: EffectZone,
+        collider: Collider,
+        translation: Vec3,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            effect_zone,
+            sensor: Sensor,
+            collider,
+            collision_groups: CollisionGroups {
+                memberships: CollisionMask::ZONE.bits(),
+                filters: CollisionMask::CHARACTER.bits(),
+            },
+            colliding_entities: CollidingEntities::default(),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            ingame_only: InGameOnly,
+            transform: TransformBundle::from_transform(Transform::from_translation(translation)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::scene::ScenePlugin;
+
+    use super::*;
+    use crate::core::{
+        effect::effect_definition::EffectDefinitionPlugin, headless::HeadlessRenderPlugin,
+        hero::LocalHeroBundle,
+    };
+
+    #[test]
+    fn zone_applies_and_removes_effect() {
+        let mut app = App::new();
+        app.add_plugin(TestEffectZonePlugin);
+
+        app.world
+            .spawn()
+            .insert_bundle(dummy_zone_bundle(ZoneFilter::All));
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        let (effect, target) = app
+            .world
+            .query_filtered::<(Entity, &EffectTarget), With<DummyEffect>>()
+            .iter(&app.world)
+            .next()
+            .expect("Effect should be applied to the character inside the zone");
+        assert_eq!(
+            target.0, character,
+            "Effect should target the character inside the zone"
+        );
+
+        app.world
+            .get_mut::<Transform>(character)
+            .unwrap()
+            .translation = Vec3::X * 100.0;
+
+        app.update();
+        app.update();
+        app.update();
+
+        assert!(
+            app.world.entity(effect).contains::<Dispelled>(),
+            "Effect should be dispelled after leaving the zone"
+        );
+    }
+
+    #[test]
+    fn zone_team_filter() {
+        let mut app = App::new();
+        app.add_plugin(TestEffectZonePlugin);
+
+        let owner = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert(Team(0))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(dummy_zone_bundle(ZoneFilter::Enemies))
+            .insert(Owner(owner));
+        let ally = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Team(0))
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Team(1))
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        let targets: Vec<_> = app
+            .world
+            .query_filtered::<&EffectTarget, With<DummyEffect>>()
+            .iter(&app.world)
+            .map(|target| target.0)
+            .collect();
+        assert!(
+            targets.contains(&enemy),
+            "Effect should be applied to the enemy"
+        );
+        assert!(
+            !targets.contains(&ally),
+            "Effect shouldn't be applied to the ally"
+        );
+    }
+
+    #[test]
+    fn zone_despawn_dispells_effects() {
+        let mut app = App::new();
+        app.add_plugin(TestEffectZonePlugin);
+
+        let zone = app
+            .world
+            .spawn()
+            .insert_bundle(dummy_zone_bundle(ZoneFilter::All))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default());
+
+        app.update();
+        app.update();
+        app.update();
+
+        app.world.despawn(zone);
+
+        app.update();
+
+        let dispelled = app
+            .world
+            .query_filtered::<(), (With<DummyEffect>, With<Dispelled>)>()
+            .iter(&app.world)
+            .count();
+        assert_eq!(dispelled, 1, "Effect should be dispelled with its zone");
+    }
+
+    struct TestEffectZonePlugin;
+
+    impl Plugin for TestEffectZonePlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(TransformPlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(EffectDefinitionPlugin)
+                .add_plugin(EffectZonePlugin);
+        }
+    }
+
+    fn dummy_zone_bundle(filter: ZoneFilter) -> EffectZoneBundle {
+        EffectZoneBundle::new(
+            "Dummy zone",
+            EffectZone::new(
+                |effect| {
+                    effect.insert(DummyEffect);
+                },
+                filter,
+            ),
+            Collider::cuboid(1.0, 1.0, 1.0),
+            Vec3::ZERO,
+        )
+    }
+
+    #[derive(Bundle)]
+    struct DummyCharacterBundle {
+        health: Health,
+        transform: Transform,
+
+        #[bundle]
+        local_hero_bundle: LocalHeroBundle,
+    }
+
+    impl Default for DummyCharacterBundle {
+        fn default() -> Self {
+            Self {
+                health: Health::default(),
+                transform: Transform::default(),
+                local_hero_bundle: LocalHeroBundle::default(),
+            }
+        }
+    }
+
+    #[derive(Component)]
+    struct DummyEffect;
+}
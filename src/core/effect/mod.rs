@@ -17,8 +17,12 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
+pub(super) mod effect_definition;
+pub(super) mod effect_zone;
 pub(super) mod modifier_effect;
 pub(super) mod periodic_effect;
+pub(super) mod stacking;
+pub(super) mod status_effect;
 
 use bevy::prelude::*;
 use derive_more::From;
@@ -27,10 +31,16 @@ use iyes_loopless::prelude::*;
 use super::{
     game_state::GameState,
     health::Death,
-    hero::{DamageModifier, HealingModifier, SpeedModifier},
+    hero::{DamageModifier, DamageTakenModifier, HealingModifier, SpeedModifier},
+    pool::{Energy, Mana, UltimateCharge},
+    simulation::{SimulationAppExt, SimulationTime},
 };
+use effect_definition::EffectDefinitionPlugin;
+use effect_zone::EffectZonePlugin;
 use modifier_effect::ModifierEffectPlugin;
-use periodic_effect::PeriodicEffectPlugin;
+use periodic_effect::{PeriodicEffectPlugin, PeriodicPoolPlugin};
+use stacking::StackingPlugin;
+use status_effect::{Disarmed, Rooted, Silenced, StatusEffectPlugin, Stunned};
 
 pub(super) struct EffectPlugin;
 
@@ -38,11 +48,23 @@ impl Plugin for EffectPlugin {
     fn build(&self, app: &mut App) {
         app.add_plugin(ModifierEffectPlugin::<SpeedModifier>::default())
             .add_plugin(ModifierEffectPlugin::<DamageModifier>::default())
+            .add_plugin(ModifierEffectPlugin::<DamageTakenModifier>::default())
             .add_plugin(ModifierEffectPlugin::<HealingModifier>::default())
+            .add_plugin(StatusEffectPlugin::<Stunned>::default())
+            .add_plugin(StatusEffectPlugin::<Rooted>::default())
+            .add_plugin(StatusEffectPlugin::<Silenced>::default())
+            .add_plugin(StatusEffectPlugin::<Disarmed>::default())
             .add_plugin(PeriodicEffectPlugin)
-            .add_system(Self::dispell_on_death_system.run_in_state(GameState::InGame))
-            .add_system(Self::timer_system.run_in_state(GameState::InGame))
-            .add_system(Self::despawn_system.run_in_state(GameState::InGame));
+            .add_plugin(PeriodicPoolPlugin::<Mana>::default())
+            .add_plugin(PeriodicPoolPlugin::<Energy>::default())
+            .add_plugin(PeriodicPoolPlugin::<UltimateCharge>::default())
+            .add_plugin(StackingPlugin)
+            .add_plugin(EffectZonePlugin)
+            .add_plugin(EffectDefinitionPlugin)
+            .add_tick_system(Self::dispell_on_death_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::dispell_orphaned_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::timer_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::despawn_system.run_in_state(GameState::InGame));
     }
 }
 
@@ -67,9 +89,22 @@ impl EffectPlugin {
         }
     }
 
+    /// Dispells effects whose target no longer exists
+    fn dispell_orphaned_system(
+        mut commands: Commands,
+        effects: Query<(Entity, &EffectTarget), Without<Dispelled>>,
+        entities: Query<()>,
+    ) {
+        for (effect, target) in effects.iter() {
+            if entities.get(target.0).is_err() {
+                commands.entity(effect).insert(Dispelled);
+            }
+        }
+    }
+
     fn timer_system(
         mut commands: Commands,
-        time: Res<Time>,
+        time: Res<SimulationTime>,
         mut effects: Query<(Entity, &mut EffectTimer)>,
     ) {
         for (effect, mut timer) in effects.iter_mut() {
@@ -114,6 +149,27 @@ mod tests {
         );
     }
 
+    #[test]
+    fn effects_cleanup_on_target_despawn() {
+        let mut app = App::new();
+        app.add_plugin(TestEffectPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app.world.spawn().insert(EffectTarget(character)).id();
+
+        app.update();
+
+        app.world.despawn(character);
+
+        app.update();
+        app.update();
+
+        assert!(
+            app.world.get_entity(effect).is_none(),
+            "Effect should be removed with its target"
+        );
+    }
+
     #[test]
     fn effect_expires() {
         let mut app = App::new();
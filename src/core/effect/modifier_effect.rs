@@ -17,51 +17,80 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use iyes_loopless::prelude::*;
-use std::{
-    marker::PhantomData,
-    ops::{AddAssign, SubAssign},
-};
+use std::marker::PhantomData;
 
 use super::{Dispelled, EffectTarget};
-use crate::core::game_state::GameState;
+use crate::core::{game_state::GameState, simulation::SimulationAppExt};
 
+/// Recomputes modifier `T` on characters from active effects.
+///
+/// Effect's `T` value is added to the base value ([`Default`] value of `T`)
+/// and the sum is multiplied by all effect's [`Multiplier<T>`] values.
 #[derive(Default)]
 pub(super) struct ModifierEffectPlugin<T> {
     effect: PhantomData<T>,
 }
 
-impl<T: Component + AddAssign + SubAssign + Copy> Plugin for ModifierEffectPlugin<T> {
+impl<T: Component + Copy + Default + From<f32>> Plugin for ModifierEffectPlugin<T>
+where
+    f32: From<T>,
+{
     fn build(&self, app: &mut App) {
-        app.add_system(Self::apply_modifier_system.run_in_state(GameState::InGame))
-            .add_system(Self::remove_modifier_system.run_in_state(GameState::InGame));
+        app.add_post_tick_system(Self::recompute_system.run_in_state(GameState::InGame));
     }
 }
 
-impl<T: Component + AddAssign + SubAssign + Copy> ModifierEffectPlugin<T> {
-    fn apply_modifier_system(
-        added_effects: Query<(&EffectTarget, &T), Added<EffectTarget>>,
-        mut characters: Query<&mut T, Without<EffectTarget>>,
+impl<T: Component + Copy + Default + From<f32>> ModifierEffectPlugin<T>
+where
+    f32: From<T>,
+{
+    /// Runs after tick systems to see effects spawned or dispelled during the tick.
+    fn recompute_system(
+        effects: Query<(&EffectTarget, Option<&T>, Option<&Multiplier<T>>), Without<Dispelled>>,
+        mut characters: Query<(Entity, &mut T), Without<EffectTarget>>,
     ) {
-        for (target, effect_modifier) in added_effects.iter() {
-            *characters.get_mut(target.0).unwrap() += *effect_modifier;
+        let mut modifiers = HashMap::<Entity, (f32, f32)>::default();
+        for (target, addend, multiplier) in effects.iter() {
+            let (sum, product) = modifiers.entry(target.0).or_insert((0.0, 1.0));
+            if let Some(&addend) = addend {
+                *sum += f32::from(addend);
+            }
+            if let Some(multiplier) = multiplier {
+                *product *= multiplier.value;
+            }
+        }
+
+        for (character, mut modifier) in characters.iter_mut() {
+            let (sum, product) = modifiers.get(&character).copied().unwrap_or((0.0, 1.0));
+            let value = (f32::from(T::default()) + sum) * product;
+            if f32::from(*modifier) != value {
+                *modifier = value.into();
+            }
         }
     }
+}
 
-    fn remove_modifier_system(
-        dispelled_effects: Query<(&EffectTarget, &T), Added<Dispelled>>,
-        mut characters: Query<&mut T, Without<EffectTarget>>,
-    ) {
-        for (target, effect_modifier) in dispelled_effects.iter() {
-            *characters.get_mut(target.0).unwrap() -= *effect_modifier;
+/// Multiplies modifier `T` of the effect target.
+#[derive(Component)]
+pub(crate) struct Multiplier<T> {
+    value: f32,
+    modifier: PhantomData<T>,
+}
+
+impl<T> From<f32> for Multiplier<T> {
+    fn from(value: f32) -> Self {
+        Self {
+            value,
+            modifier: PhantomData,
         }
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use derive_more::{AddAssign, From, SubAssign};
+    use derive_more::{From, Into};
 
     use super::*;
     use crate::core::game_state::GameState;
@@ -100,6 +129,46 @@ mod tests {
         );
     }
 
+    #[test]
+    fn modifiers_combine() {
+        let mut app = App::new();
+        app.add_plugin(TestModifierEffectPlugin);
+
+        const MODIFIER_VALUE: f32 = 0.5;
+        const MULTIPLIER_VALUE: f32 = 2.0;
+        let player = app.world.spawn().insert(DummyModifier::default()).id();
+        let multiplier_effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyMultiplierBundle {
+                target: player.into(),
+                multiplier: MULTIPLIER_VALUE.into(),
+            })
+            .id();
+        app.world.spawn().insert_bundle(DummyModifierBundle {
+            target: player.into(),
+            modifier: MODIFIER_VALUE.into(),
+        });
+
+        app.update();
+
+        assert_eq!(
+            app.world.entity(player).get::<DummyModifier>().unwrap().0,
+            (DummyModifier::default().0 + MODIFIER_VALUE) * MULTIPLIER_VALUE,
+            "Additive modifiers should be applied before multiplicative"
+        );
+
+        app.world.despawn(multiplier_effect);
+
+        app.update();
+
+        assert_eq!(
+            app.world.entity(player).get::<DummyModifier>().unwrap().0,
+            DummyModifier::default().0 + MODIFIER_VALUE,
+            "Player's modifier value should be recomputed after effect despawn"
+        );
+    }
+
     struct TestModifierEffectPlugin;
 
     impl Plugin for TestModifierEffectPlugin {
@@ -116,7 +185,13 @@ mod tests {
         modifier: DummyModifier,
     }
 
-    #[derive(Component, Clone, Copy, SubAssign, AddAssign, From)]
+    #[derive(Bundle)]
+    struct DummyMultiplierBundle {
+        target: EffectTarget,
+        multiplier: Multiplier<DummyModifier>,
+    }
+
+    #[derive(Component, Clone, Copy, From, Into)]
     struct DummyModifier(f32);
 
     impl Default for DummyModifier {
@@ -20,65 +20,170 @@
 use bevy::prelude::*;
 use derive_more::From;
 use iyes_loopless::prelude::*;
+use std::marker::PhantomData;
 
 use super::EffectTarget;
-use crate::core::{game_state::GameState, health::HealthChanged, Owner};
+use crate::core::{
+    game_state::GameState,
+    health::HealthChanged,
+    hero::{DamageModifier, HealingModifier},
+    pool::Pool,
+    simulation::{SimulationAppExt, SimulationTime},
+    Owner,
+};
 
 pub(super) struct PeriodicEffectPlugin;
 
 impl Plugin for PeriodicEffectPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::update_health_system.run_in_state(GameState::InGame))
-            .add_system(Self::periodic_timer_system.run_in_state(GameState::InGame));
+        app.add_tick_system(
+            Self::owner_scaling_system
+                .run_in_state(GameState::InGame)
+                .before(Self::update_health_system),
+        )
+        .add_tick_system(
+            Self::update_health_system
+                .run_in_state(GameState::InGame)
+                .after(Self::periodic_timer_system),
+        )
+        .add_tick_system(Self::periodic_timer_system.run_in_state(GameState::InGame));
     }
 }
 
 impl PeriodicEffectPlugin {
+    /// Scales health change by the owner's modifiers at the moment of the effect application.
+    fn owner_scaling_system(
+        mut effects: Query<(&Owner, &mut PeriodicHealthChange), Added<OwnerScaling>>,
+        owners: Query<(&DamageModifier, &HealingModifier)>,
+    ) {
+        for (owner, mut health_change) in effects.iter_mut() {
+            if let Ok((damage_modifier, healing_modifier)) = owners.get(owner.0) {
+                let modifier = if health_change.0 < 0 {
+                    damage_modifier.0
+                } else {
+                    healing_modifier.0
+                };
+                health_change.0 = (health_change.0 as f32 * modifier) as i32;
+            }
+        }
+    }
+
     fn update_health_system(
         mut health_events: EventWriter<HealthChanged>,
         mut effects: Query<(
-            &Owner,
+            Option<&Owner>,
             &EffectTarget,
             &PeriodicHealthChange,
             &PeriodicEffectTimer,
+            Option<&OwnerScaling>,
         )>,
     ) {
-        for (instigator, target, delta, timer) in effects.iter_mut() {
+        for (owner, target, delta, timer, owner_scaling) in effects.iter_mut() {
             if timer.just_finished() {
                 health_events.send(HealthChanged {
-                    instigator: instigator.0,
+                    instigator: owner.map(|owner| owner.0),
                     target: target.0,
                     delta: delta.0,
+                    modified: owner_scaling.is_some(),
+                    environment: false,
                 })
             }
         }
     }
 
-    fn periodic_timer_system(time: Res<Time>, mut effects: Query<&mut PeriodicEffectTimer>) {
+    fn periodic_timer_system(
+        time: Res<SimulationTime>,
+        mut effects: Query<&mut PeriodicEffectTimer>,
+    ) {
         for mut timer in effects.iter_mut() {
             timer.tick(time.delta());
         }
     }
 }
 
+/// Applies [`PeriodicPoolChange<T>`] effects.
+#[derive(Default)]
+pub(super) struct PeriodicPoolPlugin<T> {
+    pool: PhantomData<T>,
+}
+
+impl<T: Pool> Plugin for PeriodicPoolPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(
+            Self::update_pool_system
+                .run_in_state(GameState::InGame)
+                .after(PeriodicEffectPlugin::periodic_timer_system),
+        );
+    }
+}
+
+impl<T: Pool> PeriodicPoolPlugin<T> {
+    fn update_pool_system(
+        effects: Query<(&EffectTarget, &PeriodicPoolChange<T>, &PeriodicEffectTimer)>,
+        mut targets: Query<&mut T, Without<EffectTarget>>,
+    ) {
+        for (target, pool_change, timer) in effects.iter() {
+            if timer.just_finished() {
+                if let Ok(mut pool) = targets.get_mut(target.0) {
+                    pool.change(pool_change.delta as f32);
+                }
+            }
+        }
+    }
+}
+
 #[derive(Component, Deref, DerefMut)]
 pub(crate) struct PeriodicEffectTimer(Timer);
 
+impl PeriodicEffectTimer {
+    /// Creates a timer that ticks every `interval` seconds.
+    /// If `tick_on_apply` is set, the first tick happens right after the effect application.
+    pub(crate) fn new(interval: f32, tick_on_apply: bool) -> Self {
+        let mut timer = Timer::from_seconds(interval, true);
+        if tick_on_apply {
+            timer.set_elapsed(timer.duration());
+        }
+        Self(timer)
+    }
+}
+
 impl Default for PeriodicEffectTimer {
     fn default() -> Self {
-        Self(Timer::from_seconds(1.0, true))
+        Self::new(1.0, false)
     }
 }
 
 #[derive(Component, From)]
 pub(crate) struct PeriodicHealthChange(i32);
 
+/// Scales [`PeriodicHealthChange`] by the owner's modifiers at the moment of the application
+/// instead of the moment of each tick.
+#[derive(Component)]
+pub(crate) struct OwnerScaling;
+
+/// Periodically changes pool `T` of the effect target.
+#[derive(Component)]
+pub(crate) struct PeriodicPoolChange<T> {
+    delta: i32,
+    pool: PhantomData<T>,
+}
+
+impl<T> From<i32> for PeriodicPoolChange<T> {
+    fn from(delta: i32) -> Self {
+        Self {
+            delta,
+            pool: PhantomData,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::ecs::event::Events;
     use std::time::Duration;
 
     use super::*;
+    use crate::core::pool::PoolValue;
 
     #[test]
     fn timer_ticks() {
@@ -129,7 +234,8 @@ mod tests {
             .next()
             .expect("Health change event should be triggered");
         assert_eq!(
-            event.instigator, instigator,
+            event.instigator,
+            Some(instigator),
             "Event instigator should be equal to effect owner"
         );
         assert_eq!(
@@ -139,6 +245,89 @@ mod tests {
         );
     }
 
+    #[test]
+    fn tick_on_apply() {
+        let mut app = App::new();
+        app.add_plugin(TestPeriodicEffectPlugin);
+
+        let target = app.world.spawn().id();
+        let instigator = app.world.spawn().id();
+
+        let mut heal_bundle = DummyPeriodicHealBundle::new(instigator.into(), target.into());
+        heal_bundle.periodic_timer = PeriodicEffectTimer::new(1.0, true);
+        app.world.spawn().insert_bundle(heal_bundle);
+
+        app.update();
+
+        let health_events = app.world.resource::<Events<HealthChanged>>();
+        assert_eq!(
+            health_events.len(),
+            1,
+            "Health change event should be triggered right after application"
+        );
+    }
+
+    #[test]
+    fn owner_scaling() {
+        let mut app = App::new();
+        app.add_plugin(TestPeriodicEffectPlugin);
+
+        const MODIFIER: f32 = 2.0;
+        let target = app.world.spawn().id();
+        let instigator = app
+            .world
+            .spawn()
+            .insert(DamageModifier::default())
+            .insert(HealingModifier(MODIFIER))
+            .id();
+
+        let mut heal_bundle = DummyPeriodicHealBundle::new(instigator.into(), target.into());
+        heal_bundle.periodic_timer = PeriodicEffectTimer::new(1.0, true);
+        app.world
+            .spawn()
+            .insert_bundle(heal_bundle)
+            .insert(OwnerScaling);
+
+        app.update();
+
+        // Modifier changes after application shouldn't affect the effect
+        app.world.get_mut::<HealingModifier>(instigator).unwrap().0 = 1.0;
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let event = health_events
+            .drain()
+            .next()
+            .expect("Health change event should be triggered");
+        assert_eq!(
+            event.delta,
+            (DummyPeriodicHealBundle::DELTA as f32 * MODIFIER) as i32,
+            "Event delta should be scaled by the owner's modifier"
+        );
+        assert!(event.modified, "Event should be marked as modified");
+    }
+
+    #[test]
+    fn periodic_pool_change() {
+        let mut app = App::new();
+        app.add_plugin(TestPeriodicEffectPlugin);
+
+        const DELTA: i32 = 5;
+        let target = app.world.spawn().insert(DummyPool::default()).id();
+        app.world.spawn().insert_bundle(DummyPoolChangeBundle {
+            pool_change: DELTA.into(),
+            periodic_timer: PeriodicEffectTimer::new(1.0, true),
+            target: target.into(),
+        });
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<DummyPool>(target).unwrap().current,
+            DELTA as f32,
+            "Target pool should be changed by the effect"
+        );
+    }
+
     struct TestPeriodicEffectPlugin;
 
     impl Plugin for TestPeriodicEffectPlugin {
@@ -146,7 +335,8 @@ mod tests {
             app.add_loopless_state(GameState::InGame)
                 .add_event::<HealthChanged>()
                 .add_plugins(MinimalPlugins)
-                .add_plugin(PeriodicEffectPlugin);
+                .add_plugin(PeriodicEffectPlugin)
+                .add_plugin(PeriodicPoolPlugin::<DummyPool>::default());
         }
     }
 
@@ -170,4 +360,26 @@ mod tests {
             }
         }
     }
+
+    #[derive(Bundle)]
+    struct DummyPoolChangeBundle {
+        pool_change: PeriodicPoolChange<DummyPool>,
+        periodic_timer: PeriodicEffectTimer,
+        target: EffectTarget,
+    }
+
+    #[derive(Component, Deref, DerefMut)]
+    struct DummyPool(PoolValue);
+
+    impl Default for DummyPool {
+        fn default() -> Self {
+            Self(PoolValue {
+                current: 0.0,
+                max: 100.0,
+                regeneration: 0.0,
+            })
+        }
+    }
+
+    impl Pool for DummyPool {}
 }
@@ -0,0 +1,460 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+use derive_more::{Display, From};
+use iyes_loopless::prelude::*;
+use serde::Deserialize;
+use std::{cmp::Reverse, time::Duration};
+
+use super::{Dispelled, EffectTarget, EffectTimer};
+use crate::core::{game_state::GameState, simulation::SimulationAppExt};
+
+pub(super) struct StackingPlugin;
+
+impl Plugin for StackingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(Self::stacking_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl StackingPlugin {
+    /// Resolves newly applied effects against already active effects of the same kind on the same target.
+    fn stacking_system(
+        mut commands: Commands,
+        added_effects: Query<
+            (
+                Entity,
+                &EffectKind,
+                &EffectTarget,
+                Option<&EffectStrength>,
+                Option<&Stacking>,
+            ),
+            Added<EffectKind>,
+        >,
+        mut active_effects: Query<
+            (
+                Entity,
+                &EffectKind,
+                &EffectTarget,
+                Option<&EffectStrength>,
+                Option<&mut EffectTimer>,
+            ),
+            Without<Dispelled>,
+        >,
+    ) {
+        let added: HashSet<_> = added_effects.iter().map(|(effect, ..)| effect).collect();
+        // Effects added earlier in this tick are treated as active for the following ones
+        let mut resolved = Vec::new();
+        let mut dispelled = HashSet::default();
+        for (effect, &kind, target, strength, stacking) in added_effects.iter() {
+            let mut same_effects = Vec::new();
+            for (active_effect, &active_kind, active_target, active_strength, timer) in
+                active_effects.iter()
+            {
+                if active_kind != kind
+                    || active_target.0 != target.0
+                    || dispelled.contains(&active_effect)
+                    || (added.contains(&active_effect) && !resolved.contains(&active_effect))
+                {
+                    continue;
+                }
+
+                let elapsed = timer.map(|timer| timer.elapsed()).unwrap_or_default();
+                let order = resolved.iter().position(|&entity| entity == active_effect);
+                same_effects.push((
+                    active_effect,
+                    active_strength.copied().unwrap_or_default(),
+                    elapsed,
+                    order,
+                ));
+            }
+            // Oldest first, effects from previous ticks before effects added in this tick
+            same_effects.sort_by_key(|&(_, _, elapsed, order)| (Reverse(elapsed), order));
+
+            let mut dispel = |entity| {
+                commands.entity(entity).insert(Dispelled);
+                dispelled.insert(entity);
+            };
+            match stacking.copied().unwrap_or_else(|| kind.stacking()) {
+                Stacking::Independent => (),
+                Stacking::Refresh => {
+                    if !same_effects.is_empty() {
+                        for &(active_effect, ..) in &same_effects {
+                            if let Ok((.., Some(mut timer))) = active_effects.get_mut(active_effect)
+                            {
+                                timer.reset();
+                            }
+                        }
+                        dispel(effect);
+                    }
+                }
+                Stacking::Stack { max } => {
+                    let excess = (same_effects.len() + 1).saturating_sub(max);
+                    for &(active_effect, ..) in same_effects.iter().take(excess) {
+                        dispel(active_effect);
+                    }
+                }
+                Stacking::ReplaceIfStronger => {
+                    for &(active_effect, active_strength, ..) in &same_effects {
+                        if strength.copied().unwrap_or_default() >= active_strength {
+                            dispel(active_effect);
+                        } else {
+                            dispel(effect);
+                            break;
+                        }
+                    }
+                }
+            }
+            resolved.push(effect);
+        }
+    }
+}
+
+/// Identifies the effect to resolve stacking with other effects of the same kind.
+#[derive(Component, Clone, Copy, PartialEq, Debug, Display, Deserialize)]
+pub(crate) enum EffectKind {
+    Healing,
+    Rage,
+    Speed,
+    Cleanse,
+    Chill,
+    Freeze,
+    Protection,
+    Stun,
+}
+
+impl EffectKind {
+    /// Default stacking policy, could be overridden by inserting [`Stacking`] into the effect.
+    fn stacking(self) -> Stacking {
+        match self {
+            EffectKind::Healing => Stacking::Stack { max: 3 },
+            EffectKind::Rage => Stacking::Refresh,
+            EffectKind::Speed => Stacking::ReplaceIfStronger,
+            EffectKind::Cleanse => Stacking::Independent,
+            EffectKind::Chill | EffectKind::Freeze | EffectKind::Protection | EffectKind::Stun => {
+                Stacking::Refresh
+            }
+        }
+    }
+}
+
+/// Policy for applying an effect while an effect of the same kind is already active on the target.
+#[derive(Component, Clone, Copy, Deserialize)]
+pub(crate) enum Stacking {
+    /// Effects are applied independently.
+    Independent,
+    /// New effect is ignored, but the duration of the active effect is refreshed.
+    Refresh,
+    /// New effect is applied as an additional stack with its own duration.
+    /// The oldest stack is removed when exceeding `max`.
+    Stack { max: usize },
+    /// New effect replaces the active effect if its [`EffectStrength`] is greater or equal.
+    ReplaceIfStronger,
+}
+
+/// Strength used to compare effects with [`Stacking::ReplaceIfStronger`] policy.
+#[derive(Component, Clone, Copy, Default, PartialEq, PartialOrd, From)]
+pub(crate) struct EffectStrength(pub(crate) f32);
+
+/// Provides information about active effects on targets.
+#[derive(SystemParam)]
+pub(crate) struct ActiveEffects<'w, 's> {
+    effects: Query<
+        'w,
+        's,
+        (
+            &'static EffectKind,
+            &'static EffectTarget,
+            Option<&'static EffectTimer>,
+        ),
+        Without<Dispelled>,
+    >,
+}
+
+impl ActiveEffects<'_, '_> {
+    /// Returns active effects on `target` grouped by their kind.
+    pub(crate) fn on(&self, target: Entity) -> Vec<ActiveEffect> {
+        let mut active_effects = Vec::<ActiveEffect>::new();
+        for (&kind, effect_target, timer) in self.effects.iter() {
+            if effect_target.0 != target {
+                continue;
+            }
+
+            let remaining = timer.map(|timer| timer.duration() - timer.elapsed());
+            match active_effects.iter_mut().find(|effect| effect.kind == kind) {
+                Some(active_effect) => {
+                    active_effect.stacks += 1;
+                    active_effect.remaining = active_effect.remaining.max(remaining);
+                }
+                None => active_effects.push(ActiveEffect {
+                    kind,
+                    stacks: 1,
+                    remaining,
+                }),
+            }
+        }
+
+        active_effects
+    }
+}
+
+/// Information about effects of the same kind on a target.
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub(crate) struct ActiveEffect {
+    pub(crate) kind: EffectKind,
+    pub(crate) stacks: usize,
+    /// Remaining time of the longest effect or [`None`] if the effect is permanent.
+    pub(crate) remaining: Option<Duration>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    #[test]
+    fn refresh() {
+        let mut app = App::new();
+        app.add_plugin(TestStackingPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyEffectBundle::new(EffectKind::Rage, character))
+            .id();
+
+        app.update();
+
+        let mut timer = app.world.get_mut::<EffectTimer>(effect).unwrap();
+        timer.tick(Duration::from_secs(1));
+
+        let new_effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyEffectBundle::new(EffectKind::Rage, character))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.entity(new_effect).contains::<Dispelled>(),
+            "New effect should be ignored"
+        );
+        let timer = app.world.get::<EffectTimer>(effect).unwrap();
+        assert!(
+            timer.elapsed() < Duration::from_secs(1),
+            "Active effect should be refreshed"
+        );
+    }
+
+    #[test]
+    fn stack() {
+        let mut app = App::new();
+        app.add_plugin(TestStackingPlugin);
+
+        let character = app.world.spawn().id();
+        let max = match EffectKind::Healing.stacking() {
+            Stacking::Stack { max } => max,
+            _ => panic!("Healing should be stackable"),
+        };
+        let effects: Vec<_> = (0..=max)
+            .map(|_| {
+                let effect = app
+                    .world
+                    .spawn()
+                    .insert_bundle(DummyEffectBundle::new(EffectKind::Healing, character))
+                    .id();
+                app.update();
+                effect
+            })
+            .collect();
+
+        let dispelled_count = effects
+            .iter()
+            .filter(|&&effect| app.world.entity(effect).contains::<Dispelled>())
+            .count();
+        assert_eq!(
+            dispelled_count, 1,
+            "A stack should be removed after exceeding the maximum"
+        );
+
+        let mut system_state: SystemState<ActiveEffects> = SystemState::new(&mut app.world);
+        let active_effects = system_state.get(&app.world);
+        assert_eq!(
+            active_effects.on(character),
+            [ActiveEffect {
+                kind: EffectKind::Healing,
+                stacks: max,
+                remaining: Some(DummyEffectBundle::DURATION),
+            }],
+        );
+    }
+
+    #[test]
+    fn stack_removes_oldest() {
+        let mut app = App::new();
+        app.add_plugin(TestStackingPlugin);
+
+        let character = app.world.spawn().id();
+        let max = match EffectKind::Healing.stacking() {
+            Stacking::Stack { max } => max,
+            _ => panic!("Healing should be stackable"),
+        };
+        let effects: Vec<_> = (0..=max)
+            .map(|_| {
+                let effect = app
+                    .world
+                    .spawn()
+                    .insert_bundle(DummyEffectBundle::new(EffectKind::Healing, character))
+                    .id();
+                app.update();
+                for mut timer in app
+                    .world
+                    .query::<&mut EffectTimer>()
+                    .iter_mut(&mut app.world)
+                {
+                    timer.tick(Duration::from_millis(100));
+                }
+                effect
+            })
+            .collect();
+
+        assert!(
+            app.world.entity(effects[0]).contains::<Dispelled>(),
+            "The oldest stack should be removed after exceeding the maximum"
+        );
+        for &effect in &effects[1..] {
+            assert!(
+                !app.world.entity(effect).contains::<Dispelled>(),
+                "Newer stacks should stay active"
+            );
+        }
+    }
+
+    #[test]
+    fn stack_in_same_tick() {
+        let mut app = App::new();
+        app.add_plugin(TestStackingPlugin);
+
+        let character = app.world.spawn().id();
+        let max = match EffectKind::Healing.stacking() {
+            Stacking::Stack { max } => max,
+            _ => panic!("Healing should be stackable"),
+        };
+        let effects: Vec<_> = (0..=max)
+            .map(|_| {
+                app.world
+                    .spawn()
+                    .insert_bundle(DummyEffectBundle::new(EffectKind::Healing, character))
+                    .id()
+            })
+            .collect();
+
+        app.update();
+
+        let dispelled_count = effects
+            .iter()
+            .filter(|&&effect| app.world.entity(effect).contains::<Dispelled>())
+            .count();
+        assert_eq!(
+            dispelled_count, 1,
+            "Effects applied in the same tick should be limited by the maximum"
+        );
+    }
+
+    #[test]
+    fn replace_if_stronger() {
+        let mut app = App::new();
+        app.add_plugin(TestStackingPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyEffectBundle::new(EffectKind::Speed, character))
+            .insert(EffectStrength(0.5))
+            .id();
+
+        app.update();
+
+        let weaker_effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyEffectBundle::new(EffectKind::Speed, character))
+            .insert(EffectStrength(0.2))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.entity(weaker_effect).contains::<Dispelled>(),
+            "Weaker effect should be ignored"
+        );
+
+        let stronger_effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyEffectBundle::new(EffectKind::Speed, character))
+            .insert(EffectStrength(0.7))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.entity(effect).contains::<Dispelled>(),
+            "Active effect should be replaced by the stronger one"
+        );
+        assert!(
+            !app.world.entity(stronger_effect).contains::<Dispelled>(),
+            "Stronger effect should be applied"
+        );
+    }
+
+    struct TestStackingPlugin;
+
+    impl Plugin for TestStackingPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugins(MinimalPlugins)
+                .add_plugin(StackingPlugin);
+        }
+    }
+
+    #[derive(Bundle)]
+    struct DummyEffectBundle {
+        kind: EffectKind,
+        timer: EffectTimer,
+        target: EffectTarget,
+    }
+
+    impl DummyEffectBundle {
+        const DURATION: Duration = Duration::from_secs(4);
+
+        fn new(kind: EffectKind, target: Entity) -> Self {
+            Self {
+                kind,
+                timer: Timer::new(Self::DURATION, false).into(),
+                target: target.into(),
+            }
+        }
+    }
+}
@@ -0,0 +1,404 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{prelude::*, utils::HashSet};
+use iyes_loopless::prelude::*;
+use std::marker::PhantomData;
+
+use super::{stacking::EffectKind, Dispelled, EffectTarget, EffectTimer};
+use crate::core::{
+    game_state::GameState,
+    simulation::{SimulationAppExt, SimulationTime},
+};
+
+/// Duration multipliers for each consecutive status of the same type.
+/// The target becomes immune to the status after the last one.
+const DIMINISHING_FACTORS: [f32; 3] = [1.0, 0.5, 0.25];
+const DIMINISHING_RESET_SECS: f32 = 15.0;
+
+/// Inserts status `T` to characters while they are affected by at least one effect with `T`.
+#[derive(Default)]
+pub(super) struct StatusEffectPlugin<T> {
+    status: PhantomData<T>,
+}
+
+impl<T: Component + Default> Plugin for StatusEffectPlugin<T> {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(Self::diminishing_reset_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::cleanse_system.run_in_state(GameState::InGame))
+            .add_post_tick_system(
+                Self::diminishing_returns_system
+                    .chain(Self::status_system)
+                    .run_in_state(GameState::InGame),
+            );
+    }
+}
+
+impl<T: Component + Default> StatusEffectPlugin<T> {
+    /// Shortens consecutive statuses of the same type on the same target.
+    /// Runs after tick systems to skip effects dispelled by stacking.
+    /// Returns effects the target is immune to, they are dispelled and shouldn't apply the status.
+    fn diminishing_returns_system(
+        mut commands: Commands,
+        mut added_effects: Query<
+            (Entity, &EffectTarget, &mut EffectTimer),
+            (Added<T>, Without<Dispelled>),
+        >,
+        mut characters: Query<Option<&mut DiminishingReturns<T>>, Without<EffectTarget>>,
+    ) -> HashSet<Entity> {
+        let mut immune_effects = HashSet::default();
+        for (effect, target, mut timer) in added_effects.iter_mut() {
+            let diminishing_returns = match characters.get_mut(target.0) {
+                Ok(diminishing_returns) => diminishing_returns,
+                Err(_) => continue,
+            };
+
+            let applied_count = match diminishing_returns {
+                Some(mut diminishing_returns) => {
+                    diminishing_returns.timer.reset();
+                    diminishing_returns.applied_count += 1;
+                    diminishing_returns.applied_count
+                }
+                None => {
+                    commands
+                        .entity(target.0)
+                        .insert(DiminishingReturns::<T>::default());
+                    0
+                }
+            };
+
+            match DIMINISHING_FACTORS.get(applied_count) {
+                Some(factor) => {
+                    let duration = timer.duration().mul_f32(*factor);
+                    timer.set_duration(duration);
+                }
+                None => {
+                    commands.entity(effect).insert(Dispelled);
+                    immune_effects.insert(effect);
+                }
+            }
+        }
+
+        immune_effects
+    }
+
+    fn diminishing_reset_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut characters: Query<(Entity, &mut DiminishingReturns<T>)>,
+    ) {
+        for (character, mut diminishing_returns) in characters.iter_mut() {
+            diminishing_returns.timer.tick(time.delta());
+            if diminishing_returns.timer.finished() {
+                commands.entity(character).remove::<DiminishingReturns<T>>();
+            }
+        }
+    }
+
+    fn cleanse_system(
+        mut commands: Commands,
+        cleanses: Query<&EffectTarget, Added<Cleanse>>,
+        effects: Query<(Entity, &EffectTarget), With<T>>,
+    ) {
+        for cleanse_target in cleanses.iter() {
+            for (effect, target) in effects.iter() {
+                if target.0 == cleanse_target.0 {
+                    commands.entity(effect).insert(Dispelled);
+                }
+            }
+        }
+    }
+
+    /// Synchronizes status `T` on characters with active effects.
+    /// Runs after tick systems to see effects spawned or dispelled during the tick.
+    fn status_system(
+        In(immune_effects): In<HashSet<Entity>>,
+        mut commands: Commands,
+        effects: Query<(Entity, &EffectTarget), (With<T>, Without<Dispelled>)>,
+        characters: Query<Option<&T>, Without<EffectTarget>>,
+        affected_characters: Query<Entity, (With<T>, Without<EffectTarget>)>,
+    ) {
+        let targets: HashSet<_> = effects
+            .iter()
+            .filter(|(effect, _)| !immune_effects.contains(effect))
+            .map(|(_, target)| target.0)
+            .collect();
+        for character in affected_characters.iter() {
+            if !targets.contains(&character) {
+                commands.entity(character).remove::<T>();
+            }
+        }
+
+        for &target in targets.iter() {
+            if let Ok(None) = characters.get(target) {
+                commands.entity(target).insert(T::default());
+            }
+        }
+    }
+}
+
+/// Prevents movement and activation of all abilities.
+#[derive(Component, Default)]
+pub(crate) struct Stunned;
+
+/// Prevents movement.
+#[derive(Component, Default)]
+pub(crate) struct Rooted;
+
+/// Prevents activation of all abilities except the base attack.
+#[derive(Component, Default)]
+pub(crate) struct Silenced;
+
+/// Prevents activation of the base attack.
+#[derive(Component, Default)]
+pub(crate) struct Disarmed;
+
+/// Removes all statuses from the target.
+#[derive(Component)]
+struct Cleanse;
+
+/// A component bundle for an instant effect that removes all statuses from the target.
+#[derive(Bundle)]
+pub(crate) struct CleanseBundle {
+    name: Name,
+    kind: EffectKind,
+    cleanse: Cleanse,
+    target: EffectTarget,
+    dispelled: Dispelled,
+}
+
+impl CleanseBundle {
+    pub(crate) fn new(target: Entity) -> Self {
+        Self {
+            name: "Cleanse Effect".into(),
+            kind: EffectKind::Cleanse,
+            cleanse: Cleanse,
+            target: target.into(),
+            dispelled: Dispelled, // Instant effect, removed right after applying
+        }
+    }
+}
+
+/// Tracks recently applied statuses of type `T` on the character.
+#[derive(Component)]
+struct DiminishingReturns<T> {
+    applied_count: usize,
+    timer: Timer,
+    status: PhantomData<T>,
+}
+
+impl<T> Default for DiminishingReturns<T> {
+    fn default() -> Self {
+        Self {
+            applied_count: 0,
+            timer: Timer::from_seconds(DIMINISHING_RESET_SECS, false),
+            status: PhantomData,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::core::effect::stacking::StackingPlugin;
+
+    #[test]
+    fn status_applies_and_expires() {
+        let mut app = App::new();
+        app.add_plugin(TestStatusEffectPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyStatusBundle::new(character.into()))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.entity(character).contains::<DummyStatus>(),
+            "Status should be applied to the target"
+        );
+
+        app.world.despawn(effect);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<DummyStatus>(),
+            "Status should be removed after effect expiration"
+        );
+    }
+
+    #[test]
+    fn diminishing_returns() {
+        let mut app = App::new();
+        app.add_plugin(TestStatusEffectPlugin);
+
+        let character = app.world.spawn().id();
+        for factor in DIMINISHING_FACTORS {
+            let effect = app
+                .world
+                .spawn()
+                .insert_bundle(DummyStatusBundle::new(character.into()))
+                .id();
+
+            app.update();
+
+            let timer = app.world.get::<EffectTimer>(effect).unwrap();
+            assert_eq!(
+                timer.duration(),
+                DummyStatusBundle::DURATION.mul_f32(factor),
+                "Status duration should be multiplied by {factor}"
+            );
+
+            app.world.despawn(effect);
+        }
+
+        app.world
+            .spawn()
+            .insert_bundle(DummyStatusBundle::new(character.into()));
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<DummyStatus>(),
+            "Target should become immune to the status"
+        );
+
+        let mut diminishing_returns = app
+            .world
+            .get_mut::<DiminishingReturns<DummyStatus>>(character)
+            .unwrap();
+        let duration = diminishing_returns.timer.duration();
+        diminishing_returns.timer.tick(duration);
+
+        app.update();
+
+        assert!(
+            !app.world
+                .entity(character)
+                .contains::<DiminishingReturns<DummyStatus>>(),
+            "Diminishing returns should be reset after some time"
+        );
+    }
+
+    #[test]
+    fn diminishing_returns_ignore_refreshed() {
+        let mut app = App::new();
+        app.add_plugin(TestStatusEffectPlugin)
+            .add_plugin(StackingPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyStatusBundle::new(character.into()))
+            .insert(EffectKind::Stun)
+            .id();
+
+        app.update();
+
+        app.world
+            .spawn()
+            .insert_bundle(DummyStatusBundle::new(character.into()))
+            .insert(EffectKind::Stun);
+
+        app.update();
+
+        let diminishing_returns = app
+            .world
+            .get::<DiminishingReturns<DummyStatus>>(character)
+            .unwrap();
+        assert_eq!(
+            diminishing_returns.applied_count, 0,
+            "Refreshed stun shouldn't be counted"
+        );
+        assert!(
+            !app.world.entity(effect).contains::<Dispelled>(),
+            "Original stun should stay active"
+        );
+        assert!(app.world.entity(character).contains::<DummyStatus>());
+    }
+
+    #[test]
+    fn cleanse() {
+        let mut app = App::new();
+        app.add_plugin(TestStatusEffectPlugin);
+
+        let character = app.world.spawn().id();
+        let effect = app
+            .world
+            .spawn()
+            .insert_bundle(DummyStatusBundle::new(character.into()))
+            .id();
+
+        app.update();
+
+        app.world
+            .spawn()
+            .insert_bundle(CleanseBundle::new(character));
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<DummyStatus>(),
+            "Status should be removed by cleanse"
+        );
+        assert!(
+            app.world.entity(effect).contains::<Dispelled>(),
+            "Status effect should be dispelled by cleanse"
+        );
+    }
+
+    struct TestStatusEffectPlugin;
+
+    impl Plugin for TestStatusEffectPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugins(MinimalPlugins)
+                .add_plugin(StatusEffectPlugin::<DummyStatus>::default());
+        }
+    }
+
+    #[derive(Bundle)]
+    struct DummyStatusBundle {
+        status: DummyStatus,
+        timer: EffectTimer,
+        target: EffectTarget,
+    }
+
+    impl DummyStatusBundle {
+        const DURATION: Duration = Duration::from_secs(4);
+
+        fn new(target: EffectTarget) -> Self {
+            Self {
+                status: DummyStatus,
+                timer: Timer::new(Self::DURATION, false).into(),
+                target,
+            }
+        }
+    }
+
+    #[derive(Component, Default)]
+    struct DummyStatus;
+}
@@ -22,17 +22,21 @@ use iyes_loopless::prelude::*;
 
 use super::{
     game_state::GameState,
-    hero::{DamageModifier, HealingModifier},
+    hero::{DamageModifier, DamageTakenModifier, HealingModifier},
     player::{Damage, Deaths, Healing, Kills},
+    pool::UltimateCharge,
+    simulation::{SimulationAppExt, SimulationTime},
 };
 
 pub(super) struct HealthPlugin;
 
 impl Plugin for HealthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<HealthChanged>()
-            .add_system(Self::healing_system.run_in_state(GameState::InGame))
-            .add_system(Self::damage_system.run_in_state(GameState::InGame));
+        app.add_tick_event::<HealthChanged>()
+            .add_tick_system(Self::healing_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::damage_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::shield_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::last_attacker_system.run_in_state(GameState::InGame));
     }
 }
 
@@ -40,51 +44,144 @@ impl HealthPlugin {
     fn healing_system(
         mut health_events: EventReader<HealthChanged>,
         mut targets: Query<&mut Health>,
-        mut instigators: Query<(&mut Healing, &HealingModifier)>,
+        mut instigators: Query<(
+            Entity,
+            &mut Healing,
+            &HealingModifier,
+            Option<&mut UltimateCharge>,
+        )>,
     ) {
         for event in health_events.iter().filter(|event| event.delta > 0) {
-            let mut health = targets.get_mut(event.target).unwrap();
+            let mut health = match targets.get_mut(event.target) {
+                Ok(health) => health,
+                Err(_) => continue,
+            };
             if health.current == 0 {
                 continue;
             }
 
-            let (mut healing, healing_modifier) = instigators.get_mut(event.instigator).unwrap();
+            let instigator = event
+                .instigator
+                .and_then(|instigator| instigators.get_mut(instigator).ok());
+            let healing_modifier = match &instigator {
+                Some((_, _, healing_modifier, _)) if !event.modified => healing_modifier.0,
+                _ => 1.0,
+            };
+
             let delta = health
                 .missing()
-                .min((event.delta as f32 * healing_modifier.0) as u32);
+                .min((event.delta as f32 * healing_modifier) as u32);
             health.current += delta;
-            healing.0 += delta;
+
+            if let Some((instigator, mut healing, _, ultimate_charge)) = instigator {
+                healing.0 += delta;
+                if instigator != event.target {
+                    charge_ultimate(ultimate_charge, delta);
+                }
+            }
         }
     }
 
     fn damage_system(
         mut commands: Commands,
         mut health_events: EventReader<HealthChanged>,
-        mut targets: Query<(&mut Health, &mut Deaths)>,
-        mut instigators: Query<(&mut Damage, &mut Kills, &DamageModifier)>,
+        mut targets: Query<(
+            &mut Health,
+            &mut Deaths,
+            Option<&DamageTakenModifier>,
+            Option<&mut Shield>,
+            Option<&Invulnerable>,
+        )>,
+        mut instigators: Query<(
+            Entity,
+            &mut Damage,
+            &mut Kills,
+            &DamageModifier,
+            Option<&mut UltimateCharge>,
+        )>,
     ) {
         for event in health_events.iter().filter(|event| event.delta < 0) {
-            let (mut health, mut deaths) = targets.get_mut(event.target).unwrap();
-            let (mut damage, mut kills, damage_modifier) =
-                instigators.get_mut(event.instigator).unwrap();
+            let (mut health, mut deaths, damage_taken_modifier, shield, invulnerable) =
+                match targets.get_mut(event.target) {
+                    Ok(components) => components,
+                    Err(_) => continue,
+                };
+            // Already dead characters may still receive damage sent before their death was applied
+            if health.current == 0 || (invulnerable.is_some() && !event.environment) {
+                continue;
+            }
 
-            let delta = health
-                .current
-                .min((event.delta.abs() as f32 * damage_modifier.0) as u32);
+            let instigator = event
+                .instigator
+                .and_then(|instigator| instigators.get_mut(instigator).ok());
+            let damage_modifier = match &instigator {
+                Some((_, _, _, damage_modifier, _)) if !event.modified => damage_modifier.0,
+                _ => 1.0,
+            };
+
+            let damage_taken_modifier = damage_taken_modifier.map_or(1.0, |modifier| modifier.0);
+
+            let mut amount =
+                (event.delta.abs() as f32 * damage_modifier * damage_taken_modifier) as u32;
+            if let Some(mut shield) = shield {
+                let absorbed = shield.amount.min(amount);
+                shield.amount -= absorbed;
+                amount -= absorbed;
+                if shield.amount == 0 {
+                    commands.entity(event.target).remove::<Shield>();
+                }
+            }
+
+            let delta = health.current.min(amount);
             health.current -= delta;
             if health.current == 0 {
                 deaths.0 += 1;
                 commands.entity(event.target).insert(Death);
             }
 
-            if event.target != event.instigator {
-                damage.0 += delta;
-                if health.current == 0 {
-                    kills.0 += 1;
+            if let Some((instigator, mut damage, mut kills, _, ultimate_charge)) = instigator {
+                if instigator != event.target {
+                    damage.0 += delta;
+                    charge_ultimate(ultimate_charge, delta);
+                    if health.current == 0 {
+                        kills.0 += 1;
+                    }
+                    // Environment damage shouldn't extend the credit window
+                    if !event.environment {
+                        commands
+                            .entity(event.target)
+                            .insert(LastAttacker::new(instigator));
+                    }
                 }
             }
         }
     }
+
+    fn shield_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut shields: Query<(Entity, &mut Shield)>,
+    ) {
+        for (character, mut shield) in shields.iter_mut() {
+            shield.timer.tick(time.delta());
+            if shield.timer.finished() {
+                commands.entity(character).remove::<Shield>();
+            }
+        }
+    }
+
+    fn last_attacker_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut last_attackers: Query<(Entity, &mut LastAttacker)>,
+    ) {
+        for (character, mut last_attacker) in last_attackers.iter_mut() {
+            last_attacker.timer.tick(time.delta());
+            if last_attacker.timer.finished() {
+                commands.entity(character).remove::<LastAttacker>();
+            }
+        }
+    }
 }
 
 #[derive(Component)]
@@ -109,17 +206,88 @@ impl Health {
 }
 
 pub(super) struct HealthChanged {
-    pub(super) instigator: Entity,
+    /// Character that caused the change, [`None`] if caused by the environment.
+    pub(super) instigator: Option<Entity>,
     pub(super) target: Entity,
     pub(super) delta: i32,
+    /// Whether `delta` already includes the instigator's modifiers.
+    pub(super) modified: bool,
+    /// Whether the change was caused by the environment and only credited to the instigator.
+    pub(super) environment: bool,
+}
+
+impl HealthChanged {
+    /// Creates a change caused by the environment.
+    /// Credited to the last attacker and not affected by its modifiers.
+    pub(super) fn environment(
+        target: Entity,
+        delta: i32,
+        last_attacker: Option<&LastAttacker>,
+    ) -> Self {
+        Self {
+            instigator: last_attacker.map(|last_attacker| last_attacker.instigator),
+            target,
+            delta,
+            modified: true,
+            environment: true,
+        }
+    }
 }
 
 #[derive(Component)]
 pub(super) struct Death;
 
+/// Absorbs incoming damage before health until depleted or expired.
+#[derive(Component)]
+pub(crate) struct Shield {
+    pub(crate) amount: u32,
+    timer: Timer,
+}
+
+impl Shield {
+    pub(super) fn new(amount: u32, secs: f32) -> Self {
+        Self {
+            amount,
+            timer: Timer::from_seconds(secs, false),
+        }
+    }
+}
+
+/// Ignores all incoming damage except from the environment.
+#[derive(Component)]
+pub(super) struct Invulnerable;
+
+/// Stores the character that recently damaged or pushed the entity.
+/// Used to credit kills caused by the environment, like falling out of the map.
+#[derive(Component)]
+pub(super) struct LastAttacker {
+    pub(super) instigator: Entity,
+    timer: Timer,
+}
+
+impl LastAttacker {
+    /// How long the attacker is remembered
+    const DURATION_SECS: f32 = 5.0;
+
+    pub(super) fn new(instigator: Entity) -> Self {
+        Self {
+            instigator,
+            timer: Timer::from_seconds(Self::DURATION_SECS, false),
+        }
+    }
+}
+
+/// Charges ultimate for `amount` of damage or healing done.
+fn charge_ultimate(ultimate_charge: Option<Mut<UltimateCharge>>, amount: u32) {
+    if let Some(mut ultimate_charge) = ultimate_charge {
+        ultimate_charge.change(amount as f32 * UltimateCharge::PER_POINT);
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use bevy::ecs::event::Events;
+    use std::time::Duration;
 
     use super::*;
     use crate::core::player::PlayerBundle;
@@ -155,9 +323,11 @@ mod tests {
 
             let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
             health_events.send(HealthChanged {
-                instigator,
+                instigator: Some(instigator),
                 target,
                 delta,
+                modified: false,
+                environment: false,
             });
 
             app.update();
@@ -176,6 +346,44 @@ mod tests {
         }
     }
 
+    #[test]
+    fn modified_change_ignores_modifiers() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        const DELTA: i32 = -10;
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let instigator = app
+            .world
+            .spawn()
+            .insert_bundle(PlayerBundle::default())
+            .insert(DamageModifier(2.0))
+            .id();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(HealthChanged {
+            instigator: Some(instigator),
+            target,
+            delta: DELTA,
+            modified: true,
+            environment: false,
+        });
+
+        app.update();
+
+        let health = app.world.get::<Health>(target).unwrap();
+        assert_eq!(
+            health.current,
+            Health::default().max - DELTA.unsigned_abs(),
+            "Instigator's modifier shouldn't be applied to already modified change"
+        );
+    }
+
     #[test]
     fn damaging() {
         let mut app = App::new();
@@ -207,9 +415,11 @@ mod tests {
 
             let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
             health_events.send(HealthChanged {
-                instigator,
+                instigator: Some(instigator),
                 target,
                 delta,
+                modified: false,
+                environment: false,
             });
 
             app.update();
@@ -250,6 +460,130 @@ mod tests {
         }
     }
 
+    #[test]
+    fn damage_taken_modifier() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert(DamageTakenModifier(0.5))
+            .insert_bundle(PlayerBundle::default())
+            .id();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(HealthChanged {
+            instigator: None,
+            target,
+            delta: -20,
+            modified: true,
+            environment: false,
+        });
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Health>(target).unwrap().current,
+            Health::default().max - 10,
+            "Damage taken should be reduced even if the damage already includes instigator's modifiers"
+        );
+    }
+
+    #[test]
+    fn invulnerable_ignores_damage() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert(Invulnerable)
+            .insert_bundle(PlayerBundle::default())
+            .id();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(HealthChanged {
+            instigator: None,
+            target,
+            delta: -20,
+            modified: false,
+            environment: false,
+        });
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Health>(target).unwrap().current,
+            Health::default().max,
+            "Invulnerable target shouldn't take damage"
+        );
+    }
+
+    #[test]
+    fn shield_absorbs_damage() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert(Shield::new(15, 10.0))
+            .insert_bundle(PlayerBundle::default())
+            .id();
+
+        for (delta, expected_health, expected_shield) in [
+            (-10, Health::default().max, Some(5)),
+            (-10, Health::default().max - 5, None),
+        ] {
+            let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+            health_events.send(HealthChanged {
+                instigator: None,
+                target,
+                delta,
+                modified: false,
+                environment: false,
+            });
+
+            app.update();
+
+            assert_eq!(
+                app.world.get::<Health>(target).unwrap().current,
+                expected_health,
+                "Only damage exceeding the shield should reduce health"
+            );
+            assert_eq!(
+                app.world.get::<Shield>(target).map(|shield| shield.amount),
+                expected_shield,
+                "Depleted shield should be removed"
+            );
+        }
+    }
+
+    #[test]
+    fn shield_expires() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let character = app.world.spawn().insert(Shield::new(10, 1.0)).id();
+
+        app.update();
+
+        let mut shield = app.world.get_mut::<Shield>(character).unwrap();
+        let duration = shield.timer.duration();
+        shield.timer.tick(duration);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(character).contains::<Shield>(),
+            "Shield should be removed after expiration"
+        );
+    }
+
     #[test]
     fn self_damaging() {
         let mut app = App::new();
@@ -266,9 +600,11 @@ mod tests {
         let delta = -(Health::default().max as i32);
         let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
         health_events.send(HealthChanged {
-            instigator: target,
+            instigator: Some(target),
             target,
             delta,
+            modified: false,
+            environment: false,
         });
 
         app.update();
@@ -297,6 +633,239 @@ mod tests {
             .expect("Target should have a Death component");
     }
 
+    #[test]
+    fn environment_damaging() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let despawned_instigator = app.world.spawn().id();
+        app.world.despawn(despawned_instigator);
+
+        const DAMAGE: u32 = 10;
+        for instigator in [None, Some(despawned_instigator)] {
+            let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+            health_events.send(HealthChanged {
+                instigator,
+                target,
+                delta: -(DAMAGE as i32),
+                modified: false,
+                environment: false,
+            });
+        }
+
+        app.update();
+
+        let health = app.world.get::<Health>(target).unwrap();
+        assert_eq!(
+            health.current,
+            Health::default().max - DAMAGE * 2,
+            "Damage without instigator should be applied without modifiers"
+        );
+        assert!(
+            !app.world.entity(target).contains::<LastAttacker>(),
+            "Environment shouldn't be remembered as attacker"
+        );
+    }
+
+    #[test]
+    fn environment_damage_credited() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let instigator = app
+            .world
+            .spawn()
+            .insert(DamageModifier(2.0))
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let mut last_attacker = LastAttacker::new(instigator);
+        last_attacker
+            .timer
+            .tick(Duration::from_secs_f32(LastAttacker::DURATION_SECS / 2.0));
+        let elapsed = last_attacker.timer.elapsed();
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert_bundle(PlayerBundle::default())
+            .insert(last_attacker)
+            .id();
+
+        const DAMAGE: u32 = 10;
+        let last_attacker = app.world.get::<LastAttacker>(target);
+        let event = HealthChanged::environment(target, -(DAMAGE as i32), last_attacker);
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(event);
+
+        app.update();
+
+        let health = app.world.get::<Health>(target).unwrap();
+        assert_eq!(
+            health.current,
+            Health::default().max - DAMAGE,
+            "Environment damage shouldn't be affected by the credited attacker modifiers"
+        );
+        assert_eq!(
+            app.world.get::<Damage>(instigator).unwrap().0,
+            DAMAGE,
+            "Environment damage should be credited to the last attacker"
+        );
+        assert!(
+            app.world
+                .get::<LastAttacker>(target)
+                .unwrap()
+                .timer
+                .elapsed()
+                > elapsed,
+            "Environment damage shouldn't refresh the last attacker"
+        );
+    }
+
+    #[test]
+    fn missing_target_ignored() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let instigator = app
+            .world
+            .spawn()
+            .insert(DamageModifier::default())
+            .insert(HealingModifier::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let target = app.world.spawn().id();
+        app.world.despawn(target);
+
+        for delta in [-10, 10] {
+            let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+            health_events.send(HealthChanged {
+                instigator: Some(instigator),
+                target,
+                delta,
+                modified: false,
+                environment: false,
+            });
+        }
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Damage>(instigator).unwrap().0,
+            0,
+            "Damage to missing target shouldn't be counted"
+        );
+        assert_eq!(
+            app.world.get::<Healing>(instigator).unwrap().0,
+            0,
+            "Healing of missing target shouldn't be counted"
+        );
+    }
+
+    #[test]
+    fn ultimate_charges() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        const DAMAGE: u32 = 10;
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let instigator = app
+            .world
+            .spawn()
+            .insert(DamageModifier::default())
+            .insert(UltimateCharge::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(HealthChanged {
+            instigator: Some(instigator),
+            target,
+            delta: -(DAMAGE as i32),
+            modified: false,
+            environment: false,
+        });
+
+        app.update();
+
+        let ultimate_charge = app.world.get::<UltimateCharge>(instigator).unwrap();
+        assert_eq!(
+            ultimate_charge.current,
+            DAMAGE as f32 * UltimateCharge::PER_POINT,
+            "Ultimate should be charged by damage done"
+        );
+    }
+
+    #[test]
+    fn last_attacker_remembered() {
+        let mut app = App::new();
+        app.add_plugin(TestHealthPlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Health::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+        let instigator = app
+            .world
+            .spawn()
+            .insert(DamageModifier::default())
+            .insert_bundle(PlayerBundle::default())
+            .id();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        health_events.send(HealthChanged {
+            instigator: Some(instigator),
+            target,
+            delta: -1,
+            modified: false,
+            environment: false,
+        });
+
+        app.update();
+
+        let last_attacker = app
+            .world
+            .get::<LastAttacker>(target)
+            .expect("Damaged character should remember its attacker");
+        assert_eq!(
+            last_attacker.instigator, instigator,
+            "Last attacker should be the damage instigator"
+        );
+        assert_eq!(
+            HealthChanged::environment(target, -1, Some(last_attacker)).instigator,
+            Some(instigator),
+            "Environment damage should be credited to the last attacker"
+        );
+
+        let mut last_attacker = app.world.get_mut::<LastAttacker>(target).unwrap();
+        let duration = last_attacker.timer.duration();
+        last_attacker.timer.tick(duration);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(target).contains::<LastAttacker>(),
+            "Last attacker should be forgotten after some time"
+        );
+        assert_eq!(
+            HealthChanged::environment(target, -1, None).instigator,
+            None,
+            "Environment damage shouldn't have instigator without last attacker"
+        );
+    }
+
     struct TestHealthPlugin;
 
     impl Plugin for TestHealthPlugin {
@@ -0,0 +1,670 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use super::{
+    character_direction, characters_in_radius,
+    hero_definition::{AbilityArchetypes, AbilityParams},
+    Aim,
+};
+use crate::core::{
+    ability::{Activator, HoldPower},
+    effect::{effect_definition::EffectDefinition, effect_zone::ZoneFilter, EffectTarget},
+    game_state::GameState,
+    health::{Health, HealthChanged, LastAttacker, Shield},
+    hitscan::{Hitscan, PerceivedTick},
+    player::Team,
+    simulation::{SimulationAppExt, SimulationTime},
+    Owner,
+};
+
+const CLEAVE_DAMAGE: f32 = 25.0;
+const CLEAVE_RANGE: f32 = 3.0;
+const CLEAVE_ANGLE_DEGREES: f32 = 90.0;
+const CHARGE_SPEED: f32 = 30.0;
+const CHARGE_SECS: f32 = 0.3;
+const SHOCKWAVE_RADIUS: f32 = 6.0;
+const SHOCKWAVE_IMPULSE: f32 = 40.0;
+const SHOCKWAVE_DAMAGE: f32 = 10.0;
+/// Fraction of damage and impulse of Shockwave released without holding.
+const SHOCKWAVE_MIN_POWER: f32 = 0.5;
+const IRON_SKIN_AMOUNT: f32 = 50.0;
+const IRON_SKIN_SECS: f32 = 5.0;
+const EARTHSHATTER_RADIUS: f32 = 8.0;
+const EARTHSHATTER_DAMAGE: f32 = 40.0;
+const STUN_EFFECT_PATH: &str = "effects/stun.effect.json";
+
+pub(super) struct BrakkPlugin;
+
+impl Plugin for BrakkPlugin {
+    fn build(&self, app: &mut App) {
+        let mut archetypes = app
+            .world
+            .get_resource_or_insert_with(AbilityArchetypes::default);
+        archetypes.register("cleave", |ability, params| {
+            ability.insert(CleaveAbility::new(params));
+        });
+        archetypes.register("charge", |ability, params| {
+            ability.insert(ChargeAbility::new(params));
+        });
+        archetypes.register("shockwave", |ability, params| {
+            ability.insert(ShockwaveAbility::new(params));
+        });
+        archetypes.register("iron_skin", |ability, params| {
+            ability.insert(IronSkinAbility::new(params));
+        });
+        archetypes.register("earthshatter", |ability, params| {
+            ability.insert(EarthshatterAbility::new(params));
+        });
+
+        app.add_startup_system(Self::load_effects_system)
+            .add_tick_system(Self::cleave_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::charge_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::charging_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::shockwave_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::iron_skin_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::earthshatter_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl BrakkPlugin {
+    fn load_effects_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(BrakkEffects {
+            stun: asset_server.load(STUN_EFFECT_PATH),
+        });
+    }
+
+    /// Damages enemies within a short arc in front of the caster.
+    /// Uses the tick perceived by the caster's client to compensate latency.
+    fn cleave_system(
+        mut commands: Commands,
+        mut health_events: EventWriter<HealthChanged>,
+        hitscan: Hitscan,
+        abilities: Query<(Entity, &Activator, &CleaveAbility)>,
+        casters: Query<(&Transform, &Aim, Option<&PerceivedTick>)>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, cleave) in abilities.iter() {
+            let caster = activator.0;
+            let (transform, aim, perceived_tick) = casters.get(caster).unwrap();
+            let caster_team = characters.get(caster).ok().flatten().copied();
+
+            for character in hitscan.cast_arc(
+                transform.translation,
+                character_direction(aim.direction),
+                cleave.range,
+                cleave.angle,
+                perceived_tick.map(|tick| tick.0),
+                caster,
+            ) {
+                let team = match characters.get(character) {
+                    Ok(team) => team.copied(),
+                    Err(_) => continue,
+                };
+                if caster_team.is_some() && team == caster_team {
+                    continue;
+                }
+
+                health_events.send(HealthChanged {
+                    instigator: Some(caster),
+                    target: character,
+                    delta: -(cleave.damage as i32),
+                    modified: false,
+                    environment: false,
+                });
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    fn charge_system(
+        mut commands: Commands,
+        abilities: Query<(Entity, &Activator, &ChargeAbility)>,
+        casters: Query<&Aim>,
+    ) {
+        for (ability, activator, charge) in abilities.iter() {
+            let aim = casters.get(activator.0).unwrap();
+            commands.entity(activator.0).insert(Charging {
+                velocity: character_direction(aim.direction) * charge.speed,
+                timer: Timer::from_seconds(charge.secs, false),
+            });
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Keeps horizontal velocity of charging characters.
+    fn charging_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut characters: Query<(Entity, &mut Charging, &mut Velocity)>,
+    ) {
+        for (character, mut charging, mut velocity) in characters.iter_mut() {
+            charging.timer.tick(time.delta());
+            if charging.timer.finished() {
+                commands.entity(character).remove::<Charging>();
+                continue;
+            }
+
+            velocity.linvel.x = charging.velocity.x;
+            velocity.linvel.z = charging.velocity.z;
+        }
+    }
+
+    /// Damages and knocks back enemies around the caster, scaled by [`HoldPower`] if held.
+    /// Knocked back characters remember the caster to credit environment kills.
+    fn shockwave_system(
+        mut commands: Commands,
+        mut health_events: EventWriter<HealthChanged>,
+        rapier_ctx: Res<RapierContext>,
+        abilities: Query<(Entity, &Activator, &ShockwaveAbility, Option<&HoldPower>)>,
+        transforms: Query<&Transform>,
+        mut velocities: Query<&mut Velocity>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, shockwave, hold_power) in abilities.iter() {
+            let power = hold_power.map_or(1.0, |hold_power| {
+                SHOCKWAVE_MIN_POWER + (1.0 - SHOCKWAVE_MIN_POWER) * hold_power.0
+            });
+            let caster = activator.0;
+            let translation = transforms.get(caster).unwrap().translation;
+            for character in characters_in_radius(
+                &rapier_ctx,
+                &characters,
+                caster,
+                translation,
+                shockwave.radius,
+                ZoneFilter::Enemies,
+            ) {
+                if let Ok(mut velocity) = velocities.get_mut(character) {
+                    let offset = transforms.get(character).unwrap().translation - translation;
+                    velocity.linvel += character_direction(offset) * shockwave.impulse * power;
+                }
+                commands.entity(character).insert(LastAttacker::new(caster));
+                health_events.send(HealthChanged {
+                    instigator: Some(caster),
+                    target: character,
+                    delta: -((shockwave.damage as f32 * power) as i32),
+                    modified: false,
+                    environment: false,
+                });
+            }
+
+            commands
+                .entity(ability)
+                .remove::<Activator>()
+                .remove::<HoldPower>();
+        }
+    }
+
+    fn iron_skin_system(
+        mut commands: Commands,
+        abilities: Query<(Entity, &Activator, &IronSkinAbility)>,
+    ) {
+        for (ability, activator, iron_skin) in abilities.iter() {
+            commands
+                .entity(activator.0)
+                .insert(Shield::new(iron_skin.amount, iron_skin.secs));
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Damages and stuns enemies around the caster.
+    fn earthshatter_system(
+        mut commands: Commands,
+        mut health_events: EventWriter<HealthChanged>,
+        rapier_ctx: Res<RapierContext>,
+        effects: Res<BrakkEffects>,
+        definitions: Res<Assets<EffectDefinition>>,
+        abilities: Query<(Entity, &Activator, &EarthshatterAbility)>,
+        casters: Query<&Transform>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, earthshatter) in abilities.iter() {
+            let caster = activator.0;
+            let translation = casters.get(caster).unwrap().translation;
+            for character in characters_in_radius(
+                &rapier_ctx,
+                &characters,
+                caster,
+                translation,
+                earthshatter.radius,
+                ZoneFilter::Enemies,
+            ) {
+                health_events.send(HealthChanged {
+                    instigator: Some(caster),
+                    target: character,
+                    delta: -(earthshatter.damage as i32),
+                    modified: false,
+                    environment: false,
+                });
+                if let Some(stun) = definitions.get(&effects.stun) {
+                    let mut effect = commands.spawn();
+                    effect.insert(EffectTarget(character)).insert(Owner(caster));
+                    stun.insert_into(&mut effect);
+                }
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+}
+
+#[derive(Component)]
+struct CleaveAbility {
+    damage: u32,
+    range: f32,
+    /// Arc angle in radians.
+    angle: f32,
+}
+
+impl CleaveAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            damage: params.get("damage", CLEAVE_DAMAGE) as u32,
+            range: params.get("range", CLEAVE_RANGE),
+            angle: params.get("angle", CLEAVE_ANGLE_DEGREES).to_radians(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct ChargeAbility {
+    speed: f32,
+    secs: f32,
+}
+
+impl ChargeAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            speed: params.get("speed", CHARGE_SPEED),
+            secs: params.get("duration", CHARGE_SECS),
+        }
+    }
+}
+
+/// Indicates that the character dashes with constant horizontal velocity.
+#[derive(Component)]
+struct Charging {
+    velocity: Vec3,
+    timer: Timer,
+}
+
+#[derive(Component)]
+struct ShockwaveAbility {
+    radius: f32,
+    impulse: f32,
+    damage: u32,
+}
+
+impl ShockwaveAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", SHOCKWAVE_RADIUS),
+            impulse: params.get("impulse", SHOCKWAVE_IMPULSE),
+            damage: params.get("damage", SHOCKWAVE_DAMAGE) as u32,
+        }
+    }
+}
+
+#[derive(Component)]
+struct IronSkinAbility {
+    amount: u32,
+    secs: f32,
+}
+
+impl IronSkinAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            amount: params.get("amount", IRON_SKIN_AMOUNT) as u32,
+            secs: params.get("duration", IRON_SKIN_SECS),
+        }
+    }
+}
+
+/// Applies [`STUN_EFFECT_PATH`] effect.
+#[derive(Component)]
+struct EarthshatterAbility {
+    radius: f32,
+    damage: u32,
+}
+
+impl EarthshatterAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", EARTHSHATTER_RADIUS),
+            damage: params.get("damage", EARTHSHATTER_DAMAGE) as u32,
+        }
+    }
+}
+
+/// Effect definitions applied by Brakk's abilities.
+struct BrakkEffects {
+    stun: Handle<EffectDefinition>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, scene::ScenePlugin};
+
+    use super::*;
+    use crate::core::{
+        effect::{effect_definition::EffectDefinitionPlugin, status_effect::Stunned},
+        headless::{self, HeadlessRenderPlugin},
+        hero::LocalHeroBundle,
+    };
+
+    #[test]
+    fn cleave() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::from_translation(Vec3::X * 2.0 + Vec3::Z));
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(Vec3::X * 2.0 - Vec3::Z))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(-Vec3::X * 2.0));
+
+        app.update();
+
+        let ability = app
+            .world
+            .spawn()
+            .insert(CleaveAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster))
+            .id();
+
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let events: Vec<_> = health_events.drain().collect();
+        assert_eq!(
+            events.len(),
+            1,
+            "Only enemies in front of the caster should be hit"
+        );
+        assert_eq!(events[0].instigator, Some(caster));
+        assert_eq!(events[0].target, enemy);
+        assert_eq!(events[0].delta, -(CLEAVE_DAMAGE as i32));
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
+    }
+
+    #[test]
+    fn charge() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert(Velocity::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X + Vec3::Y, None))
+            .id();
+        app.world
+            .spawn()
+            .insert(ChargeAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(caster).unwrap();
+        assert_eq!(
+            velocity.linvel,
+            Vec3::X * CHARGE_SPEED,
+            "Caster should dash horizontally towards the aim direction"
+        );
+
+        let mut charging = app.world.get_mut::<Charging>(caster).unwrap();
+        let duration = charging.timer.duration();
+        charging.timer.set_elapsed(duration);
+
+        app.update();
+
+        assert!(
+            !app.world.entity(caster).contains::<Charging>(),
+            "Charging should be removed after the dash"
+        );
+    }
+
+    #[test]
+    fn shockwave() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        let ally = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Velocity::default())
+            .insert(Transform::from_translation(Vec3::Z * 2.0))
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Velocity::default())
+            .insert(Transform::from_translation(Vec3::X * 2.0))
+            .id();
+
+        app.update();
+
+        app.world
+            .spawn()
+            .insert(ShockwaveAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+
+        let ally_velocity = app.world.get::<Velocity>(ally).unwrap();
+        assert_eq!(
+            ally_velocity.linvel.x + ally_velocity.linvel.z,
+            0.0,
+            "Allies shouldn't be pushed"
+        );
+        let enemy_velocity = app.world.get::<Velocity>(enemy).unwrap();
+        assert!(
+            enemy_velocity.linvel.x > 0.0,
+            "Enemies should be pushed away from the caster"
+        );
+        assert_eq!(
+            app.world.get::<LastAttacker>(enemy).unwrap().instigator,
+            caster,
+            "Caster should be credited for knockback kills"
+        );
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let events: Vec<_> = health_events.drain().collect();
+        assert_eq!(events.len(), 1, "Only enemies should be damaged");
+        assert_eq!(events[0].target, enemy);
+        assert_eq!(events[0].delta, -(SHOCKWAVE_DAMAGE as i32));
+    }
+
+    #[test]
+    fn shockwave_hold_power() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Velocity::default())
+            .insert(Transform::from_translation(Vec3::X * 2.0));
+
+        app.update();
+
+        let ability = app
+            .world
+            .spawn()
+            .insert(ShockwaveAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster))
+            .insert(HoldPower(0.0))
+            .id();
+
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let event = health_events
+            .drain()
+            .next()
+            .expect("Enemy should be damaged");
+        assert_eq!(
+            event.delta,
+            -((SHOCKWAVE_DAMAGE * SHOCKWAVE_MIN_POWER) as i32),
+            "Released without holding should deal reduced damage"
+        );
+        assert!(
+            !app.world.entity(ability).contains::<HoldPower>(),
+            "Hold power should be consumed"
+        );
+    }
+
+    #[test]
+    fn iron_skin() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app.world.spawn().id();
+        app.world
+            .spawn()
+            .insert(IronSkinAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+
+        let shield = app
+            .world
+            .get::<Shield>(caster)
+            .expect("Caster should be shielded");
+        assert_eq!(shield.amount, IRON_SKIN_AMOUNT as u32);
+    }
+
+    #[test]
+    fn earthshatter() {
+        let mut app = App::new();
+        app.add_plugin(TestBrakkPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(Vec3::X * 2.0))
+            .id();
+
+        app.update();
+
+        headless::wait_for_asset_loading(&mut app, STUN_EFFECT_PATH);
+
+        app.world
+            .spawn()
+            .insert(EarthshatterAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let events: Vec<_> = health_events.drain().collect();
+        assert_eq!(events.len(), 1, "Only enemies should be damaged");
+        assert_eq!(events[0].target, enemy);
+        assert_eq!(events[0].delta, -(EARTHSHATTER_DAMAGE as i32));
+
+        let stunned: Vec<_> = app
+            .world
+            .query_filtered::<(&EffectTarget, &Owner), With<Stunned>>()
+            .iter(&app.world)
+            .map(|(target, owner)| (target.0, owner.0))
+            .collect();
+        assert_eq!(stunned, [(enemy, caster)], "Enemies should be stunned");
+    }
+
+    struct TestBrakkPlugin;
+
+    impl Plugin for TestBrakkPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_event::<HealthChanged>()
+                .add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(TransformPlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(EffectDefinitionPlugin)
+                .add_plugin(BrakkPlugin);
+        }
+    }
+}
@@ -0,0 +1,329 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{
+    asset::{AssetLoader, BoxedFuture, LoadContext, LoadedAsset},
+    ecs::system::EntityCommands,
+    prelude::*,
+    reflect::TypeUuid,
+    utils::HashMap,
+};
+use serde::Deserialize;
+
+use super::HeroKind;
+use crate::core::{
+    ability::{AbilityCharges, AbilityCost, ActivationKind, IconPath},
+    control_actions::ControlAction,
+    cooldown::Cooldown,
+    pool::{Energy, Mana},
+};
+
+const HEROES_FOLDER: &str = "heroes";
+
+/// Registers [`HeroDefinition`] assets and loads all definitions from the heroes folder.
+pub(crate) struct HeroDefinitionPlugin;
+
+impl Plugin for HeroDefinitionPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_asset::<HeroDefinition>()
+            .init_asset_loader::<HeroDefinitionLoader>()
+            .init_resource::<AbilityArchetypes>()
+            .init_resource::<HeroDefinitions>();
+    }
+}
+
+/// Hero description loaded from `*.hero.json` files.
+#[derive(Deserialize, TypeUuid)]
+#[uuid = "5b2f5e0e-8c3a-4f4d-9a43-0d8a7b1f6c21"]
+pub(crate) struct HeroDefinition {
+    /// Display name.
+    pub(crate) name: String,
+    /// Path to the hero icon.
+    pub(crate) icon: String,
+    /// Path to the hero scene, placeholder capsule is used if not specified.
+    pub(super) model: Option<String>,
+    pub(super) collider: ColliderDefinition,
+    pub(super) health: u32,
+    pub(super) speed: f32,
+    /// Additional jumps in the air.
+    #[serde(default)]
+    pub(super) air_jumps: u8,
+    /// Resource pools in addition to ultimate charge.
+    #[serde(default)]
+    pools: Vec<PoolKind>,
+    abilities: Vec<AbilityDefinition>,
+}
+
+impl HeroDefinition {
+    /// Inserts defined resource pools.
+    pub(super) fn insert_pools(&self, hero: &mut EntityCommands) {
+        for pool in &self.pools {
+            match pool {
+                PoolKind::Mana => hero.insert(Mana::default()),
+                PoolKind::Energy => hero.insert(Energy::default()),
+            };
+        }
+    }
+
+    /// Spawns defined abilities using registered archetypes.
+    /// Abilities with unknown archetypes are skipped.
+    pub(super) fn spawn_abilities(
+        &self,
+        commands: &mut Commands,
+        archetypes: &AbilityArchetypes,
+    ) -> Vec<Entity> {
+        self.abilities
+            .iter()
+            .filter_map(|ability| ability.spawn(commands, archetypes))
+            .collect()
+    }
+}
+
+/// Capsule collider size.
+#[derive(Deserialize)]
+pub(super) struct ColliderDefinition {
+    pub(super) half_height: f32,
+    pub(super) radius: f32,
+}
+
+#[derive(Deserialize)]
+enum PoolKind {
+    Mana,
+    Energy,
+}
+
+#[derive(Deserialize)]
+struct AbilityDefinition {
+    /// Name of the registered [`AbilityArchetype`].
+    archetype: String,
+    name: String,
+    icon: String,
+    action: ControlAction,
+    /// Cooldown in seconds.
+    cooldown: Option<f32>,
+    charges: Option<u8>,
+    cost: Option<AbilityCost>,
+    activation: Option<ActivationKind>,
+    #[serde(default)]
+    params: AbilityParams,
+}
+
+impl AbilityDefinition {
+    fn spawn(&self, commands: &mut Commands, archetypes: &AbilityArchetypes) -> Option<Entity> {
+        let archetype = match archetypes.0.get(self.archetype.as_str()) {
+            Some(archetype) => archetype,
+            None => {
+                error!(
+                    "Unable to spawn ability {}: archetype {} isn't registered",
+                    self.name, self.archetype
+                );
+                return None;
+            }
+        };
+
+        let mut ability = commands.spawn();
+        ability
+            .insert(Name::new(self.name.clone()))
+            .insert(IconPath::from(self.icon.clone()))
+            .insert(self.action);
+        if let Some(cooldown) = self.cooldown {
+            ability.insert(Cooldown::from_secs_f32(cooldown));
+        }
+        if let Some(charges) = self.charges {
+            ability.insert(AbilityCharges::new(charges));
+        }
+        if let Some(cost) = self.cost {
+            ability.insert(cost);
+        }
+        if let Some(activation) = self.activation {
+            ability.insert(activation);
+        }
+        archetype(&mut ability, &self.params);
+
+        Some(ability.id())
+    }
+}
+
+/// Archetype-specific ability parameters.
+#[derive(Default, Deserialize)]
+pub(super) struct AbilityParams(HashMap<String, f32>);
+
+impl AbilityParams {
+    /// Returns parameter value or `default` if it's not specified.
+    pub(super) fn get(&self, name: &str, default: f32) -> f32 {
+        self.0.get(name).copied().unwrap_or(default)
+    }
+}
+
+/// Inserts archetype-specific components into the ability.
+pub(super) type AbilityArchetype = fn(&mut EntityCommands, &AbilityParams);
+
+/// Ability archetypes registered by hero plugins, referred from definitions by name.
+#[derive(Default)]
+pub(super) struct AbilityArchetypes(HashMap<&'static str, AbilityArchetype>);
+
+impl AbilityArchetypes {
+    pub(super) fn register(&mut self, name: &'static str, archetype: AbilityArchetype) {
+        self.0.insert(name, archetype);
+    }
+}
+
+/// All available heroes.
+/// Holds handles to keep definitions loaded.
+pub(crate) struct HeroDefinitions(Vec<(HeroKind, Handle<HeroDefinition>)>);
+
+impl HeroDefinitions {
+    pub(crate) fn iter(&self) -> impl Iterator<Item = &(HeroKind, Handle<HeroDefinition>)> {
+        self.0.iter()
+    }
+}
+
+impl FromWorld for HeroDefinitions {
+    fn from_world(world: &mut World) -> Self {
+        let asset_server = world.resource::<AssetServer>();
+        let handles = asset_server
+            .load_folder(HEROES_FOLDER)
+            .unwrap_or_else(|error| {
+                error!("Unable to load hero definitions: {}", error);
+                Vec::new()
+            });
+
+        let mut definitions: Vec<_> = handles
+            .into_iter()
+            .map(|handle| {
+                let path = asset_server
+                    .get_handle_path(&handle)
+                    .expect("Loaded definition should have a path");
+                let kind = HeroKind::from(path.path().to_string_lossy().into_owned());
+                (kind, handle.typed())
+            })
+            .collect();
+        // Folder order depends on the filesystem, sort to keep random selection reproducible.
+        definitions.sort_by(|(kind, _), (other_kind, _)| kind.0.cmp(&other_kind.0));
+
+        Self(definitions)
+    }
+}
+
+#[derive(Default)]
+struct HeroDefinitionLoader;
+
+impl AssetLoader for HeroDefinitionLoader {
+    fn load<'a>(
+        &'a self,
+        bytes: &'a [u8],
+        load_context: &'a mut LoadContext,
+    ) -> BoxedFuture<'a, Result<(), anyhow::Error>> {
+        Box::pin(async move {
+            let definition: HeroDefinition = serde_json::from_slice(bytes)?;
+            load_context.set_default_asset(LoadedAsset::new(definition));
+            Ok(())
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["hero.json"]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::CommandQueue;
+
+    use super::*;
+
+    #[test]
+    fn definition_spawns_abilities() {
+        let definition: HeroDefinition = serde_json::from_str(
+            r#"{
+                "name": "Dummy Hero",
+                "icon": "dummy.png",
+                "collider": { "half_height": 0.5, "radius": 0.5 },
+                "health": 150,
+                "speed": 8.0,
+                "pools": ["Energy"],
+                "abilities": [
+                    {
+                        "archetype": "dummy",
+                        "name": "Dummy Ability",
+                        "icon": "dummy_ability.png",
+                        "action": "Ability1",
+                        "cooldown": 1.5,
+                        "charges": 2,
+                        "cost": { "Energy": 10.0 },
+                        "activation": { "Cast": { "secs": 0.5 } },
+                        "params": { "power": 3.0 }
+                    },
+                    {
+                        "archetype": "unknown",
+                        "name": "Unknown Ability",
+                        "icon": "unknown_ability.png",
+                        "action": "Ability2"
+                    }
+                ]
+            }"#,
+        )
+        .expect("Definition should be deserializable");
+
+        let mut archetypes = AbilityArchetypes::default();
+        archetypes.register("dummy", |ability, params| {
+            ability.insert(DummyPower(params.get("power", 1.0)));
+        });
+
+        let mut world = World::new();
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        let abilities = definition.spawn_abilities(&mut commands, &archetypes);
+        let mut hero = commands.spawn();
+        definition.insert_pools(&mut hero);
+        let hero = hero.id();
+        queue.apply(&mut world);
+
+        assert!(world.entity(hero).contains::<Energy>());
+        assert!(!world.entity(hero).contains::<Mana>());
+
+        assert_eq!(
+            abilities.len(),
+            1,
+            "Abilities with unknown archetypes should be skipped"
+        );
+        let ability = world.entity(abilities[0]);
+        assert_eq!(ability.get::<Name>().unwrap().as_str(), "Dummy Ability");
+        assert_eq!(ability.get::<IconPath>().unwrap().0, "dummy_ability.png");
+        assert_eq!(
+            *ability.get::<ControlAction>().unwrap(),
+            ControlAction::Ability1
+        );
+        assert_eq!(
+            ability.get::<Cooldown>().unwrap().duration().as_secs_f32(),
+            1.5
+        );
+        assert!(ability.contains::<AbilityCharges>());
+        assert!(ability.contains::<AbilityCost>());
+        assert!(ability.contains::<ActivationKind>());
+        assert_eq!(
+            ability.get::<DummyPower>().unwrap().0,
+            3.0,
+            "Archetype should receive parameters from the definition"
+        );
+    }
+
+    #[derive(Component)]
+    struct DummyPower(f32);
+}
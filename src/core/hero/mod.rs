@@ -17,22 +17,137 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
+mod brakk;
+pub(crate) mod hero_definition;
 mod north;
+mod sylva;
 
-use bevy::prelude::*;
+use bevy::{asset::LoadState, prelude::*, utils::HashSet};
 use bevy_rapier3d::prelude::*;
-use derive_more::{AddAssign, From, SubAssign};
+use derive_more::{From, Into};
+use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::*;
-use strum::{EnumIter, EnumString};
+use serde::{Deserialize, Serialize};
 
-use super::{ability::Abilities, control_actions::ControlAction, health::Health, CollisionMask};
+use super::{
+    ability::Abilities,
+    control_actions::ControlAction,
+    effect::effect_zone::ZoneFilter,
+    game_state::GameState,
+    health::Health,
+    hitscan::TransformHistory,
+    movement::{AirJumps, BaseSpeed, CharacterController, FallSpeed},
+    player::Team,
+    pool::{Energy, Mana, Stamina, UltimateCharge},
+    simulation::SimulationAppExt,
+    AssociatedAsset, CollisionMask,
+};
+use brakk::BrakkPlugin;
+use hero_definition::{AbilityArchetypes, HeroDefinition, HeroDefinitionPlugin};
 use north::NorthPlugin;
+use sylva::SylvaPlugin;
 
 pub(super) struct HeroPlugin;
 
 impl Plugin for HeroPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(NorthPlugin);
+        app.add_plugin(HeroDefinitionPlugin)
+            .add_plugin(BrakkPlugin)
+            .add_plugin(NorthPlugin)
+            .add_plugin(SylvaPlugin)
+            .add_tick_system(Self::change_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::spawn_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl HeroPlugin {
+    /// Removes abilities, pools, air jumps and model of the previous hero when [`HeroKind`] changes.
+    /// The hero will be spawned again from the new definition.
+    fn change_system(
+        mut commands: Commands,
+        heroes: Query<(Entity, &Abilities, Option<&Children>), Changed<HeroKind>>,
+    ) {
+        for (hero, abilities, children) in heroes.iter() {
+            for &ability in abilities.iter() {
+                commands.entity(ability).despawn_recursive();
+            }
+            if let Some(children) = children {
+                // Abilities are also children, but already despawned above
+                for child in children.iter().filter(|child| !abilities.contains(*child)) {
+                    commands.entity(*child).despawn_recursive();
+                }
+            }
+            commands
+                .entity(hero)
+                .remove::<Abilities>()
+                .remove::<Mana>()
+                .remove::<Energy>()
+                .remove::<AirJumps>();
+        }
+    }
+
+    /// Inserts hero components from the definition once it's loaded.
+    /// Definitions that failed to load are reported once per path.
+    #[allow(clippy::too_many_arguments)]
+    fn spawn_system(
+        mut commands: Commands,
+        mut failed_paths: Local<HashSet<String>>,
+        asset_server: Res<AssetServer>,
+        definitions: Res<Assets<HeroDefinition>>,
+        archetypes: Res<AbilityArchetypes>,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+        mut heroes: Query<(Entity, &HeroKind, &mut Health, &mut BaseSpeed), Without<Abilities>>,
+    ) {
+        for (hero, hero_kind, mut health, mut base_speed) in heroes.iter_mut() {
+            let definition = match definitions.get(hero_kind.asset_path()) {
+                Some(definition) => definition,
+                None => {
+                    if asset_server.get_load_state(hero_kind.asset_path()) == LoadState::Failed
+                        && failed_paths.insert(hero_kind.asset_path().to_string())
+                    {
+                        error!(
+                            "Unable to spawn hero: definition {} failed to load",
+                            hero_kind.asset_path()
+                        );
+                    }
+                    continue;
+                }
+            };
+
+            *health = Health {
+                current: definition.health,
+                max: definition.health,
+            };
+            *base_speed = definition.speed.into();
+
+            let abilities = definition.spawn_abilities(&mut commands, &archetypes);
+            let collider = &definition.collider;
+            let mut entity_commands = commands.entity(hero);
+            entity_commands.insert_bundle(LocalHeroBundle {
+                abilities: abilities.into(),
+                collider: Collider::capsule_y(collider.half_height, collider.radius),
+                ..Default::default()
+            });
+            definition.insert_pools(&mut entity_commands);
+            if definition.air_jumps != 0 {
+                entity_commands.insert(AirJumps::new(definition.air_jumps));
+            }
+
+            if let Some(model) = &definition.model {
+                entity_commands.with_children(|parent| {
+                    parent.spawn_scene(asset_server.load(model.as_str()));
+                });
+            } else {
+                entity_commands
+                    .insert(meshes.add(Mesh::from(shape::Capsule {
+                        radius: collider.radius,
+                        depth: collider.half_height * 2.0,
+                        ..Default::default()
+                    })))
+                    .insert(materials.add(Color::rgb(0.3, 0.3, 0.3).into()));
+            }
+        }
     }
 }
 
@@ -40,12 +155,20 @@ impl Plugin for HeroPlugin {
 pub(crate) struct HeroBundle {
     hero_kind: HeroKind,
     health: Health,
+    base_speed: BaseSpeed,
+    ultimate_charge: UltimateCharge,
     speed_modifier: SpeedModifier,
     damage_modifier: DamageModifier,
+    damage_taken_modifier: DamageTakenModifier,
     healing_modifier: HealingModifier,
     transform: Transform,
     velocity: Velocity,
+    fall_speed: FallSpeed,
+    stamina: Stamina,
+    character_controller: CharacterController,
     action_state: ActionState<ControlAction>,
+    aim: Aim,
+    transform_history: TransformHistory,
 }
 
 impl HeroBundle {
@@ -53,19 +176,32 @@ impl HeroBundle {
         Self {
             hero_kind,
             health: Health::default(),
+            base_speed: BaseSpeed::default(),
+            ultimate_charge: UltimateCharge::default(),
             speed_modifier: SpeedModifier::default(),
             damage_modifier: DamageModifier::default(),
+            damage_taken_modifier: DamageTakenModifier::default(),
             healing_modifier: HealingModifier::default(),
             transform: Transform::from_translation(translation),
             velocity: Velocity::default(),
+            fall_speed: FallSpeed::default(),
+            stamina: Stamina::default(),
+            character_controller: CharacterController::default(),
             action_state: ActionState::default(),
+            aim: Aim::default(),
+            transform_history: TransformHistory::default(),
         }
     }
 }
 
-#[derive(Clone, Copy, PartialEq, EnumIter, EnumString, Debug, Component)]
-pub(crate) enum HeroKind {
-    North,
+/// Path to the hero definition.
+#[derive(Component, Clone, PartialEq, Debug, From, Serialize, Deserialize)]
+pub(crate) struct HeroKind(String);
+
+impl AssociatedAsset for HeroKind {
+    fn asset_path(&self) -> &str {
+        &self.0
+    }
 }
 
 #[derive(Bundle)]
@@ -73,8 +209,12 @@ pub(super) struct LocalHeroBundle {
     abilities: Abilities,
     rigid_body: RigidBody,
     locked_axes: LockedAxes,
+    gravity_scale: GravityScale,
+    friction: Friction,
     collider: Collider,
     collision_groups: CollisionGroups,
+    colliding_entities: CollidingEntities,
+    active_events: ActiveEvents,
     mesh: Handle<Mesh>,
     material: Handle<StandardMaterial>,
     global_transform: GlobalTransform,
@@ -88,11 +228,19 @@ impl Default for LocalHeroBundle {
             abilities: Abilities::default(),
             rigid_body: RigidBody::Dynamic,
             locked_axes: LockedAxes::ROTATION_LOCKED,
+            gravity_scale: GravityScale::default(),
+            // Movement is handled by the character controller
+            friction: Friction {
+                coefficient: 0.0,
+                combine_rule: CoefficientCombineRule::Min,
+            },
             collider: Collider::capsule_y(0.5, 0.5),
             collision_groups: CollisionGroups {
                 memberships: CollisionMask::CHARACTER.bits(),
                 filters: CollisionMask::all().bits(),
             },
+            colliding_entities: CollidingEntities::default(),
+            active_events: ActiveEvents::COLLISION_EVENTS,
             mesh: Default::default(),
             material: Default::default(),
             global_transform: Default::default(),
@@ -103,7 +251,7 @@ impl Default for LocalHeroBundle {
 }
 
 /// Movement speed modifier
-#[derive(Component, AddAssign, SubAssign, Clone, Copy, From)]
+#[derive(Component, Clone, Copy, From, Into)]
 pub(super) struct SpeedModifier(pub(super) f32);
 
 impl Default for SpeedModifier {
@@ -113,7 +261,7 @@ impl Default for SpeedModifier {
 }
 
 /// Outgoing damage modifier
-#[derive(Component, AddAssign, SubAssign, Clone, Copy, From)]
+#[derive(Component, Clone, Copy, From, Into)]
 pub(super) struct DamageModifier(pub(super) f32);
 
 impl Default for DamageModifier {
@@ -122,8 +270,18 @@ impl Default for DamageModifier {
     }
 }
 
+/// Incoming damage modifier
+#[derive(Component, Clone, Copy, From, Into)]
+pub(super) struct DamageTakenModifier(pub(super) f32);
+
+impl Default for DamageTakenModifier {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
 /// Outgoing healing modifier
-#[derive(Component, AddAssign, SubAssign, Clone, Copy, From)]
+#[derive(Component, Clone, Copy, From, Into)]
 pub(super) struct HealingModifier(pub(super) f32);
 
 impl Default for HealingModifier {
@@ -132,33 +290,225 @@ impl Default for HealingModifier {
     }
 }
 
+/// Where the character is aiming.
+/// Computed from the camera for the local player and received from clients for remote players.
+#[derive(Component, Clone, Copy, Serialize, Deserialize)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+pub(crate) struct Aim {
+    /// Start of the aim ray.
+    pub(crate) origin: Vec3,
+    /// Normalized direction of the aim ray.
+    pub(crate) direction: Vec3,
+    /// Point where the aim ray hits something or ends.
+    pub(crate) target_point: Vec3,
+}
+
+impl Aim {
+    pub(crate) const MAX_DISTANCE: f32 = 100.0;
+
+    /// Creates aim from ray with optional hit distance.
+    pub(crate) fn new(origin: Vec3, direction: Vec3, hit_distance: Option<f32>) -> Self {
+        Self {
+            origin,
+            direction,
+            target_point: origin + direction * hit_distance.unwrap_or(Self::MAX_DISTANCE),
+        }
+    }
+
+    /// Returns normalized direction from `translation` to the target point.
+    /// Falls back to the aim direction if they coincide.
+    pub(crate) fn direction_from(&self, translation: Vec3) -> Vec3 {
+        (self.target_point - translation)
+            .try_normalize()
+            .unwrap_or(self.direction)
+    }
+}
+
+impl Default for Aim {
+    fn default() -> Self {
+        Self::new(Vec3::ZERO, -Vec3::Z, None)
+    }
+}
+
+/// Returns characters within `radius` around `translation` that match `filter` relative to the `caster`.
+fn characters_in_radius(
+    rapier_ctx: &RapierContext,
+    characters: &Query<Option<&Team>, With<Health>>,
+    caster: Entity,
+    translation: Vec3,
+    radius: f32,
+    filter: ZoneFilter,
+) -> Vec<Entity> {
+    let caster_team = characters.get(caster).ok().flatten().copied();
+    let mut result = Vec::new();
+    rapier_ctx.intersections_with_shape(
+        translation,
+        Quat::IDENTITY,
+        &Collider::ball(radius),
+        QueryFilter::new().groups(InteractionGroups::new(
+            CollisionMask::ZONE.bits(),
+            CollisionMask::CHARACTER.bits(),
+        )),
+        |character| {
+            if let Ok(team) = characters.get(character) {
+                let ally = character == caster || (team.is_some() && team.copied() == caster_team);
+                if filter.matches(ally) {
+                    result.push(character);
+                }
+            }
+            true
+        },
+    );
+
+    result
+}
+
 /// Returns normalized direction (without Y coordinate).
-/// Returns `-Vec3::Z` if the camera rotation is facing down
-fn character_direction(camera_rotation: Quat) -> Vec3 {
-    let mut direction = camera_rotation * -Vec3::Z;
+/// Returns `-Vec3::Z` if the aim direction is vertical
+fn character_direction(aim_direction: Vec3) -> Vec3 {
+    let mut direction = aim_direction;
     direction.y = 0.0;
     direction.try_normalize().unwrap_or(-Vec3::Z)
 }
 
 #[cfg(test)]
 mod tests {
+    use bevy::scene::ScenePlugin;
+
     use super::*;
+    use crate::core::{
+        ability::IconPath,
+        effect::effect_definition::EffectDefinitionPlugin,
+        headless::{self, HeadlessRenderPlugin},
+        health::HealthChanged,
+        hero::hero_definition::HeroDefinitions,
+        projectile::ProjectilePlugin,
+    };
+
+    #[test]
+    fn hero_spawns_from_definition() {
+        let mut app = App::new();
+        app.add_plugin(TestHeroPlugin);
+
+        let (hero_kind, _) = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .find(|(hero_kind, _)| hero_kind.asset_path().ends_with("north.hero.json"))
+            .cloned()
+            .expect("North definition should be available");
+        headless::wait_for_asset_loading(&mut app, hero_kind.asset_path());
+
+        let hero = app
+            .world
+            .spawn()
+            .insert_bundle(HeroBundle::new(hero_kind, Vec3::ZERO))
+            .id();
+
+        app.update();
+
+        let hero = app.world.entity(hero);
+        assert!(hero.contains::<Mana>(), "Pools should be inserted");
+        let abilities = hero
+            .get::<Abilities>()
+            .expect("Abilities should be inserted after definition loading");
+        assert_eq!(
+            abilities.len(),
+            5,
+            "All defined abilities should be spawned"
+        );
+        for &ability in abilities.iter() {
+            let ability = app.world.entity(ability);
+            assert!(ability.contains::<IconPath>());
+            assert!(ability.contains::<ControlAction>());
+        }
+    }
+
+    #[test]
+    fn hero_changes() {
+        let mut app = App::new();
+        app.add_plugin(TestHeroPlugin);
+
+        let hero_kinds: Vec<_> = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .map(|(hero_kind, _)| hero_kind.clone())
+            .collect();
+        for hero_kind in &hero_kinds {
+            headless::wait_for_asset_loading(&mut app, hero_kind.asset_path());
+        }
+        let (north_kind, sylva_kind) = (
+            hero_kinds
+                .iter()
+                .find(|hero_kind| hero_kind.asset_path().ends_with("north.hero.json"))
+                .expect("North definition should be available"),
+            hero_kinds
+                .iter()
+                .find(|hero_kind| hero_kind.asset_path().ends_with("sylva.hero.json"))
+                .expect("Sylva definition should be available"),
+        );
+
+        let hero = app
+            .world
+            .spawn()
+            .insert_bundle(HeroBundle::new(north_kind.clone(), Vec3::ZERO))
+            .id();
+
+        app.update();
+
+        let previous_abilities = app.world.get::<Abilities>(hero).unwrap().0.clone();
+        app.world.entity_mut(hero).insert(sylva_kind.clone());
+
+        app.update();
+        app.update();
+
+        for &ability in &previous_abilities {
+            assert!(
+                app.world.get_entity(ability).is_none(),
+                "Abilities of the previous hero should be despawned"
+            );
+        }
+        let abilities = app
+            .world
+            .get::<Abilities>(hero)
+            .expect("Abilities of the new hero should be inserted");
+        assert_eq!(
+            abilities.len(),
+            4,
+            "All abilities of the new hero should be spawned"
+        );
+    }
 
     #[test]
-    fn character_direction_from_camera() {
-        for (rotation, expected_direction) in [
-            (Quat::from_rotation_x(90_f32.to_radians()), -Vec3::Z),
-            (Quat::from_rotation_y(90_f32.to_radians()), -Vec3::X),
-            (Quat::from_rotation_z(90_f32.to_radians()), -Vec3::Z),
-            (Quat::from_rotation_x(-90_f32.to_radians()), -Vec3::Z),
-            (Quat::from_rotation_y(-90_f32.to_radians()), Vec3::X),
-            (Quat::from_rotation_z(-90_f32.to_radians()), -Vec3::Z),
+    fn character_direction_from_aim() {
+        for (aim_direction, expected_direction) in [
+            (Vec3::Y, -Vec3::Z),
+            (-Vec3::Y, -Vec3::Z),
+            (-Vec3::X, -Vec3::X),
+            (Vec3::new(1.0, 1.0, 0.0).normalize(), Vec3::X),
+            (Vec3::new(0.0, -1.0, 1.0).normalize(), Vec3::Z),
         ] {
             assert_eq!(
-                character_direction(rotation),
+                character_direction(aim_direction),
                 expected_direction,
-                "Character direction from {rotation} should be equal to {expected_direction}"
+                "Character direction from {aim_direction} should be equal to {expected_direction}"
             );
         }
     }
+
+    struct TestHeroPlugin;
+
+    impl Plugin for TestHeroPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_event::<HealthChanged>()
+                .add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(ProjectilePlugin)
+                .add_plugin(EffectDefinitionPlugin)
+                .add_plugin(HeroPlugin);
+        }
+    }
 }
@@ -17,138 +17,281 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use bevy::{prelude::*, render::camera::Camera};
+use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
 use iyes_loopless::prelude::*;
 
-use super::{character_direction, HeroKind, LocalHeroBundle};
+use super::{
+    character_direction, characters_in_radius,
+    hero_definition::{AbilityArchetypes, AbilityParams},
+    Aim,
+};
 use crate::core::{
-    ability::{Activator, IconPath},
-    control_actions::ControlAction,
-    cooldown::Cooldown,
-    game_state::GameState,
-    health::{Health, HealthChanged},
-    Owner, ProjectileBundle,
+    ability::Activator,
+    despawn_timer::DespawnTimer,
+    effect::{
+        effect_definition::EffectDefinition,
+        effect_zone::{EffectZone, EffectZoneBundle, ZoneFilter},
+        EffectTarget,
+    },
+    game_state::{GameState, InGameOnly},
+    health::{Health, HealthChanged, LastAttacker},
+    player::Team,
+    projectile::{OnHit, ProjectileBundle, ProjectilePool},
+    simulation::{SimulationAppExt, SimulationTime},
+    CollisionMask, Owner,
 };
 
 const PROJECTILE_SPEED: f32 = 20.0;
 const FROST_BOLT_SPAWN_OFFSET: f32 = 4.0;
-const FROST_BOLT_DAMAGE: i32 = -20;
+const FROST_BOLT_DAMAGE: f32 = 20.0;
 const FROST_PATH_IMPULSE: f32 = 130.0;
+const FROST_PATH_PUSH_SECS: f32 = 1.0;
+const ICE_WALL_SPAWN_OFFSET: f32 = 5.0;
+const ICE_WALL_SECS: f32 = 5.0;
+const ICE_WALL_HALF_WIDTH: f32 = 4.0;
+const ICE_WALL_HALF_HEIGHT: f32 = 2.0;
+const ICE_WALL_HALF_THICKNESS: f32 = 0.5;
+const FROST_NOVA_RADIUS: f32 = 8.0;
+const DEEP_FREEZE_RADIUS: f32 = 10.0;
+const DEEP_FREEZE_SECS: f32 = 4.0;
+const CHILL_EFFECT_PATH: &str = "effects/chill.effect.json";
+const FREEZE_EFFECT_PATH: &str = "effects/freeze.effect.json";
 
 pub(super) struct NorthPlugin;
 
 impl Plugin for NorthPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::spawn_system.run_in_state(GameState::InGame))
-            .add_system(Self::frost_bolt_system.run_in_state(GameState::InGame))
-            .add_system(Self::frost_bolt_hit_system.run_in_state(GameState::InGame))
-            .add_system(Self::frost_path_system.run_in_state(GameState::InGame));
+        let mut archetypes = app
+            .world
+            .get_resource_or_insert_with(AbilityArchetypes::default);
+        archetypes.register("frost_bolt", |ability, params| {
+            ability.insert_bundle(FrostBoltBundle::new(params));
+        });
+        archetypes.register("frost_path", |ability, params| {
+            ability.insert(FrostPathAbility::new(params));
+        });
+        archetypes.register("ice_wall", |ability, params| {
+            ability.insert(IceWallAbility::new(params));
+        });
+        archetypes.register("frost_nova", |ability, params| {
+            ability.insert(FrostNovaAbility::new(params));
+        });
+        archetypes.register("deep_freeze", |ability, params| {
+            ability.insert(DeepFreezeAbility::new(params));
+        });
+
+        app.add_startup_system(Self::load_effects_system)
+            .add_startup_system(Self::create_assets_system)
+            .add_tick_system(Self::frost_bolt_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::frost_bolt_hit_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::frost_path_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::frost_path_push_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::ice_wall_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::frost_nova_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::deep_freeze_system.run_in_state(GameState::InGame));
     }
 }
 
 impl NorthPlugin {
-    fn spawn_system(
+    fn load_effects_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(NorthEffects {
+            chill: asset_server.load(CHILL_EFFECT_PATH),
+            freeze: asset_server.load(FREEZE_EFFECT_PATH),
+        });
+    }
+
+    fn create_assets_system(
         mut commands: Commands,
         mut meshes: ResMut<Assets<Mesh>>,
         mut materials: ResMut<Assets<StandardMaterial>>,
-        heroes: Query<(Entity, &HeroKind), Added<HeroKind>>,
     ) {
-        for (hero, &hero_kind) in heroes.iter() {
-            if hero_kind != HeroKind::North {
-                continue;
-            }
-
-            let abilities = vec![
-                commands.spawn_bundle(FrostBoltBundle::default()).id(),
-                commands.spawn_bundle(FrostPathBundle::default()).id(),
-            ];
-
-            let mut entity_commands = commands.entity(hero);
-            entity_commands.insert_bundle(LocalHeroBundle {
-                abilities: abilities.into(),
-                mesh: meshes.add(Mesh::from(shape::Capsule::default())),
-                material: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
-                ..Default::default()
-            });
-        }
+        commands.insert_resource(NorthAssets {
+            frost_bolt_mesh: meshes.add(Mesh::from(shape::Capsule::default())),
+            frost_bolt_material: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
+            ice_wall_mesh: meshes.add(Mesh::from(shape::Box::new(
+                ICE_WALL_HALF_WIDTH * 2.0,
+                ICE_WALL_HALF_HEIGHT * 2.0,
+                ICE_WALL_HALF_THICKNESS * 2.0,
+            ))),
+            ice_wall_material: materials.add(Color::rgba(0.6, 0.8, 1.0, 0.8).into()),
+        });
     }
 
     fn frost_bolt_system(
         mut commands: Commands,
-        mut meshes: ResMut<Assets<Mesh>>,
-        mut materials: ResMut<Assets<StandardMaterial>>,
-        abilities: Query<(Entity, &Activator), With<FrostBoltAbility>>,
-        characters: Query<&Transform>,
-        cameras: Query<&Transform, With<Camera>>,
+        north_assets: Res<NorthAssets>,
+        mut abilities: Query<(Entity, &Activator, &FrostBoltAbility, &mut ProjectilePool)>,
+        characters: Query<(&Transform, &Aim)>,
     ) {
-        for (ability, activator) in abilities.iter() {
-            let camera_transform = cameras.single();
-            let character_transform = characters.get(activator.0).unwrap();
+        for (ability, activator, frost_bolt, mut pool) in abilities.iter_mut() {
+            let (character_transform, aim) = characters.get(activator.0).unwrap();
 
+            let translation =
+                character_transform.translation + aim.direction * FROST_BOLT_SPAWN_OFFSET;
+            let direction = aim.direction_from(translation);
             let transform = Transform {
-                translation: character_transform.translation
-                    + camera_transform.rotation * -Vec3::Z * FROST_BOLT_SPAWN_OFFSET,
-                rotation: camera_transform.rotation * Quat::from_rotation_x(90.0_f32.to_radians()),
+                translation,
+                rotation: Quat::from_rotation_arc(Vec3::Y, direction),
                 scale: character_transform.scale,
             };
 
-            commands
-                .spawn_bundle(ProjectileBundle {
-                    velocity: Velocity::linear(
-                        transform.rotation
-                            * Quat::from_rotation_x(-90.0_f32.to_radians())
-                            * -Vec3::Z
-                            * PROJECTILE_SPEED,
-                    ),
+            pool.spawn(
+                &mut commands,
+                ability,
+                ProjectileBundle {
+                    velocity: Velocity::linear(direction * frost_bolt.speed),
                     pbr: PbrBundle {
-                        mesh: meshes.add(Mesh::from(shape::Capsule::default())),
-                        material: materials.add(Color::rgb(0.3, 0.3, 0.3).into()),
+                        mesh: north_assets.frost_bolt_mesh.clone(),
+                        material: north_assets.frost_bolt_material.clone(),
                         transform,
                         ..Default::default()
                     },
                     ..Default::default()
-                })
-                .insert(FrostBoltAbility)
-                .insert(Owner(activator.0));
+                },
+            )
+            .insert(FrostBoltProjectile {
+                damage: frost_bolt.damage,
+            })
+            .insert(Owner(activator.0));
 
             commands.entity(ability).remove::<Activator>();
         }
     }
 
     fn frost_bolt_hit_system(
-        mut commands: Commands,
+        mut hit_events: EventReader<OnHit>,
         mut health_events: EventWriter<HealthChanged>,
-        projectiles: Query<
-            (Entity, &Owner, &CollidingEntities),
-            (With<FrostBoltAbility>, Changed<CollidingEntities>),
-        >,
-        health: Query<(), With<Health>>,
+        projectiles: Query<&FrostBoltProjectile>,
     ) {
-        for (projectile, owner, collisions) in projectiles.iter() {
-            if let Some(first_collision) = collisions.iter().next() {
-                commands.entity(projectile).despawn();
-                if health.get(first_collision).is_ok() {
-                    health_events.send(HealthChanged {
-                        instigator: owner.0,
-                        target: first_collision,
-                        delta: FROST_BOLT_DAMAGE,
-                    });
-                }
+        for event in hit_events.iter() {
+            if let Ok(projectile) = projectiles.get(event.projectile) {
+                health_events.send(HealthChanged {
+                    instigator: event.owner,
+                    target: event.target,
+                    delta: -(projectile.damage as i32),
+                    modified: false,
+                    environment: false,
+                });
             }
         }
     }
 
     fn frost_path_system(
         mut commands: Commands,
-        mut characters: Query<&mut Velocity>,
-        abilities: Query<(Entity, &Activator), With<FrostPathAbility>>,
-        cameras: Query<&Transform, With<Camera>>,
+        mut characters: Query<(&mut Velocity, &Aim)>,
+        abilities: Query<(Entity, &Activator, &FrostPathAbility)>,
+    ) {
+        for (ability, activator, frost_path) in abilities.iter() {
+            let (mut velocity, aim) = characters.get_mut(activator.0).unwrap();
+            velocity.linvel += character_direction(aim.direction) * frost_path.impulse;
+
+            commands.entity(ability).remove::<Activator>();
+            commands
+                .entity(activator.0)
+                .insert(FrostPathPush::default());
+        }
+    }
+
+    /// Marks characters pushed by Frost Path to credit their out-of-bounds deaths to the caster.
+    fn frost_path_push_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut casters: Query<(Entity, &mut FrostPathPush, &CollidingEntities)>,
+        characters: Query<(), With<Health>>,
+    ) {
+        for (caster, mut push, colliding_entities) in casters.iter_mut() {
+            push.tick(time.delta());
+            if push.finished() {
+                commands.entity(caster).remove::<FrostPathPush>();
+                continue;
+            }
+
+            for character in colliding_entities.iter() {
+                if characters.get(character).is_ok() {
+                    commands.entity(character).insert(LastAttacker::new(caster));
+                }
+            }
+        }
+    }
+
+    fn ice_wall_system(
+        mut commands: Commands,
+        north_assets: Res<NorthAssets>,
+        abilities: Query<(Entity, &Activator, &IceWallAbility)>,
+        characters: Query<(&Transform, &Aim)>,
+    ) {
+        for (ability, activator, ice_wall) in abilities.iter() {
+            let (character_transform, aim) = characters.get(activator.0).unwrap();
+
+            let direction = character_direction(aim.direction);
+            let translation = character_transform.translation + direction * ICE_WALL_SPAWN_OFFSET;
+            commands.spawn_bundle(IceWallBundle {
+                despawn_timer: DespawnTimer::from_secs_f32(ice_wall.secs),
+                pbr: PbrBundle {
+                    mesh: north_assets.ice_wall_mesh.clone(),
+                    material: north_assets.ice_wall_material.clone(),
+                    transform: Transform::from_translation(translation)
+                        .looking_at(translation + direction, Vec3::Y),
+                    ..Default::default()
+                },
+                ..Default::default()
+            });
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Slows enemies around the caster.
+    fn frost_nova_system(
+        mut commands: Commands,
+        rapier_ctx: Res<RapierContext>,
+        effects: Res<NorthEffects>,
+        definitions: Res<Assets<EffectDefinition>>,
+        abilities: Query<(Entity, &Activator, &FrostNovaAbility)>,
+        casters: Query<&Transform>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, frost_nova) in abilities.iter() {
+            let caster = activator.0;
+            let translation = casters.get(caster).unwrap().translation;
+            for character in characters_in_radius(
+                &rapier_ctx,
+                &characters,
+                caster,
+                translation,
+                frost_nova.radius,
+                ZoneFilter::Enemies,
+            ) {
+                if let Some(chill) = definitions.get(&effects.chill) {
+                    let mut effect = commands.spawn();
+                    effect.insert(EffectTarget(character)).insert(Owner(caster));
+                    chill.insert_into(&mut effect);
+                }
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Spawns a zone around the caster that freezes enemies inside.
+    fn deep_freeze_system(
+        mut commands: Commands,
+        effects: Res<NorthEffects>,
+        abilities: Query<(Entity, &Activator, &DeepFreezeAbility)>,
+        casters: Query<&Transform>,
     ) {
-        for (ability, activator) in abilities.iter() {
-            let camera_transform = cameras.single();
-            let mut velocity = characters.get_mut(activator.0).unwrap();
-            velocity.linvel += character_direction(camera_transform.rotation) * FROST_PATH_IMPULSE;
+        for (ability, activator, deep_freeze) in abilities.iter() {
+            let translation = casters.get(activator.0).unwrap().translation;
+            commands
+                .spawn_bundle(EffectZoneBundle::new(
+                    "Deep Freeze Zone",
+                    EffectZone::from_definition(effects.freeze.clone(), ZoneFilter::Enemies),
+                    Collider::ball(deep_freeze.radius),
+                    translation,
+                ))
+                .insert(Owner(activator.0))
+                .insert(DespawnTimer::from_secs_f32(deep_freeze.secs));
 
             commands.entity(ability).remove::<Activator>();
         }
@@ -157,51 +300,148 @@ impl NorthPlugin {
 
 #[derive(Bundle)]
 struct FrostBoltBundle {
-    name: Name,
     frost_bolt_ability: FrostBoltAbility,
-    icon: IconPath,
-    action: ControlAction,
-    cooldown: Cooldown,
+    projectile_pool: ProjectilePool,
 }
 
-impl Default for FrostBoltBundle {
-    fn default() -> Self {
+impl FrostBoltBundle {
+    fn new(params: &AbilityParams) -> Self {
         Self {
-            name: "Frost Bolt Ability".into(),
-            frost_bolt_ability: FrostBoltAbility,
-            icon: "character/hero/north/frost_bolt.png".into(),
-            action: ControlAction::BaseAttack,
-            cooldown: Cooldown::from_secs(4),
+            frost_bolt_ability: FrostBoltAbility {
+                damage: params.get("damage", FROST_BOLT_DAMAGE) as u32,
+                speed: params.get("speed", PROJECTILE_SPEED),
+            },
+            projectile_pool: ProjectilePool::default(),
         }
     }
 }
 
 #[derive(Component)]
-struct FrostBoltAbility;
+struct FrostBoltAbility {
+    damage: u32,
+    speed: f32,
+}
+
+/// Frost Bolt projectile with damage from the ability.
+#[derive(Component)]
+struct FrostBoltProjectile {
+    damage: u32,
+}
 
+#[derive(Component)]
+struct FrostPathAbility {
+    impulse: f32,
+}
+
+impl FrostPathAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            impulse: params.get("impulse", FROST_PATH_IMPULSE),
+        }
+    }
+}
+
+#[derive(Component)]
+struct IceWallAbility {
+    secs: f32,
+}
+
+impl IceWallAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            secs: params.get("duration", ICE_WALL_SECS),
+        }
+    }
+}
+
+/// Temporary obstacle that blocks characters and projectiles.
 #[derive(Bundle)]
-struct FrostPathBundle {
+struct IceWallBundle {
     name: Name,
-    frost_path_ability: FrostPathAbility,
-    icon: IconPath,
-    action: ControlAction,
-    cooldown: Cooldown,
+    rigid_body: RigidBody,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+    despawn_timer: DespawnTimer,
+    ingame_only: InGameOnly,
+
+    #[bundle]
+    pbr: PbrBundle,
 }
 
-impl Default for FrostPathBundle {
+impl Default for IceWallBundle {
     fn default() -> Self {
         Self {
-            name: "Frost Path Ability".into(),
-            frost_path_ability: FrostPathAbility,
-            icon: "character/hero/north/frost_path.png".into(),
-            action: ControlAction::Ability1,
-            cooldown: Cooldown::from_secs(4),
+            name: "Ice Wall".into(),
+            rigid_body: RigidBody::Fixed,
+            collider: Collider::cuboid(
+                ICE_WALL_HALF_WIDTH,
+                ICE_WALL_HALF_HEIGHT,
+                ICE_WALL_HALF_THICKNESS,
+            ),
+            collision_groups: CollisionGroups {
+                memberships: CollisionMask::WORLD.bits(),
+                filters: CollisionMask::all().bits(),
+            },
+            despawn_timer: DespawnTimer::from_secs_f32(ICE_WALL_SECS),
+            ingame_only: InGameOnly,
+            pbr: PbrBundle::default(),
         }
     }
 }
 
+/// Applies [`CHILL_EFFECT_PATH`] effect.
 #[derive(Component)]
-struct FrostPathAbility;
+struct FrostNovaAbility {
+    radius: f32,
+}
+
+impl FrostNovaAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", FROST_NOVA_RADIUS),
+        }
+    }
+}
+
+/// Spawns a zone that applies [`FREEZE_EFFECT_PATH`] effect.
+#[derive(Component)]
+struct DeepFreezeAbility {
+    radius: f32,
+    secs: f32,
+}
+
+impl DeepFreezeAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", DEEP_FREEZE_RADIUS),
+            secs: params.get("duration", DEEP_FREEZE_SECS),
+        }
+    }
+}
+
+/// Meshes and materials shared between objects spawned by North's abilities.
+struct NorthAssets {
+    frost_bolt_mesh: Handle<Mesh>,
+    frost_bolt_material: Handle<StandardMaterial>,
+    ice_wall_mesh: Handle<Mesh>,
+    ice_wall_material: Handle<StandardMaterial>,
+}
+
+/// Effect definitions applied by North's abilities.
+struct NorthEffects {
+    chill: Handle<EffectDefinition>,
+    freeze: Handle<EffectDefinition>,
+}
+
+/// Indicates that the caster is dashing and pushes other characters.
+#[derive(Component, Deref, DerefMut)]
+struct FrostPathPush(Timer);
+
+impl Default for FrostPathPush {
+    fn default() -> Self {
+        Self(Timer::from_seconds(FROST_PATH_PUSH_SECS, false))
+    }
+}
 
 #[cfg(test)]
 mod tests {
@@ -209,35 +449,37 @@ mod tests {
     use bevy::{ecs::event::Events, scene::ScenePlugin};
 
     use super::*;
-    use crate::core::headless::HeadlessRenderPlugin;
+    use crate::core::{
+        effect::{effect_definition::EffectDefinitionPlugin, modifier_effect::Multiplier},
+        headless::{self, HeadlessRenderPlugin},
+        hero::{LocalHeroBundle, SpeedModifier},
+        projectile::ProjectilePlugin,
+    };
 
     #[test]
     fn frost_bolt() {
         let mut app = App::new();
         app.add_plugin(TestNorthPlugin);
 
+        let aim = Aim::new(Vec3::ONE, Vec3::Y, None);
         let instigator = app
             .world
             .spawn()
             .insert(Transform::from_translation(Vec3::ONE))
+            .insert(aim)
             .id();
         let ability = app
             .world
             .spawn()
-            .insert_bundle(FrostBoltBundle::default())
+            .insert_bundle(FrostBoltBundle::new(&AbilityParams::default()))
             .insert(Activator(instigator))
             .id();
-        let camera = app
-            .world
-            .spawn()
-            .insert_bundle(DummyCameraBundle::default())
-            .id();
 
         app.update();
 
         let projectile_transform = *app
             .world
-            .query_filtered::<&Transform, With<FrostBoltAbility>>()
+            .query_filtered::<&Transform, With<FrostBoltProjectile>>()
             .iter(&app.world)
             .next()
             .unwrap(); // TODO 0.8: Use single
@@ -260,10 +502,9 @@ mod tests {
             "Spawned projectile must be of the same scale as the character"
         );
 
-        let camera_transform = app.world.get::<Transform>(camera).unwrap();
         assert_abs_diff_eq!(
-            projectile_transform.rotation,
-            camera_transform.rotation * Quat::from_rotation_x(90.0_f32.to_radians()),
+            projectile_transform.rotation * Vec3::Y,
+            aim.direction,
             epsilon = 0.000001,
         );
 
@@ -291,16 +532,59 @@ mod tests {
             .expect("Health change event should be emitted");
 
         assert_eq!(
-            event.instigator, instigator,
+            event.instigator,
+            Some(instigator),
             "Instigator should be equal to specified"
         );
         assert_eq!(event.target, target, "Target should be equal to specified");
         assert_eq!(
-            event.delta, FROST_BOLT_DAMAGE,
+            event.delta,
+            -(FROST_BOLT_DAMAGE as i32),
             "Damage should be equal to frost bolt damage"
         );
     }
 
+    #[test]
+    fn frost_bolt_offset_origin() {
+        let mut app = App::new();
+        app.add_plugin(TestNorthPlugin);
+
+        // Camera origin is offset from the character like with the shoulder view
+        let aim = Aim::new(Vec3::new(1.0, 2.0, 0.0), -Vec3::Z, Some(20.0));
+        let instigator = app
+            .world
+            .spawn()
+            .insert(Transform::default())
+            .insert(aim)
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(FrostBoltBundle::new(&AbilityParams::default()))
+            .insert(Activator(instigator));
+
+        app.update();
+
+        let (projectile_transform, velocity) = app
+            .world
+            .query_filtered::<(&Transform, &Velocity), With<FrostBoltProjectile>>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        let expected_direction =
+            (aim.target_point - -Vec3::Z * FROST_BOLT_SPAWN_OFFSET).normalize();
+
+        assert_abs_diff_eq!(
+            velocity.linvel,
+            expected_direction * PROJECTILE_SPEED,
+            epsilon = 0.0001,
+        );
+        assert_abs_diff_eq!(
+            projectile_transform.rotation * Vec3::Y,
+            expected_direction,
+            epsilon = 0.0001,
+        );
+    }
+
     #[test]
     fn frost_path() {
         let mut app = App::new();
@@ -311,34 +595,179 @@ mod tests {
             .spawn()
             .insert(Transform::default())
             .insert(Velocity::linear(Vec3::ZERO))
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
             .id();
         let ability = app
             .world
             .spawn()
-            .insert_bundle(FrostPathBundle::default())
+            .insert(FrostPathAbility::new(&AbilityParams::default()))
             .insert(Activator(character))
             .id();
-        let camera = app
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        assert_eq!(
+            velocity.linvel,
+            Vec3::X * FROST_PATH_IMPULSE,
+            "Character should recieve impulse in aim direction"
+        );
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
+        assert!(
+            app.world.entity(character).contains::<FrostPathPush>(),
+            "Character should push others after activation"
+        );
+    }
+
+    #[test]
+    fn ice_wall() {
+        let mut app = App::new();
+        app.add_plugin(TestNorthPlugin);
+
+        let character = app
             .world
             .spawn()
-            .insert_bundle(DummyCameraBundle::default())
+            .insert(Transform::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
+            .id();
+        let ability = app
+            .world
+            .spawn()
+            .insert(IceWallAbility::new(&AbilityParams::default()))
+            .insert(Activator(character))
             .id();
 
         app.update();
 
-        let velocity = app.world.get::<Velocity>(character).unwrap();
-        let camera_transform = app.world.get::<Transform>(camera).unwrap();
+        let (wall_transform, despawn_timer) = app
+            .world
+            .query::<(&Transform, &DespawnTimer)>()
+            .iter(&app.world)
+            .next()
+            .expect("Ice wall should be spawned"); // TODO 0.8: Use single
+        assert_eq!(
+            wall_transform.translation,
+            Vec3::X * ICE_WALL_SPAWN_OFFSET,
+            "Ice wall should be spawned in front of the character"
+        );
+        assert_abs_diff_eq!(
+            wall_transform.rotation * Vec3::X,
+            Vec3::Z,
+            epsilon = 0.000001
+        );
+        assert_eq!(despawn_timer.duration().as_secs_f32(), ICE_WALL_SECS);
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
+    }
+
+    #[test]
+    fn frost_nova() {
+        let mut app = App::new();
+        app.add_plugin(TestNorthPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        let ally = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::from_translation(Vec3::X * 2.0))
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(-Vec3::X * 2.0))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(
+                Vec3::X * FROST_NOVA_RADIUS * 2.0,
+            ));
+
+        app.update();
+
+        headless::wait_for_asset_loading(&mut app, CHILL_EFFECT_PATH);
+
+        let ability = app
+            .world
+            .spawn()
+            .insert(FrostNovaAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster))
+            .id();
 
+        app.update();
+
+        let targets: Vec<_> = app
+            .world
+            .query_filtered::<&EffectTarget, With<Multiplier<SpeedModifier>>>()
+            .iter(&app.world)
+            .map(|target| target.0)
+            .collect();
+        assert!(!targets.contains(&ally), "Allies shouldn't be slowed");
         assert_eq!(
-            velocity.linvel,
-            character_direction(camera_transform.rotation) * FROST_PATH_IMPULSE,
-            "Character should recieve impulse in camera direction"
+            targets,
+            [enemy],
+            "Only enemies within the radius should be slowed"
         );
 
         assert!(
             !app.world.entity(ability).contains::<Activator>(),
             "Activator component should be removed from the ability",
-        )
+        );
+    }
+
+    #[test]
+    fn deep_freeze() {
+        let mut app = App::new();
+        app.add_plugin(TestNorthPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert(Transform::from_translation(Vec3::ONE))
+            .id();
+        let ability = app
+            .world
+            .spawn()
+            .insert(DeepFreezeAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster))
+            .id();
+
+        app.update();
+
+        let (zone_transform, owner, despawn_timer) = app
+            .world
+            .query_filtered::<(&Transform, &Owner, &DespawnTimer), With<EffectZone>>()
+            .iter(&app.world)
+            .next()
+            .expect("Deep Freeze zone should be spawned"); // TODO 0.8: Use single
+        assert_eq!(zone_transform.translation, Vec3::ONE);
+        assert_eq!(owner.0, caster, "Zone should be owned by the caster");
+        assert_eq!(despawn_timer.duration().as_secs_f32(), DEEP_FREEZE_SECS);
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
     }
 
     struct TestNorthPlugin;
@@ -350,22 +779,9 @@ mod tests {
                 .add_plugin(HeadlessRenderPlugin)
                 .add_plugin(ScenePlugin)
                 .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(ProjectilePlugin)
+                .add_plugin(EffectDefinitionPlugin)
                 .add_plugin(NorthPlugin);
         }
     }
-
-    #[derive(Bundle)]
-    struct DummyCameraBundle {
-        transform: Transform,
-        camera: Camera,
-    }
-
-    impl Default for DummyCameraBundle {
-        fn default() -> Self {
-            Self {
-                transform: Transform::from_rotation(Quat::from_rotation_x(90_f32.to_radians())),
-                camera: Camera::default(),
-            }
-        }
-    }
 }
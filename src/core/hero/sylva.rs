@@ -0,0 +1,696 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use super::{
+    characters_in_radius,
+    hero_definition::{AbilityArchetypes, AbilityParams},
+    Aim,
+};
+use crate::core::{
+    ability::Activator,
+    despawn_timer::DespawnTimer,
+    effect::{
+        effect_definition::EffectDefinition,
+        effect_zone::{EffectZone, EffectZoneBundle, ZoneFilter},
+        periodic_effect::{OwnerScaling, PeriodicEffectTimer, PeriodicHealthChange},
+        stacking::EffectKind,
+        status_effect::CleanseBundle,
+        EffectTarget,
+    },
+    game_state::GameState,
+    health::{Health, HealthChanged},
+    hitscan::{Hitscan, PerceivedTick},
+    player::Team,
+    projectile::{Homing, OnHit, Projectile, ProjectileBundle, ProjectilePool},
+    simulation::SimulationAppExt,
+    Owner,
+};
+
+const LIFE_SEED_SPEED: f32 = 25.0;
+const LIFE_SEED_SPAWN_OFFSET: f32 = 4.0;
+const LIFE_SEED_HEALING: f32 = 15.0;
+const LIFE_SEED_DAMAGE: f32 = 10.0;
+/// Turn rate towards the aimed character in degrees per second.
+const LIFE_SEED_HOMING_DEGREES: f32 = 90.0;
+const BLOSSOM_FIELD_RANGE: f32 = 20.0;
+const BLOSSOM_FIELD_RADIUS: f32 = 5.0;
+const BLOSSOM_FIELD_SECS: f32 = 6.0;
+const BLOSSOM_HEALING_PER_TICK: i32 = 5;
+const BLOSSOM_TICK_SECS: f32 = 1.0;
+const BARKSKIN_RADIUS: f32 = 10.0;
+const PROTECTION_EFFECT_PATH: &str = "effects/protection.effect.json";
+const FULL_BLOOM_RADIUS: f32 = 15.0;
+const FULL_BLOOM_HEALING: f32 = 60.0;
+
+pub(super) struct SylvaPlugin;
+
+impl Plugin for SylvaPlugin {
+    fn build(&self, app: &mut App) {
+        let mut archetypes = app
+            .world
+            .get_resource_or_insert_with(AbilityArchetypes::default);
+        archetypes.register("life_seed", |ability, params| {
+            ability.insert_bundle(LifeSeedBundle::new(params));
+        });
+        archetypes.register("blossom_field", |ability, params| {
+            ability.insert(BlossomFieldAbility::new(params));
+        });
+        archetypes.register("barkskin", |ability, params| {
+            ability.insert(BarkskinAbility::new(params));
+        });
+        archetypes.register("full_bloom", |ability, params| {
+            ability.insert(FullBloomAbility::new(params));
+        });
+
+        app.add_startup_system(Self::load_effects_system)
+            .add_startup_system(Self::create_assets_system)
+            .add_tick_system(Self::life_seed_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::life_seed_hit_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::blossom_field_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::barkskin_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::full_bloom_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl SylvaPlugin {
+    fn load_effects_system(mut commands: Commands, asset_server: Res<AssetServer>) {
+        commands.insert_resource(SylvaEffects {
+            protection: asset_server.load(PROTECTION_EFFECT_PATH),
+        });
+    }
+
+    fn create_assets_system(
+        mut commands: Commands,
+        mut meshes: ResMut<Assets<Mesh>>,
+        mut materials: ResMut<Assets<StandardMaterial>>,
+    ) {
+        commands.insert_resource(SylvaAssets {
+            life_seed_mesh: meshes.add(Mesh::from(shape::Icosphere {
+                radius: 0.5,
+                subdivisions: 2,
+            })),
+            life_seed_material: materials.add(Color::rgb(0.4, 0.8, 0.3).into()),
+        });
+    }
+
+    /// Launches Life Seed that homes on the character under the crosshair.
+    /// Uses the tick perceived by the caster's client to pick the target.
+    fn life_seed_system(
+        mut commands: Commands,
+        hitscan: Hitscan,
+        sylva_assets: Res<SylvaAssets>,
+        mut abilities: Query<(Entity, &Activator, &LifeSeedAbility, &mut ProjectilePool)>,
+        characters: Query<(&Transform, &Aim, Option<&PerceivedTick>)>,
+    ) {
+        for (ability, activator, life_seed, mut pool) in abilities.iter_mut() {
+            let (character_transform, aim, perceived_tick) = characters.get(activator.0).unwrap();
+
+            let homing_target = hitscan
+                .cast(
+                    aim.origin,
+                    aim.direction,
+                    Aim::MAX_DISTANCE,
+                    perceived_tick.map(|tick| tick.0),
+                    activator.0,
+                )
+                .map(|(target, _)| target);
+
+            let translation =
+                character_transform.translation + aim.direction * LIFE_SEED_SPAWN_OFFSET;
+            let direction = aim.direction_from(translation);
+            let transform = Transform {
+                translation,
+                rotation: Quat::from_rotation_arc(Vec3::Y, direction),
+                scale: character_transform.scale,
+            };
+
+            let projectile = pool
+                .spawn(
+                    &mut commands,
+                    ability,
+                    ProjectileBundle {
+                        projectile: Projectile {
+                            ignore_allies: false,
+                            ..Default::default()
+                        },
+                        velocity: Velocity::linear(direction * life_seed.speed),
+                        pbr: PbrBundle {
+                            mesh: sylva_assets.life_seed_mesh.clone(),
+                            material: sylva_assets.life_seed_material.clone(),
+                            transform,
+                            ..Default::default()
+                        },
+                        ..Default::default()
+                    },
+                )
+                .insert(LifeSeedProjectile {
+                    healing: life_seed.healing,
+                    damage: life_seed.damage,
+                })
+                .insert(Owner(activator.0))
+                .id();
+
+            if let Some(target) = homing_target {
+                if life_seed.homing_turn_rate > 0.0 {
+                    commands.entity(projectile).insert(Homing {
+                        target,
+                        turn_rate: life_seed.homing_turn_rate,
+                    });
+                }
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Heals allies and damages enemies hit by Life Seed.
+    fn life_seed_hit_system(
+        mut hit_events: EventReader<OnHit>,
+        mut health_events: EventWriter<HealthChanged>,
+        projectiles: Query<&LifeSeedProjectile>,
+        teams: Query<&Team>,
+    ) {
+        for event in hit_events.iter() {
+            if let Ok(projectile) = projectiles.get(event.projectile) {
+                let owner_team = event.owner.and_then(|owner| teams.get(owner).ok());
+                let ally = owner_team.is_some() && teams.get(event.target).ok() == owner_team;
+                let delta = if ally {
+                    projectile.healing as i32
+                } else {
+                    -(projectile.damage as i32)
+                };
+
+                health_events.send(HealthChanged {
+                    instigator: event.owner,
+                    target: event.target,
+                    delta,
+                    modified: false,
+                    environment: false,
+                });
+            }
+        }
+    }
+
+    /// Spawns a zone at the aim point that heals allies inside over time.
+    fn blossom_field_system(
+        mut commands: Commands,
+        abilities: Query<(Entity, &Activator, &BlossomFieldAbility)>,
+        characters: Query<(&Transform, &Aim)>,
+    ) {
+        for (ability, activator, blossom_field) in abilities.iter() {
+            let (character_transform, aim) = characters.get(activator.0).unwrap();
+
+            let translation = field_translation(character_transform.translation, aim);
+            commands
+                .spawn_bundle(EffectZoneBundle::new(
+                    "Blossom Field",
+                    EffectZone::new(
+                        |effect| {
+                            effect.insert_bundle(BlossomEffectBundle::default());
+                        },
+                        ZoneFilter::Allies,
+                    ),
+                    Collider::ball(blossom_field.radius),
+                    translation,
+                ))
+                .insert(Owner(activator.0))
+                .insert(DespawnTimer::from_secs_f32(blossom_field.secs));
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Reduces damage taken by allies around the caster.
+    fn barkskin_system(
+        mut commands: Commands,
+        rapier_ctx: Res<RapierContext>,
+        effects: Res<SylvaEffects>,
+        definitions: Res<Assets<EffectDefinition>>,
+        abilities: Query<(Entity, &Activator, &BarkskinAbility)>,
+        casters: Query<&Transform>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, barkskin) in abilities.iter() {
+            let caster = activator.0;
+            let translation = casters.get(caster).unwrap().translation;
+            for character in characters_in_radius(
+                &rapier_ctx,
+                &characters,
+                caster,
+                translation,
+                barkskin.radius,
+                ZoneFilter::Allies,
+            ) {
+                if let Some(protection) = definitions.get(&effects.protection) {
+                    let mut effect = commands.spawn();
+                    effect.insert(EffectTarget(character)).insert(Owner(caster));
+                    protection.insert_into(&mut effect);
+                }
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+
+    /// Heals and cleanses allies around the caster.
+    fn full_bloom_system(
+        mut commands: Commands,
+        mut health_events: EventWriter<HealthChanged>,
+        rapier_ctx: Res<RapierContext>,
+        abilities: Query<(Entity, &Activator, &FullBloomAbility)>,
+        casters: Query<&Transform>,
+        characters: Query<Option<&Team>, With<Health>>,
+    ) {
+        for (ability, activator, full_bloom) in abilities.iter() {
+            let caster = activator.0;
+            let translation = casters.get(caster).unwrap().translation;
+            for character in characters_in_radius(
+                &rapier_ctx,
+                &characters,
+                caster,
+                translation,
+                full_bloom.radius,
+                ZoneFilter::Allies,
+            ) {
+                health_events.send(HealthChanged {
+                    instigator: Some(caster),
+                    target: character,
+                    delta: full_bloom.healing as i32,
+                    modified: false,
+                    environment: false,
+                });
+                commands.spawn_bundle(CleanseBundle::new(character));
+            }
+
+            commands.entity(ability).remove::<Activator>();
+        }
+    }
+}
+
+/// Returns aim target point limited by [`BLOSSOM_FIELD_RANGE`] from the character.
+fn field_translation(character_translation: Vec3, aim: &Aim) -> Vec3 {
+    let offset = aim.target_point - character_translation;
+    character_translation + offset.clamp_length_max(BLOSSOM_FIELD_RANGE)
+}
+
+#[derive(Bundle)]
+struct LifeSeedBundle {
+    life_seed_ability: LifeSeedAbility,
+    projectile_pool: ProjectilePool,
+}
+
+impl LifeSeedBundle {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            life_seed_ability: LifeSeedAbility {
+                healing: params.get("healing", LIFE_SEED_HEALING) as u32,
+                damage: params.get("damage", LIFE_SEED_DAMAGE) as u32,
+                speed: params.get("speed", LIFE_SEED_SPEED),
+                homing_turn_rate: params.get("homing", LIFE_SEED_HOMING_DEGREES).to_radians(),
+            },
+            projectile_pool: ProjectilePool::default(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct LifeSeedAbility {
+    healing: u32,
+    damage: u32,
+    speed: f32,
+    /// Radians per second, zero disables homing.
+    homing_turn_rate: f32,
+}
+
+/// Life Seed projectile with healing and damage from the ability.
+#[derive(Component)]
+struct LifeSeedProjectile {
+    healing: u32,
+    damage: u32,
+}
+
+#[derive(Component)]
+struct BlossomFieldAbility {
+    radius: f32,
+    secs: f32,
+}
+
+impl BlossomFieldAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", BLOSSOM_FIELD_RADIUS),
+            secs: params.get("duration", BLOSSOM_FIELD_SECS),
+        }
+    }
+}
+
+/// Heals the target while it stays inside Blossom Field.
+#[derive(Bundle)]
+struct BlossomEffectBundle {
+    name: Name,
+    kind: EffectKind,
+    timer: PeriodicEffectTimer,
+    health_change: PeriodicHealthChange,
+    owner_scaling: OwnerScaling,
+}
+
+impl Default for BlossomEffectBundle {
+    fn default() -> Self {
+        Self {
+            name: "Blossom Effect".into(),
+            kind: EffectKind::Healing,
+            timer: PeriodicEffectTimer::new(BLOSSOM_TICK_SECS, true),
+            health_change: BLOSSOM_HEALING_PER_TICK.into(),
+            owner_scaling: OwnerScaling,
+        }
+    }
+}
+
+/// Applies [`PROTECTION_EFFECT_PATH`] effect.
+#[derive(Component)]
+struct BarkskinAbility {
+    radius: f32,
+}
+
+impl BarkskinAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", BARKSKIN_RADIUS),
+        }
+    }
+}
+
+#[derive(Component)]
+struct FullBloomAbility {
+    radius: f32,
+    healing: u32,
+}
+
+impl FullBloomAbility {
+    fn new(params: &AbilityParams) -> Self {
+        Self {
+            radius: params.get("radius", FULL_BLOOM_RADIUS),
+            healing: params.get("healing", FULL_BLOOM_HEALING) as u32,
+        }
+    }
+}
+
+/// Meshes and materials shared between objects spawned by Sylva's abilities.
+struct SylvaAssets {
+    life_seed_mesh: Handle<Mesh>,
+    life_seed_material: Handle<StandardMaterial>,
+}
+
+/// Effect definitions applied by Sylva's abilities.
+struct SylvaEffects {
+    protection: Handle<EffectDefinition>,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, scene::ScenePlugin};
+
+    use super::*;
+    use crate::core::{
+        effect::{effect_definition::EffectDefinitionPlugin, modifier_effect::Multiplier},
+        headless::{self, HeadlessRenderPlugin},
+        hero::{DamageTakenModifier, LocalHeroBundle},
+        projectile::ProjectilePlugin,
+    };
+
+    #[test]
+    fn life_seed() {
+        let mut app = App::new();
+        app.add_plugin(TestSylvaPlugin);
+
+        let instigator = app
+            .world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
+            .insert(Team(0))
+            .id();
+        let ability = app
+            .world
+            .spawn()
+            .insert_bundle(LifeSeedBundle::new(&AbilityParams::default()))
+            .insert(Activator(instigator))
+            .id();
+
+        app.update();
+
+        let projectile_transform = *app
+            .world
+            .query_filtered::<&Transform, With<LifeSeedProjectile>>()
+            .iter(&app.world)
+            .next()
+            .expect("Life Seed projectile should be spawned"); // TODO 0.8: Use single
+        assert_eq!(
+            projectile_transform.translation,
+            Vec3::X * LIFE_SEED_SPAWN_OFFSET
+        );
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
+
+        let ally = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(projectile_transform)
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let event = health_events
+            .drain()
+            .next()
+            .expect("Health change event should be emitted");
+
+        assert_eq!(event.instigator, Some(instigator));
+        assert_eq!(event.target, ally);
+        assert_eq!(
+            event.delta, LIFE_SEED_HEALING as i32,
+            "Allies should be healed"
+        );
+    }
+
+    #[test]
+    fn life_seed_homing() {
+        let mut app = App::new();
+        app.add_plugin(TestSylvaPlugin);
+
+        let instigator = app
+            .world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
+            .insert(Team(0))
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(1))
+            .insert(Transform::from_translation(Vec3::X * 20.0))
+            .id();
+
+        app.update();
+
+        app.world
+            .spawn()
+            .insert_bundle(LifeSeedBundle::new(&AbilityParams::default()))
+            .insert(Activator(instigator));
+
+        app.update();
+
+        let homing = app
+            .world
+            .query_filtered::<&Homing, With<LifeSeedProjectile>>()
+            .iter(&app.world)
+            .next()
+            .expect("Life Seed aimed at a character should home"); // TODO 0.8: Use single
+        assert_eq!(homing.target, enemy);
+        assert_eq!(homing.turn_rate, LIFE_SEED_HOMING_DEGREES.to_radians());
+    }
+
+    #[test]
+    fn blossom_field() {
+        let mut app = App::new();
+        app.add_plugin(TestSylvaPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert(Transform::default())
+            .insert(Aim::new(Vec3::ZERO, Vec3::X, None))
+            .id();
+        let ability = app
+            .world
+            .spawn()
+            .insert(BlossomFieldAbility::new(&AbilityParams::default()))
+            .insert(Activator(character))
+            .id();
+
+        app.update();
+
+        let (zone_transform, owner, despawn_timer) = app
+            .world
+            .query_filtered::<(&Transform, &Owner, &DespawnTimer), With<EffectZone>>()
+            .iter(&app.world)
+            .next()
+            .expect("Blossom Field should be spawned"); // TODO 0.8: Use single
+        assert_eq!(
+            zone_transform.translation,
+            Vec3::X * BLOSSOM_FIELD_RANGE,
+            "Field should be placed at the aim point limited by range"
+        );
+        assert_eq!(owner.0, character, "Field should be owned by the caster");
+        assert_eq!(despawn_timer.duration().as_secs_f32(), BLOSSOM_FIELD_SECS);
+
+        assert!(
+            !app.world.entity(ability).contains::<Activator>(),
+            "Activator component should be removed from the ability",
+        );
+    }
+
+    #[test]
+    fn barkskin() {
+        let mut app = App::new();
+        app.add_plugin(TestSylvaPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        let ally = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::from_translation(Vec3::X * 2.0))
+            .id();
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(-Vec3::X * 2.0))
+            .id();
+
+        app.update();
+
+        headless::wait_for_asset_loading(&mut app, PROTECTION_EFFECT_PATH);
+
+        app.world
+            .spawn()
+            .insert(BarkskinAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+
+        let targets: Vec<_> = app
+            .world
+            .query_filtered::<&EffectTarget, With<Multiplier<DamageTakenModifier>>>()
+            .iter(&app.world)
+            .map(|target| target.0)
+            .collect();
+        assert!(
+            targets.contains(&caster),
+            "Caster should be protected as an ally to itself"
+        );
+        assert!(targets.contains(&ally), "Allies should be protected");
+        assert!(!targets.contains(&enemy), "Enemies shouldn't be protected");
+    }
+
+    #[test]
+    fn full_bloom() {
+        let mut app = App::new();
+        app.add_plugin(TestSylvaPlugin);
+
+        let caster = app
+            .world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Team(0))
+            .insert(Transform::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(LocalHeroBundle::default())
+            .insert(Health::default())
+            .insert(Transform::from_translation(-Vec3::X * 2.0));
+
+        app.update();
+
+        app.world
+            .spawn()
+            .insert(FullBloomAbility::new(&AbilityParams::default()))
+            .insert(Activator(caster));
+
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let events: Vec<_> = health_events.drain().collect();
+        assert_eq!(events.len(), 1, "Only allies should be healed");
+        assert_eq!(events[0].target, caster);
+        assert_eq!(events[0].delta, FULL_BLOOM_HEALING as i32);
+
+        let cleansed: Vec<_> = app
+            .world
+            .query::<(&EffectKind, &EffectTarget)>()
+            .iter(&app.world)
+            .filter(|(kind, _)| **kind == EffectKind::Cleanse)
+            .map(|(_, target)| target.0)
+            .collect();
+        assert_eq!(cleansed, [caster], "Allies should be cleansed");
+    }
+
+    struct TestSylvaPlugin;
+
+    impl Plugin for TestSylvaPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_event::<HealthChanged>()
+                .add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(ProjectilePlugin)
+                .add_plugin(EffectDefinitionPlugin)
+                .add_plugin(SylvaPlugin);
+        }
+    }
+}
@@ -0,0 +1,386 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+use bevy::{ecs::system::SystemParam, prelude::*};
+use bevy_rapier3d::{
+    prelude::*,
+    rapier::{
+        math::{Isometry, Vector},
+        parry::query::{PointQuery, Ray, RayCast},
+    },
+};
+use bevy_renet::renet::RenetServer;
+use iyes_loopless::prelude::*;
+use std::collections::VecDeque;
+
+use super::{game_state::GameState, health::Health, network::NetworkTick, CollisionMask};
+
+/// Number of network ticks to keep in [`TransformHistory`].
+const HISTORY_TICKS: usize = 16;
+
+pub(super) struct HitscanPlugin;
+
+impl Plugin for HitscanPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_to_stage(
+            CoreStage::PostUpdate,
+            Self::record_history_system
+                .run_in_state(GameState::InGame)
+                .after(TransformSystem::TransformPropagate)
+                .run_if_resource_exists::<RenetServer>(),
+        );
+    }
+}
+
+impl HitscanPlugin {
+    fn record_history_system(
+        network_tick: Res<NetworkTick>,
+        mut characters: Query<(&GlobalTransform, &mut TransformHistory)>,
+    ) {
+        for (&transform, mut history) in characters.iter_mut() {
+            history.record(network_tick.0, transform.into());
+        }
+    }
+}
+
+/// Resolves instant hits by casting rays against the world and character colliders.
+/// Only solid colliders of entities with [`Health`] are considered characters.
+/// Characters are checked at positions from [`TransformHistory`] if the tick is specified,
+/// so shots register as they were seen by the client despite latency.
+#[derive(SystemParam)]
+pub(super) struct Hitscan<'w, 's> {
+    rapier_ctx: Res<'w, RapierContext>,
+    characters: Query<
+        'w,
+        's,
+        (
+            Entity,
+            &'static GlobalTransform,
+            &'static Collider,
+            Option<&'static TransformHistory>,
+        ),
+        (With<Health>, Without<Sensor>),
+    >,
+}
+
+impl Hitscan<'_, '_> {
+    /// Returns the closest character hit by the ray and distance to it.
+    /// World geometry blocks the ray, `ignore` is never hit.
+    pub(super) fn cast(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        range: f32,
+        tick: Option<u32>,
+        ignore: Entity,
+    ) -> Option<(Entity, f32)> {
+        let ray = Ray::new(origin.into(), direction.into());
+        let mut closest_hit = None;
+        let mut closest_distance = self.world_distance(origin, direction, range);
+        for (character, transform, collider, history) in self.characters.iter() {
+            if character == ignore {
+                continue;
+            }
+
+            let isometry = character_isometry(transform, history, tick);
+            if let Some(distance) = collider
+                .raw
+                .cast_ray(&isometry, &ray, closest_distance, true)
+            {
+                closest_hit = Some(character);
+                closest_distance = distance;
+            }
+        }
+
+        closest_hit.map(|character| (character, closest_distance))
+    }
+
+    /// Returns characters within `range` and `angle` around `direction` in the horizontal plane.
+    /// Characters are hit if the closest point of their colliders is within `range`
+    /// and any part of their horizontal extents is within `angle`.
+    /// World geometry blocks hits, `ignore` is never hit.
+    pub(super) fn cast_arc(
+        &self,
+        origin: Vec3,
+        direction: Vec3,
+        range: f32,
+        angle: f32,
+        tick: Option<u32>,
+        ignore: Entity,
+    ) -> Vec<Entity> {
+        let direction = Vec3::new(direction.x, 0.0, direction.z);
+        let mut hits = Vec::new();
+        for (character, transform, collider, history) in self.characters.iter() {
+            if character == ignore {
+                continue;
+            }
+
+            let isometry = character_isometry(transform, history, tick);
+            let projection = collider.raw.project_point(&isometry, &origin.into(), true);
+            let offset = Vec3::from(projection.point) - origin;
+            let distance = offset.length();
+            if distance > range {
+                continue;
+            }
+
+            let half_extents = collider.raw.compute_local_aabb().half_extents();
+            let horizontal_radius = Vec2::new(half_extents.x, half_extents.z).length();
+            let mut center_offset = Vec3::from(isometry.translation.vector) - origin;
+            center_offset.y = 0.0;
+            let center_distance = center_offset.length();
+            if center_distance > horizontal_radius {
+                let angular_radius = (horizontal_radius / center_distance).asin();
+                if center_offset.angle_between(direction) > angle / 2.0 + angular_radius {
+                    continue;
+                }
+            }
+
+            if distance > f32::EPSILON
+                && self.world_distance(origin, offset / distance, distance) < distance
+            {
+                continue;
+            }
+
+            hits.push(character);
+        }
+
+        hits
+    }
+
+    /// Returns distance to the world geometry along the ray or `range` if nothing was hit.
+    fn world_distance(&self, origin: Vec3, direction: Vec3, range: f32) -> f32 {
+        self.rapier_ctx
+            .cast_ray(
+                origin,
+                direction,
+                range,
+                true,
+                QueryFilter::new().groups(InteractionGroups::new(
+                    CollisionMask::all().bits(),
+                    CollisionMask::WORLD.bits(),
+                )),
+            )
+            .map(|(_, distance)| distance)
+            .unwrap_or(range)
+    }
+}
+
+/// Returns character isometry on the `tick` from history or the current one.
+fn character_isometry(
+    transform: &GlobalTransform,
+    history: Option<&TransformHistory>,
+    tick: Option<u32>,
+) -> Isometry<f32> {
+    let transform = tick
+        .and_then(|tick| history.and_then(|history| history.at(tick)))
+        .unwrap_or_else(|| (*transform).into());
+    Isometry::from_parts(
+        Vector::from(transform.translation).into(),
+        transform.rotation.into(),
+    )
+}
+
+/// Character transforms on recent network ticks.
+/// Recorded only on server for lag compensation.
+#[derive(Component, Default)]
+pub(super) struct TransformHistory(VecDeque<(u32, Transform)>);
+
+impl TransformHistory {
+    fn record(&mut self, tick: u32, transform: Transform) {
+        match self.0.back_mut() {
+            Some((last_tick, last_transform)) if *last_tick == tick => *last_transform = transform,
+            _ => {
+                if self.0.len() == HISTORY_TICKS {
+                    self.0.pop_front();
+                }
+                self.0.push_back((tick, transform));
+            }
+        }
+    }
+
+    /// Returns transform on the `tick` or `None` if it is too old.
+    fn at(&self, tick: u32) -> Option<Transform> {
+        self.0
+            .iter()
+            .rev()
+            .find(|(recorded_tick, _)| *recorded_tick <= tick)
+            .map(|(_, transform)| *transform)
+    }
+}
+
+/// Last server tick perceived by the client that controls the character.
+/// Updated from client requests on server and used to rewind characters for lag compensation.
+#[derive(Component, Clone, Copy)]
+pub(super) struct PerceivedTick(pub(super) u32);
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::system::SystemState;
+
+    use super::*;
+
+    #[test]
+    fn history_records_ticks() {
+        let mut history = TransformHistory::default();
+        for tick in 0..HISTORY_TICKS as u32 + 2 {
+            history.record(tick, Transform::from_xyz(tick as f32, 0.0, 0.0));
+        }
+        history.record(
+            HISTORY_TICKS as u32 + 1,
+            Transform::from_xyz(-1.0, 0.0, 0.0),
+        );
+
+        assert_eq!(history.0.len(), HISTORY_TICKS, "History should be limited");
+        assert!(history.at(0).is_none(), "Old ticks should be dropped");
+        assert_eq!(
+            history.at(5).unwrap().translation.x,
+            5.0,
+            "Recorded tick should be returned"
+        );
+        assert_eq!(
+            history.at(HISTORY_TICKS as u32 + 1).unwrap().translation.x,
+            -1.0,
+            "Transform on the same tick should be overwritten"
+        );
+    }
+
+    #[test]
+    fn lag_compensated_hit() {
+        let mut world = World::new();
+        world.insert_resource(RapierContext::default());
+
+        const PAST_TICK: u32 = 3;
+        let mut history = TransformHistory::default();
+        history.record(PAST_TICK, Transform::default());
+        let shooter = world.spawn().id();
+        let character = world
+            .spawn()
+            .insert(GlobalTransform::from_xyz(5.0, 0.0, 0.0))
+            .insert(Collider::capsule_y(0.5, 0.5))
+            .insert(Health::default())
+            .insert(history)
+            .id();
+
+        let mut system_state: SystemState<Hitscan> = SystemState::new(&mut world);
+        let hitscan = system_state.get(&world);
+
+        const ORIGIN: Vec3 = Vec3::new(0.0, 0.0, -5.0);
+        assert!(
+            hitscan.cast(ORIGIN, Vec3::Z, 10.0, None, shooter).is_none(),
+            "Ray should miss the current character position"
+        );
+
+        let (hit, distance) = hitscan
+            .cast(ORIGIN, Vec3::Z, 10.0, Some(PAST_TICK), shooter)
+            .expect("Ray should hit the character position on the perceived tick");
+        assert_eq!(hit, character);
+        assert!(distance < 5.0);
+
+        assert!(
+            hitscan
+                .cast(ORIGIN, Vec3::Z, 10.0, Some(PAST_TICK), character)
+                .is_none(),
+            "Ignored character shouldn't be hit"
+        );
+    }
+
+    #[test]
+    fn arc_hits() {
+        let mut world = World::new();
+        world.insert_resource(RapierContext::default());
+
+        let attacker = world.spawn().id();
+        let mut spawn_character = |translation, collider| {
+            world
+                .spawn()
+                .insert(GlobalTransform::from_translation(translation))
+                .insert(collider)
+                .insert(Health::default())
+                .id()
+        };
+        let front = spawn_character(Vec3::new(0.0, 0.0, -2.0), Collider::capsule_y(0.5, 0.5));
+        let side = spawn_character(Vec3::new(1.5, 0.0, -1.5), Collider::capsule_y(0.5, 0.5));
+        let behind = spawn_character(Vec3::new(0.0, 0.0, 2.0), Collider::capsule_y(0.5, 0.5));
+        let far = spawn_character(Vec3::new(0.0, 0.0, -4.0), Collider::capsule_y(0.5, 0.5));
+        let small = spawn_character(
+            Quat::from_rotation_y(22.5_f32.to_radians()) * -Vec3::Z * 2.5,
+            Collider::ball(0.1),
+        );
+
+        let mut system_state: SystemState<Hitscan> = SystemState::new(&mut world);
+        let hitscan = system_state.get(&world);
+
+        let hits = hitscan.cast_arc(
+            Vec3::ZERO,
+            -Vec3::Z,
+            3.0,
+            std::f32::consts::FRAC_PI_2,
+            None,
+            attacker,
+        );
+        assert!(hits.contains(&front), "Character in front should be hit");
+        assert!(
+            hits.contains(&side),
+            "Character within the arc should be hit"
+        );
+        assert!(!hits.contains(&behind), "Character behind shouldn't be hit");
+        assert!(
+            !hits.contains(&far),
+            "Character out of range shouldn't be hit"
+        );
+        assert!(
+            hits.contains(&small),
+            "Small character within the arc should be hit regardless of its direction"
+        );
+    }
+
+    #[test]
+    fn only_characters_hit() {
+        let mut world = World::new();
+        world.insert_resource(RapierContext::default());
+
+        let shooter = world.spawn().id();
+        world
+            .spawn()
+            .insert(GlobalTransform::from_xyz(0.0, 0.0, 2.0))
+            .insert(Collider::ball(0.5));
+        world
+            .spawn()
+            .insert(GlobalTransform::from_xyz(0.0, 0.0, 4.0))
+            .insert(Collider::ball(0.5))
+            .insert(Health::default())
+            .insert(Sensor);
+        let character = world
+            .spawn()
+            .insert(GlobalTransform::from_xyz(0.0, 0.0, 6.0))
+            .insert(Collider::capsule_y(0.5, 0.5))
+            .insert(Health::default())
+            .id();
+
+        let mut system_state: SystemState<Hitscan> = SystemState::new(&mut world);
+        let hitscan = system_state.get(&world);
+
+        let (hit, _) = hitscan
+            .cast(Vec3::ZERO, Vec3::Z, 10.0, None, shooter)
+            .expect("Ray should hit the character");
+        assert_eq!(
+            hit, character,
+            "Colliders without health and sensors should be ignored"
+        );
+    }
+}
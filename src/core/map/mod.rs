@@ -18,18 +18,20 @@
  */
 
 mod sky_roof;
+mod volume;
 
 use bevy::prelude::*;
 use strum::{Display, EnumIter, EnumString};
 
 use super::AssociatedAsset;
 use sky_roof::SkyRoofPlugin;
+use volume::VolumePlugin;
 
 pub(super) struct MapsPlugin;
 
 impl Plugin for MapsPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(SkyRoofPlugin);
+        app.add_plugin(SkyRoofPlugin).add_plugin(VolumePlugin);
     }
 }
 
@@ -55,6 +57,7 @@ mod tests {
     use crate::core::{
         game_state::GameState,
         headless::{self, HeadlessRenderPlugin},
+        health::HealthChanged,
         network::server::ServerSettings,
     };
 
@@ -75,6 +78,7 @@ mod tests {
     impl Plugin for TestMapPlugin {
         fn build(&self, app: &mut App) {
             app.add_loopless_state(GameState::InGame)
+                .add_event::<HealthChanged>()
                 .init_resource::<ServerSettings>()
                 .add_plugin(HeadlessRenderPlugin)
                 .add_plugin(HierarchyPlugin)
@@ -22,7 +22,10 @@ use bevy_rapier3d::prelude::*;
 use iyes_loopless::prelude::*;
 use std::f32::consts::PI;
 
-use super::Map;
+use super::{
+    volume::{DamageVolume, KillVolume, VolumeBundle},
+    Map,
+};
 use crate::core::{
     game_state::{GameState, InGameOnly},
     pickup::{PickupBundle, PickupKind},
@@ -80,6 +83,22 @@ impl SkyRoofPlugin {
             Vec3::new(4.0, 0.1, 1.0),
         ));
 
+        commands
+            .spawn_bundle(VolumeBundle::new(
+                "Out of bounds",
+                Vec3::new(0.0, -30.0, 0.0),
+                Vec3::new(200.0, 5.0, 200.0),
+            ))
+            .insert(KillVolume);
+
+        commands
+            .spawn_bundle(VolumeBundle::new(
+                "Burning floor",
+                Vec3::new(-4.0, 0.5, 0.0),
+                Vec3::new(1.5, 0.5, 1.5),
+            ))
+            .insert(DamageVolume::new(10));
+
         let map = asset_server.load(Map::SkyRoof.asset_path());
         commands
             .spawn_bundle(TransformBundle::default())
@@ -0,0 +1,409 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::core::{
+    game_state::{GameState, InGameOnly},
+    health::{Death, Health, HealthChanged, LastAttacker},
+    simulation::{SimulationAppExt, SimulationTime},
+    CollisionMask,
+};
+
+/// Handles map areas that affect characters inside them.
+pub(super) struct VolumePlugin;
+
+impl Plugin for VolumePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(Self::kill_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::damage_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl VolumePlugin {
+    /// Checks characters inside every tick to also kill those who respawned inside.
+    fn kill_system(
+        mut health_events: EventWriter<HealthChanged>,
+        volumes: Query<&CollidingEntities, With<KillVolume>>,
+        characters: Query<Option<&LastAttacker>, (With<Health>, Without<Death>)>,
+    ) {
+        for colliding_entities in volumes.iter() {
+            for character in colliding_entities.iter() {
+                if let Ok(last_attacker) = characters.get(character) {
+                    health_events.send(HealthChanged::environment(
+                        character,
+                        KillVolume::DELTA,
+                        last_attacker,
+                    ));
+                }
+            }
+        }
+    }
+
+    fn damage_system(
+        time: Res<SimulationTime>,
+        mut health_events: EventWriter<HealthChanged>,
+        mut volumes: Query<(&mut DamageVolume, &CollidingEntities)>,
+        characters: Query<Option<&LastAttacker>, (With<Health>, Without<Death>)>,
+    ) {
+        for (mut damage_volume, colliding_entities) in volumes.iter_mut() {
+            damage_volume.timer.tick(time.delta());
+            if !damage_volume.timer.just_finished() {
+                continue;
+            }
+
+            for character in colliding_entities.iter() {
+                if let Ok(last_attacker) = characters.get(character) {
+                    health_events.send(HealthChanged::environment(
+                        character,
+                        -(damage_volume.damage as i32),
+                        last_attacker,
+                    ));
+                }
+            }
+        }
+    }
+}
+
+/// Kills all characters that enter the volume.
+#[derive(Component)]
+pub(super) struct KillVolume;
+
+impl KillVolume {
+    /// Enough to kill a character regardless of damage modifiers
+    const DELTA: i32 = -i32::MAX;
+}
+
+/// Periodically damages all characters inside the volume.
+#[derive(Component)]
+pub(super) struct DamageVolume {
+    damage: u32,
+    timer: Timer,
+}
+
+impl DamageVolume {
+    /// Creates a new [`DamageVolume`] that deals `damage` every second.
+    pub(super) fn new(damage: u32) -> Self {
+        Self {
+            damage,
+            timer: Timer::from_seconds(1.0, true),
+        }
+    }
+}
+
+/// A sensor for characters with the specified shape.
+/// Should be combined with [`KillVolume`] or [`DamageVolume`].
+#[derive(Bundle)]
+pub(super) struct VolumeBundle {
+    name: Name,
+    sensor: Sensor,
+    collider: Collider,
+    collision_groups: CollisionGroups,
+    colliding_entities: CollidingEntities,
+    active_events: ActiveEvents,
+    ingame_only: InGameOnly,
+
+    #[bundle]
+    transform: TransformBundle,
+}
+
+impl VolumeBundle {
+    /// Creates a new [`VolumeBundle`] with a cuboid shape of `half_extents` at `translation`.
+    pub(super) fn new(name: &'static str, translation: Vec3, half_extents: Vec3) -> Self {
+        Self {
+            name: name.into(),
+            sensor: Sensor,
+            collider: Collider::cuboid(half_extents.x, half_extents.y, half_extents.z),
+            collision_groups: CollisionGroups {
+                memberships: CollisionMask::VOLUME.bits(),
+                filters: CollisionMask::CHARACTER.bits(),
+            },
+            colliding_entities: CollidingEntities::default(),
+            active_events: ActiveEvents::COLLISION_EVENTS,
+            ingame_only: InGameOnly,
+            transform: TransformBundle::from_transform(Transform::from_translation(translation)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::scene::ScenePlugin;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::core::{
+        headless::HeadlessRenderPlugin,
+        health::{HealthPlugin, Invulnerable},
+        hero::{DamageModifier, LocalHeroBundle},
+        player::{Deaths, Kills, PlayerBundle},
+    };
+
+    #[test]
+    fn kill_volume_kills() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        app.world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Kill volume", Vec3::ZERO, Vec3::ONE))
+            .insert(KillVolume);
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        app.world
+            .get::<Death>(character)
+            .expect("Character should die inside kill volume");
+        assert_eq!(
+            app.world.get::<Health>(character).unwrap().current,
+            0,
+            "Character health should drop to 0"
+        );
+        assert_eq!(
+            app.world.get::<Kills>(character).unwrap().0,
+            0,
+            "Falling out without attacker shouldn't count as a kill"
+        );
+    }
+
+    #[test]
+    fn kill_volume_ignores_invulnerability() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        app.world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Kill volume", Vec3::ZERO, Vec3::ONE))
+            .insert(KillVolume);
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Invulnerable)
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        assert!(
+            app.world.entity(character).contains::<Death>(),
+            "Invulnerable character should die inside kill volume"
+        );
+    }
+
+    #[test]
+    fn kill_volume_kills_staying_inside() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        app.world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Kill volume", Vec3::ZERO, Vec3::ONE))
+            .insert(KillVolume);
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Death)
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Deaths>(character).unwrap().0,
+            0,
+            "Dead character shouldn't be killed again"
+        );
+
+        app.world.entity_mut(character).remove::<Death>();
+
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Deaths>(character).unwrap().0,
+            1,
+            "Character should be killed after becoming alive inside kill volume"
+        );
+    }
+
+    #[test]
+    fn kill_volume_credits_last_attacker() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        let attacker = app
+            .world
+            .spawn()
+            .insert_bundle(PlayerBundle::default())
+            .insert(DamageModifier::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Kill volume", Vec3::ZERO, Vec3::ONE))
+            .insert(KillVolume);
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(LastAttacker::new(attacker))
+            .id();
+
+        app.update();
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Deaths>(character).unwrap().0,
+            1,
+            "Character should die inside kill volume"
+        );
+        assert_eq!(
+            app.world.get::<Kills>(attacker).unwrap().0,
+            1,
+            "Kill should be credited to the last attacker"
+        );
+    }
+
+    #[test]
+    fn kill_volume_counts_death_once() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        let attacker = app
+            .world
+            .spawn()
+            .insert_bundle(PlayerBundle::default())
+            .insert(DamageModifier::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Kill volume", Vec3::ZERO, Vec3::ONE))
+            .insert(KillVolume);
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(LastAttacker::new(attacker))
+            .id();
+
+        for _ in 0..6 {
+            app.update();
+        }
+
+        assert_eq!(
+            app.world.get::<Deaths>(character).unwrap().0,
+            1,
+            "Death should be counted only once while staying inside kill volume"
+        );
+        assert_eq!(
+            app.world.get::<Kills>(attacker).unwrap().0,
+            1,
+            "Kill should be credited only once"
+        );
+    }
+
+    #[test]
+    fn damage_volume_damages() {
+        let mut app = App::new();
+        app.add_plugin(TestVolumePlugin);
+
+        const DAMAGE: u32 = 10;
+        let volume = app
+            .world
+            .spawn()
+            .insert_bundle(VolumeBundle::new("Damage volume", Vec3::ZERO, Vec3::ONE))
+            .insert(DamageVolume::new(DAMAGE))
+            .id();
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+
+        app.update();
+        app.update();
+
+        let mut damage_volume = app.world.get_mut::<DamageVolume>(volume).unwrap();
+        let duration_left = damage_volume.timer.duration() - damage_volume.timer.elapsed();
+        damage_volume
+            .timer
+            .tick(duration_left - Duration::from_nanos(1)); // Tick to almost end to trigger just_finished inside the system
+
+        app.update();
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Health>(character).unwrap().current,
+            Health::default().max - DAMAGE,
+            "Character inside damage volume should receive damage"
+        );
+    }
+
+    struct TestVolumePlugin;
+
+    impl Plugin for TestVolumePlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(TransformPlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(HealthPlugin)
+                .add_plugin(VolumePlugin);
+        }
+    }
+
+    #[derive(Bundle)]
+    struct DummyCharacterBundle {
+        health: Health,
+        damage_modifier: DamageModifier,
+        transform: Transform,
+
+        #[bundle]
+        player_bundle: PlayerBundle,
+
+        #[bundle]
+        local_hero_bundle: LocalHeroBundle,
+    }
+
+    impl Default for DummyCharacterBundle {
+        fn default() -> Self {
+            Self {
+                health: Health::default(),
+                damage_modifier: DamageModifier::default(),
+                transform: Transform::default(),
+                player_bundle: PlayerBundle::default(),
+                local_hero_bundle: LocalHeroBundle::default(),
+            }
+        }
+    }
+}
@@ -22,49 +22,56 @@ pub(super) mod cli;
 pub(super) mod control_actions;
 pub(super) mod cooldown;
 mod despawn_timer;
+mod determinism;
 #[cfg(feature = "developer")]
 mod developer;
-mod effect;
+pub(super) mod effect;
 pub(super) mod game_state;
 mod graphics;
 #[cfg(test)]
 pub(super) mod headless;
 pub(super) mod health;
 pub(super) mod hero;
+mod hitscan;
 pub(super) mod map;
 mod movement;
 pub(super) mod network;
 mod orbit_camera;
 mod pickup;
 pub(super) mod player;
+pub(super) mod pool;
+mod projectile;
 pub(super) mod session;
 pub(super) mod settings;
+mod simulation;
 
 use bevy::prelude::*;
-use bevy_rapier3d::prelude::*;
 use bitflags::bitflags;
 use derive_more::From;
 
 use ability::AbilityPlugin;
 use cli::Opts;
 use control_actions::ControlActionsPlugin;
-use despawn_timer::DespawnTimer;
 use despawn_timer::DespawnTimerPlugin;
+use determinism::DeterminismPlugin;
 #[cfg(feature = "developer")]
 use developer::DeveloperPlugin;
 use effect::EffectPlugin;
 use game_state::AppStatePlugin;
-use game_state::InGameOnly;
 use health::HealthPlugin;
 use hero::HeroPlugin;
+use hitscan::HitscanPlugin;
 use map::MapsPlugin;
 use movement::MovementPlugin;
 use network::NetworkPlugin;
 use orbit_camera::OrbitCameraPlugin;
 use pickup::PickupPlugin;
 use player::PlayerPlugin;
+use pool::PoolPlugin;
+use projectile::ProjectilePlugin;
 use session::SessionPlugin;
 use settings::SettingsPlugin;
+use simulation::SimulationPlugin;
 
 pub(super) struct CorePlugin;
 
@@ -73,9 +80,12 @@ impl Plugin for CorePlugin {
         app.init_resource::<Opts>()
             .add_plugin(NetworkPlugin)
             .add_plugin(SettingsPlugin)
+            .add_plugin(SimulationPlugin)
+            .add_plugin(DeterminismPlugin)
             .add_plugin(AppStatePlugin)
             .add_plugin(HealthPlugin)
             .add_plugin(HeroPlugin)
+            .add_plugin(HitscanPlugin)
             .add_plugin(ControlActionsPlugin)
             .add_plugin(AbilityPlugin)
             .add_plugin(OrbitCameraPlugin)
@@ -83,6 +93,8 @@ impl Plugin for CorePlugin {
             .add_plugin(MovementPlugin)
             .add_plugin(MapsPlugin)
             .add_plugin(PlayerPlugin)
+            .add_plugin(PoolPlugin)
+            .add_plugin(ProjectilePlugin)
             .add_plugin(SessionPlugin)
             .add_plugin(DespawnTimerPlugin)
             .add_plugin(EffectPlugin);
@@ -102,6 +114,8 @@ bitflags! {
         const CHARACTER = 0b00000010;
         const PROJECTILE = 0b00000100;
         const PICKUP = 0b00001000;
+        const VOLUME = 0b00010000;
+        const ZONE = 0b00100000;
     }
 }
 
@@ -109,42 +123,6 @@ bitflags! {
 #[derive(Component, From)]
 struct Owner(Entity);
 
-#[derive(Bundle)]
-pub(super) struct ProjectileBundle {
-    name: Name,
-    rigid_body: RigidBody,
-    collider: Collider,
-    collision_groups: CollisionGroups,
-    velocity: Velocity,
-    despawn_timer: DespawnTimer,
-    colliding_entities: CollidingEntities,
-    active_events: ActiveEvents,
-    ingame_only: InGameOnly,
-
-    #[bundle]
-    pbr: PbrBundle,
-}
-
-impl Default for ProjectileBundle {
-    fn default() -> Self {
-        Self {
-            name: "Projectile".into(),
-            rigid_body: RigidBody::KinematicVelocityBased,
-            collider: Collider::capsule_y(0.5, 0.5),
-            collision_groups: CollisionGroups {
-                memberships: CollisionMask::PROJECTILE.bits(),
-                filters: (CollisionMask::all() ^ CollisionMask::PROJECTILE).bits(),
-            },
-            velocity: Velocity::default(),
-            despawn_timer: DespawnTimer::from_secs(4),
-            colliding_entities: CollidingEntities::default(),
-            active_events: ActiveEvents::COLLISION_EVENTS,
-            ingame_only: InGameOnly,
-            pbr: PbrBundle::default(),
-        }
-    }
-}
-
 /// Trait to map enumerations with associated assets
 trait AssociatedAsset {
     /// Returns path to associated asset
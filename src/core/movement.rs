@@ -17,64 +17,584 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use approx::abs_diff_eq;
 use bevy::prelude::*;
 use bevy_rapier3d::prelude::*;
+use derive_more::From;
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::*;
 
 use super::{
-    control_actions::ControlAction, game_state::GameState, hero::SpeedModifier,
+    control_actions::ControlAction,
+    effect::status_effect::{Rooted, Stunned},
+    game_state::GameState,
+    health::{Death, HealthChanged, Invulnerable, LastAttacker},
+    hero::SpeedModifier,
+    network::server,
     orbit_camera::CameraTarget,
+    pool::Stamina,
+    simulation::{SimulationAppExt, SimulationTime},
+    CollisionMask,
 };
 
 const MOVE_SPEED: f32 = 10.0;
-const MOVEMENT_INTERPOLATION_SPEED: f32 = 6.0;
-const AIR_INTERPOLATION_SPEED: f32 = 0.9;
+const GROUND_ACCELERATION: f32 = 60.0;
+const AIR_ACCELERATION: f32 = 10.0;
 const JUMP_IMPULSE: f32 = 5.0;
-const FLOOR_VELOCITY_EPSILON: f32 = 0.05;
+const SPRINT_SPEED_MULTIPLIER: f32 = 1.5;
+const SPRINT_STAMINA_PER_SECOND: f32 = 30.0;
+const CROUCH_SPEED_MULTIPLIER: f32 = 0.5;
+/// Capsule half-height multiplier while crouching.
+const CROUCH_HEIGHT_MULTIPLIER: f32 = 0.5;
+const DODGE_SPEED: f32 = 20.0;
+const DODGE_SECS: f32 = 0.25;
+const DODGE_COOLDOWN_SECS: f32 = 1.0;
+const DODGE_STAMINA: f32 = 30.0;
+/// Maximum distance to the ground below the character to consider it grounded.
+const GROUND_CHECK_DISTANCE: f32 = 0.1;
+/// Offset for shape casts to avoid hitting surfaces the character already touches.
+const SKIN_WIDTH: f32 = 0.05;
+const MAX_SLOPE_DEGREES: f32 = 45.0;
+const MAX_STEP_HEIGHT: f32 = 0.4;
+const STEP_PROBE_DISTANCE: f32 = 0.2;
+/// Time after leaving the ground during which the character still can jump.
+const COYOTE_SECS: f32 = 0.15;
+/// Time during which the jump will be performed if requested before landing.
+const JUMP_BUFFER_SECS: f32 = 0.15;
+const SAFE_FALL_SPEED: f32 = 15.0;
+const FALL_DAMAGE_PER_SPEED: f32 = 5.0;
 
 pub(super) struct MovementPlugin;
 
 impl Plugin for MovementPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::movement_system.run_in_state(GameState::InGame));
+        app.add_tick_system(Self::ground_detection_system.run_in_state(GameState::InGame))
+            .add_tick_system(
+                Self::movement_system
+                    .run_in_state(GameState::InGame)
+                    .after(Self::ground_detection_system),
+            )
+            .add_tick_system(Self::dodge_system.run_in_state(GameState::InGame))
+            .add_tick_system(
+                Self::fall_damage_system
+                    .run_in_state(GameState::InGame)
+                    .run_if(server::fall_damage)
+                    .after(Self::ground_detection_system),
+            );
     }
 }
 
 impl MovementPlugin {
+    /// Updates [`CharacterController`] ground state by casting character collider down.
+    /// Gravity is disabled on walkable ground to avoid sliding down slopes.
+    fn ground_detection_system(
+        time: Res<SimulationTime>,
+        rapier_ctx: Res<RapierContext>,
+        mut characters: Query<(
+            Entity,
+            &Transform,
+            &Collider,
+            &Velocity,
+            &mut GravityScale,
+            &mut CharacterController,
+        )>,
+    ) {
+        for (character, transform, collider, velocity, mut gravity_scale, mut controller) in
+            characters.iter_mut()
+        {
+            // Ignore the ground the character jumps from until it starts falling
+            controller.jumping &= velocity.linvel.y > 0.0;
+
+            let ground = if controller.jumping {
+                None
+            } else {
+                rapier_ctx
+                    .cast_shape(
+                        transform.translation + Vec3::Y * SKIN_WIDTH,
+                        transform.rotation,
+                        -Vec3::Y,
+                        collider,
+                        SKIN_WIDTH + GROUND_CHECK_DISTANCE,
+                        world_filter(character),
+                    )
+                    .map(|(ground, _)| {
+                        // Shape cast normals are in local space, so use ray to get the world normal
+                        let normal = rapier_ctx
+                            .cast_ray_and_get_normal(
+                                transform.translation,
+                                -Vec3::Y,
+                                f32::MAX,
+                                true,
+                                world_filter(character),
+                            )
+                            .filter(|(entity, _)| *entity == ground)
+                            .map_or(Vec3::Y, |(_, intersection)| intersection.normal);
+                        (ground, normal)
+                    })
+                    .filter(|(_, normal)| {
+                        normal.angle_between(Vec3::Y) <= MAX_SLOPE_DEGREES.to_radians()
+                    })
+            };
+
+            match ground {
+                Some((ground, normal)) => {
+                    controller.grounded = true;
+                    controller.ground = Some(ground);
+                    controller.ground_normal = normal;
+                    controller.air_secs = 0.0;
+                    gravity_scale.0 = 0.0;
+                }
+                None => {
+                    controller.grounded = false;
+                    controller.ground = None;
+                    controller.ground_normal = Vec3::Y;
+                    controller.air_secs += time.delta_seconds();
+                    gravity_scale.0 = 1.0;
+                }
+            }
+        }
+    }
+
     fn movement_system(
-        time: Res<Time>,
-        cameras: Query<(&Transform, &CameraTarget)>,
-        mut characters: Query<(&SpeedModifier, &ActionState<ControlAction>, &mut Velocity)>,
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        rapier_ctx: Res<RapierContext>,
+        cameras: Query<(&Transform, &CameraTarget), Without<CharacterController>>,
+        mut characters: Query<(
+            &BaseSpeed,
+            &SpeedModifier,
+            &ActionState<ControlAction>,
+            &Collider,
+            &mut Transform,
+            &mut Velocity,
+            &mut CharacterController,
+            Option<&mut Stamina>,
+            Option<&mut AirJumps>,
+            Option<&Dodging>,
+            Option<&Stunned>,
+            Option<&Rooted>,
+        )>,
+        platforms: Query<(&Velocity, &GlobalTransform), Without<CharacterController>>,
     ) {
         for (camera_transform, camera_target) in cameras.iter() {
-            let (speed_modifier, action_state, mut velocity) =
-                characters.get_mut(camera_target.0).unwrap();
+            let (
+                base_speed,
+                speed_modifier,
+                action_state,
+                collider,
+                mut transform,
+                mut velocity,
+                mut controller,
+                mut stamina,
+                mut air_jumps,
+                dodging,
+                stunned,
+                rooted,
+            ) = characters.get_mut(camera_target.0).unwrap();
+            let immobilized = stunned.is_some() || rooted.is_some();
 
-            let falling_velocity = velocity.linvel.y; // Save Y velocity to avoid it interpolation
-            let on_floor = abs_diff_eq!(falling_velocity, 0.0, epsilon = FLOOR_VELOCITY_EPSILON);
-            let interpolation_speed = if on_floor {
-                MOVEMENT_INTERPOLATION_SPEED
+            let direction = if immobilized {
+                Vec3::ZERO
             } else {
-                AIR_INTERPOLATION_SPEED
+                movement_direction(action_state, camera_transform.rotation)
+            };
+            let mut speed = base_speed.0 * speed_modifier.0;
+            let crouch_pressed = action_state.pressed(ControlAction::Crouch);
+            if crouch_pressed && !controller.crouching {
+                if let Some(capsule) = collider.raw.as_capsule() {
+                    let half_height = capsule.half_height();
+                    let crouch_half_height = half_height * CROUCH_HEIGHT_MULTIPLIER;
+                    // Lower the center to keep the feet on the ground
+                    controller.crouch_offset = half_height - crouch_half_height;
+                    transform.translation.y -= controller.crouch_offset;
+                    commands
+                        .entity(camera_target.0)
+                        .insert(Collider::capsule_y(crouch_half_height, capsule.radius));
+                }
+                controller.crouching = true;
+            } else if !crouch_pressed
+                && controller.crouching
+                && rapier_ctx
+                    .cast_shape(
+                        transform.translation,
+                        transform.rotation,
+                        Vec3::Y,
+                        collider,
+                        controller.crouch_offset * 2.0,
+                        world_filter(camera_target.0),
+                    )
+                    .is_none()
+            {
+                // Stand up only if there is enough room above
+                if let Some(capsule) = collider.raw.as_capsule() {
+                    transform.translation.y += controller.crouch_offset;
+                    commands.entity(camera_target.0).insert(Collider::capsule_y(
+                        capsule.half_height() + controller.crouch_offset,
+                        capsule.radius,
+                    ));
+                }
+                controller.crouch_offset = 0.0;
+                controller.crouching = false;
+            }
+            if controller.crouching {
+                speed *= CROUCH_SPEED_MULTIPLIER;
+            } else if direction != Vec3::ZERO
+                && action_state.pressed(ControlAction::Sprint)
+                && spend_stamina(
+                    stamina.as_deref_mut(),
+                    SPRINT_STAMINA_PER_SECOND * time.delta_seconds(),
+                )
+            {
+                speed *= SPRINT_SPEED_MULTIPLIER;
+            }
+            let motion = direction * speed;
+
+            controller.dodge_cooldown_secs =
+                (controller.dodge_cooldown_secs - time.delta_seconds()).max(0.0);
+            let mut dodge_velocity = dodging.map(|dodging| dodging.velocity);
+            if dodge_velocity.is_none()
+                && !immobilized
+                && action_state.just_pressed(ControlAction::Dodge)
+                && controller.dodge_cooldown_secs == 0.0
+                && spend_stamina(stamina.as_deref_mut(), DODGE_STAMINA)
+            {
+                // Dodge backward if no direction is pressed
+                let dodge_direction = if direction == Vec3::ZERO {
+                    let backward = camera_transform.rotation * Vec3::Z;
+                    Vec3::new(backward.x, 0.0, backward.z).normalize_or_zero()
+                } else {
+                    direction
+                };
+                let velocity = dodge_direction * DODGE_SPEED * speed_modifier.0;
+                commands
+                    .entity(camera_target.0)
+                    .insert(Dodging::new(velocity))
+                    .insert(Invulnerable);
+                controller.dodge_cooldown_secs = DODGE_COOLDOWN_SECS;
+                dodge_velocity = Some(velocity);
+            }
+
+            let platform_velocity = controller
+                .ground
+                .and_then(|ground| platforms.get(ground).ok())
+                .map_or(Vec3::ZERO, |(platform_velocity, platform_transform)| {
+                    platform_velocity.linvel
+                        + platform_velocity
+                            .angvel
+                            .cross(transform.translation - platform_transform.translation)
+                });
+            let horizontal_velocity = match dodge_velocity {
+                Some(dodge_velocity) => dodge_velocity,
+                None => {
+                    // Accelerate relative to the platform the character was standing on
+                    let mut horizontal_velocity = velocity.linvel - controller.platform_velocity;
+                    horizontal_velocity.y = 0.0;
+                    let acceleration = if controller.grounded {
+                        GROUND_ACCELERATION
+                    } else {
+                        AIR_ACCELERATION
+                    };
+                    move_towards(
+                        horizontal_velocity,
+                        motion,
+                        acceleration * time.delta_seconds(),
+                    )
+                }
             };
+            controller.platform_velocity = platform_velocity;
+
+            // Buffer only the press to avoid jumping repeatedly while holding
+            let jump_pressed = action_state.just_pressed(ControlAction::Jump) && !immobilized;
+            if jump_pressed {
+                controller.jump_request_secs = Some(0.0);
+            } else if let Some(secs) = controller.jump_request_secs {
+                let secs = secs + time.delta_seconds();
+                controller.jump_request_secs = if secs <= JUMP_BUFFER_SECS {
+                    Some(secs)
+                } else {
+                    None
+                };
+            }
+
+            if let Some(air_jumps) = &mut air_jumps {
+                if controller.grounded {
+                    air_jumps.left = air_jumps.max;
+                }
+            }
+            let ground_jump = controller.jump_request_secs.is_some()
+                && controller.air_secs <= COYOTE_SECS
+                && !controller.jumping;
+            let air_jump = !ground_jump
+                && jump_pressed
+                && controller.air_secs > COYOTE_SECS
+                && air_jumps
+                    .as_ref()
+                    .map_or(false, |air_jumps| air_jumps.left != 0);
+
+            if ground_jump || air_jump {
+                if air_jump {
+                    if let Some(air_jumps) = &mut air_jumps {
+                        air_jumps.left -= 1;
+                    }
+                }
+                controller.jump_request_secs = None;
+                controller.jumping = true;
+                // Disallow jumping again from the ground in the air
+                controller.air_secs = f32::MAX;
+                velocity.linvel = horizontal_velocity + platform_velocity;
+                velocity.linvel.y = platform_velocity.y + JUMP_IMPULSE;
+            } else if controller.grounded {
+                if motion != Vec3::ZERO {
+                    if let Some(height) = step_height(
+                        &rapier_ctx,
+                        camera_target.0,
+                        &transform,
+                        collider,
+                        direction,
+                    ) {
+                        transform.translation.y += height;
+                    }
+                }
 
-            let motion = movement_direction(action_state, camera_transform.rotation)
-                * MOVE_SPEED
-                * speed_modifier.0;
-            velocity.linvel = velocity
-                .linvel
-                .lerp(motion, interpolation_speed * time.delta_seconds());
-            velocity.linvel.y = falling_velocity;
+                // Follow the ground surface to keep contact on slopes
+                let normal = controller.ground_normal;
+                let slope_velocity = -(horizontal_velocity.x * normal.x
+                    + horizontal_velocity.z * normal.z)
+                    / normal.y;
+                velocity.linvel =
+                    horizontal_velocity + Vec3::Y * slope_velocity + platform_velocity;
+            } else {
+                velocity.linvel.x = horizontal_velocity.x;
+                velocity.linvel.z = horizontal_velocity.z;
+            }
+        }
+    }
+
+    /// Ends dodges after their duration or when the character becomes immobilized.
+    fn dodge_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        mut characters: Query<(Entity, &mut Dodging, Option<&Stunned>, Option<&Rooted>)>,
+    ) {
+        for (character, mut dodging, stunned, rooted) in characters.iter_mut() {
+            dodging.timer.tick(time.delta());
+            if dodging.timer.finished() || stunned.is_some() || rooted.is_some() {
+                commands
+                    .entity(character)
+                    .remove::<Dodging>()
+                    .remove::<Invulnerable>();
+            }
+        }
+    }
 
-            if on_floor && action_state.pressed(ControlAction::Jump) {
-                velocity.linvel.y += JUMP_IMPULSE;
+    fn fall_damage_system(
+        mut health_events: EventWriter<HealthChanged>,
+        mut characters: Query<
+            (
+                Entity,
+                &Velocity,
+                &CharacterController,
+                &mut FallSpeed,
+                Option<&LastAttacker>,
+            ),
+            Without<Death>,
+        >,
+    ) {
+        for (character, velocity, controller, mut fall_speed, last_attacker) in
+            characters.iter_mut()
+        {
+            if controller.grounded {
+                let damage = fall_damage(fall_speed.0);
+                if damage != 0 {
+                    health_events.send(HealthChanged::environment(
+                        character,
+                        -(damage as i32),
+                        last_attacker,
+                    ));
+                }
+                fall_speed.0 = 0.0;
+            } else {
+                fall_speed.0 = -velocity.linvel.y;
             }
         }
     }
 }
 
+/// Ground state of the character.
+/// Updated by casting the character collider down.
+#[derive(Component)]
+pub(super) struct CharacterController {
+    /// Whether the character stands on a walkable surface.
+    grounded: bool,
+    ground: Option<Entity>,
+    ground_normal: Vec3,
+    /// Velocity of the ground added to the character velocity.
+    platform_velocity: Vec3,
+    /// Time since the character left the ground.
+    air_secs: f32,
+    /// Time since the jump was requested, [`None`] if there is no pending jump.
+    jump_request_secs: Option<f32>,
+    /// Set on jump until the character starts falling.
+    jumping: bool,
+    /// Whether the character crouches with a shrunk collider.
+    crouching: bool,
+    /// Amount by which the collider center was lowered on crouch.
+    crouch_offset: f32,
+    /// Time left until the next dodge.
+    dodge_cooldown_secs: f32,
+}
+
+impl Default for CharacterController {
+    fn default() -> Self {
+        Self {
+            grounded: false,
+            ground: None,
+            ground_normal: Vec3::Y,
+            platform_velocity: Vec3::ZERO,
+            air_secs: f32::MAX,
+            jump_request_secs: None,
+            jumping: false,
+            crouching: false,
+            crouch_offset: 0.0,
+            dodge_cooldown_secs: 0.0,
+        }
+    }
+}
+
+/// Number of jumps the character can perform in the air.
+#[derive(Component)]
+pub(super) struct AirJumps {
+    max: u8,
+    left: u8,
+}
+
+impl AirJumps {
+    pub(super) fn new(max: u8) -> Self {
+        Self { max, left: max }
+    }
+}
+
+/// Directional dodge with fixed velocity.
+/// Inserted together with [`Invulnerable`].
+#[derive(Component)]
+struct Dodging {
+    velocity: Vec3,
+    timer: Timer,
+}
+
+impl Dodging {
+    fn new(velocity: Vec3) -> Self {
+        Self {
+            velocity,
+            timer: Timer::from_seconds(DODGE_SECS, false),
+        }
+    }
+}
+
+/// Falling speed of the character on the previous frame.
+/// Used to calculate fall damage on landing.
+#[derive(Component, Default)]
+pub(super) struct FallSpeed(f32);
+
+/// Movement speed of the character before [`SpeedModifier`].
+#[derive(Component, From)]
+pub(super) struct BaseSpeed(f32);
+
+impl Default for BaseSpeed {
+    fn default() -> Self {
+        Self(MOVE_SPEED)
+    }
+}
+
+/// Spends `amount` of stamina if there is enough.
+/// Characters without stamina can always spend it.
+fn spend_stamina(stamina: Option<&mut Stamina>, amount: f32) -> bool {
+    match stamina {
+        Some(stamina) if stamina.current < amount => false,
+        Some(stamina) => {
+            stamina.change(-amount);
+            true
+        }
+        None => true,
+    }
+}
+
+/// Filters only world geometry excluding the character itself.
+fn world_filter(character: Entity) -> QueryFilter<'static> {
+    QueryFilter::new()
+        .groups(InteractionGroups::new(
+            CollisionMask::CHARACTER.bits(),
+            CollisionMask::WORLD.bits(),
+        ))
+        .exclude_collider(character)
+}
+
+/// Returns height of the obstacle in front of the character if it can be stepped over.
+fn step_height(
+    rapier_ctx: &RapierContext,
+    character: Entity,
+    transform: &Transform,
+    collider: &Collider,
+    direction: Vec3,
+) -> Option<f32> {
+    // Check if there is an obstacle
+    rapier_ctx.cast_shape(
+        transform.translation + Vec3::Y * SKIN_WIDTH,
+        transform.rotation,
+        direction,
+        collider,
+        STEP_PROBE_DISTANCE,
+        world_filter(character),
+    )?;
+
+    // Check if the obstacle is low enough
+    let raised_translation = transform.translation + Vec3::Y * MAX_STEP_HEIGHT;
+    if rapier_ctx
+        .cast_shape(
+            raised_translation,
+            transform.rotation,
+            direction,
+            collider,
+            STEP_PROBE_DISTANCE,
+            world_filter(character),
+        )
+        .is_some()
+    {
+        return None;
+    }
+
+    let (_, toi) = rapier_ctx.cast_shape(
+        raised_translation + direction * STEP_PROBE_DISTANCE,
+        transform.rotation,
+        -Vec3::Y,
+        collider,
+        MAX_STEP_HEIGHT,
+        world_filter(character),
+    )?;
+    let height = MAX_STEP_HEIGHT - toi.toi;
+
+    if height > SKIN_WIDTH {
+        Some(height)
+    } else {
+        None
+    }
+}
+
+/// Moves `current` towards `target` by not more than `max_delta`.
+fn move_towards(current: Vec3, target: Vec3, max_delta: f32) -> Vec3 {
+    let delta = target - current;
+    let distance = delta.length();
+    if distance <= max_delta {
+        target
+    } else {
+        current + delta / distance * max_delta
+    }
+}
+
+/// Returns damage for landing with `fall_speed`
+fn fall_damage(fall_speed: f32) -> u32 {
+    ((fall_speed - SAFE_FALL_SPEED).max(0.0) * FALL_DAMAGE_PER_SPEED) as u32
+}
+
 fn movement_direction(action_state: &ActionState<ControlAction>, rotation: Quat) -> Vec3 {
     let mut direction = Vec3::ZERO;
     if action_state.pressed(ControlAction::Left) {
@@ -98,12 +618,12 @@ fn movement_direction(action_state: &ActionState<ControlAction>, rotation: Quat)
 
 #[cfg(test)]
 mod tests {
-    use approx::{assert_abs_diff_eq, assert_abs_diff_ne, assert_ulps_eq};
-    use bevy::scene::ScenePlugin;
+    use approx::{assert_abs_diff_eq, assert_ulps_eq};
+    use bevy::{ecs::event::Events, scene::ScenePlugin};
     use leafwing_input_manager::prelude::*;
 
     use super::*;
-    use crate::core::{headless::HeadlessRenderPlugin, Authority};
+    use crate::core::{headless::HeadlessRenderPlugin, network::server::ServerSettings, Authority};
 
     #[test]
     fn movement_direction_normalization() {
@@ -165,8 +685,8 @@ mod tests {
         app.update();
         app.update();
 
-        let velocity = app.world.entity(character).get::<Velocity>().unwrap();
-        assert_abs_diff_ne!(velocity.linvel.y, 0.0, epsilon = FLOOR_VELOCITY_EPSILON);
+        let controller = app.world.get::<CharacterController>(character).unwrap();
+        assert!(!controller.grounded, "Character shouldn't be grounded");
 
         let mut action_state = app
             .world
@@ -205,8 +725,8 @@ mod tests {
         app.update();
         app.update();
 
-        let velocity = app.world.entity(character).get::<Velocity>().unwrap();
-        assert_abs_diff_eq!(velocity.linvel.y, 0.0, epsilon = FLOOR_VELOCITY_EPSILON);
+        let controller = app.world.get::<CharacterController>(character).unwrap();
+        assert!(controller.grounded, "Character should be grounded");
 
         let mut action_state = app
             .world
@@ -256,7 +776,7 @@ mod tests {
 
             let previous_translation = app.world.get::<Transform>(character).unwrap().translation;
 
-            // Clean previous velocity to avoid interpolation
+            // Clean previous velocity to move only in the pressed direction
             app.world.get_mut::<Velocity>(character).unwrap().linvel = Vec3::ZERO;
 
             app.update();
@@ -273,7 +793,7 @@ mod tests {
 
     #[test]
     fn speed_modifier_respected() {
-        const SPEED_MODIFIER: f32 = 100.0;
+        const SPEED_MODIFIER: f32 = 0.5;
         let mut app = App::new();
         app.add_plugin(TestMovementPlugin);
 
@@ -291,6 +811,7 @@ mod tests {
 
         app.update();
 
+        app.world.get_mut::<Velocity>(character).unwrap().linvel = -Vec3::Z * MOVE_SPEED;
         let mut action_state = app
             .world
             .get_mut::<ActionState<ControlAction>>(character)
@@ -300,11 +821,366 @@ mod tests {
         app.update();
 
         let velocity = app.world.entity(character).get::<Velocity>().unwrap();
-        let time = app.world.resource::<Time>().delta_seconds();
+        let time = app.world.resource::<SimulationTime>().delta_seconds();
+        assert_ulps_eq!(-velocity.linvel.z, MOVE_SPEED - AIR_ACCELERATION * time);
+    }
+
+    #[test]
+    fn character_steps_up() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        const START_HEIGHT: f32 = 2.0;
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle {
+                transform: Transform::from_translation(Vec3::Y * START_HEIGHT),
+                ..Default::default()
+            })
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+        app.world.spawn().insert_bundle(DummyPlainBundle::default());
+        app.world.spawn().insert_bundle(DummyPlainBundle {
+            collider: Collider::cuboid(1.0, MAX_STEP_HEIGHT / 4.0, 1.0),
+            transform: Transform::from_xyz(0.0, 1.0 + MAX_STEP_HEIGHT / 4.0, -1.5),
+            ..Default::default()
+        });
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Forward);
+
+        app.update();
+        app.update();
+        app.update();
+
+        assert!(
+            app.world.get::<Transform>(character).unwrap().translation.y > START_HEIGHT,
+            "Character should step up on the low obstacle"
+        );
+    }
+
+    #[test]
+    fn character_moves_with_platform() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle {
+                transform: Transform::from_translation(Vec3::Y * 2.0),
+                ..Default::default()
+            })
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+
+        const PLATFORM_VELOCITY: Vec3 = Vec3::X;
+        app.world
+            .spawn()
+            .insert_bundle(DummyPlainBundle {
+                rigid_body: RigidBody::KinematicVelocityBased,
+                ..Default::default()
+            })
+            .insert(Velocity::linear(PLATFORM_VELOCITY));
+
+        app.update();
+        app.update();
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        assert_abs_diff_eq!(velocity.linvel.x, PLATFORM_VELOCITY.x, epsilon = 0.01);
+        assert_abs_diff_eq!(velocity.linvel.z, PLATFORM_VELOCITY.z, epsilon = 0.01);
+    }
+
+    #[test]
+    fn sprint_spends_stamina() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+
+        app.update();
+
+        app.world.get_mut::<Velocity>(character).unwrap().linvel =
+            -Vec3::Z * MOVE_SPEED * SPRINT_SPEED_MULTIPLIER;
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Forward);
+        action_state.press(ControlAction::Sprint);
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        assert_ulps_eq!(-velocity.linvel.z, MOVE_SPEED * SPRINT_SPEED_MULTIPLIER);
+
+        let stamina = app.world.get::<Stamina>(character).unwrap();
+        assert!(stamina.current < stamina.max, "Sprint should spend stamina");
+    }
+
+    #[test]
+    fn crouch_shrinks_collider() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+
+        app.update();
+
+        let half_height = capsule_half_height(&app.world, character);
+        app.world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap()
+            .press(ControlAction::Crouch);
+
+        app.update();
+
         assert_ulps_eq!(
-            -velocity.linvel.z,
-            MOVE_SPEED * SPEED_MODIFIER * MOVEMENT_INTERPOLATION_SPEED * time,
+            capsule_half_height(&app.world, character),
+            half_height * CROUCH_HEIGHT_MULTIPLIER
         );
+
+        app.world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap()
+            .release(ControlAction::Crouch);
+
+        app.update();
+
+        assert_ulps_eq!(capsule_half_height(&app.world, character), half_height);
+    }
+
+    #[test]
+    fn dodge_makes_invulnerable() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+
+        app.update();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Dodge);
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        // Should dodge backward without direction
+        assert_ulps_eq!(velocity.linvel.z, DODGE_SPEED);
+
+        let stamina = app.world.get::<Stamina>(character).unwrap();
+        assert_eq!(stamina.current, stamina.max - DODGE_STAMINA);
+
+        let character = app.world.entity(character);
+        assert!(character.contains::<Dodging>());
+        assert!(
+            character.contains::<Invulnerable>(),
+            "Character should be invulnerable while dodging"
+        );
+    }
+
+    #[test]
+    fn air_jumps_limited() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(AirJumps::new(1))
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCameraBundle::new(character.into()));
+
+        app.update();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Jump);
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        assert!(velocity.linvel.y > 0.0, "Character should jump in the air");
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.release(ControlAction::Jump);
+
+        app.update();
+
+        let previous_velocity = app.world.get::<Velocity>(character).unwrap().linvel;
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::Jump);
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(character).unwrap();
+        assert!(
+            velocity.linvel.y < previous_velocity.y,
+            "Character shouldn't jump more than allowed"
+        );
+    }
+
+    #[test]
+    fn immobilized_character_stays() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+
+        let stunned_character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Stunned)
+            .id();
+        let rooted_character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Rooted)
+            .id();
+        for character in [stunned_character, rooted_character] {
+            app.world
+                .spawn()
+                .insert_bundle(DummyCameraBundle::new(character.into()));
+        }
+
+        app.update();
+
+        for character in [stunned_character, rooted_character] {
+            let mut action_state = app
+                .world
+                .get_mut::<ActionState<ControlAction>>(character)
+                .unwrap();
+            action_state.press(ControlAction::Forward);
+            action_state.press(ControlAction::Jump);
+        }
+
+        app.update();
+
+        for character in [stunned_character, rooted_character] {
+            let velocity = app.world.get::<Velocity>(character).unwrap();
+            assert_eq!(
+                velocity.linvel.z, 0.0,
+                "Immobilized character shouldn't move"
+            );
+            assert!(
+                velocity.linvel.y <= 0.0,
+                "Immobilized character shouldn't jump"
+            );
+        }
+    }
+
+    #[test]
+    fn fall_damage_from_speed() {
+        assert_eq!(
+            fall_damage(SAFE_FALL_SPEED),
+            0,
+            "Landing at safe speed shouldn't cause damage"
+        );
+        assert_eq!(
+            fall_damage(SAFE_FALL_SPEED + 2.0),
+            (2.0 * FALL_DAMAGE_PER_SPEED) as u32,
+            "Damage should grow with speed above the safe speed"
+        );
+    }
+
+    #[test]
+    fn landing_damages() {
+        let mut app = App::new();
+        app.add_plugin(TestMovementPlugin);
+        app.insert_resource(ServerSettings {
+            fall_damage: true,
+            ..Default::default()
+        });
+
+        const FALL_SPEED: f32 = SAFE_FALL_SPEED * 2.0;
+        let character = app
+            .world
+            .spawn()
+            .insert(FallSpeed(FALL_SPEED))
+            .insert(Velocity::default())
+            .insert(CharacterController {
+                grounded: true,
+                ..Default::default()
+            })
+            .id();
+
+        app.update();
+
+        let mut health_events = app.world.resource_mut::<Events<HealthChanged>>();
+        let event = health_events
+            .drain()
+            .next()
+            .expect("Health change event should be emitted on hard landing");
+
+        assert_eq!(
+            event.target, character,
+            "Landed character should be damaged"
+        );
+        assert_eq!(
+            event.instigator, None,
+            "Fall damage without attacker should be caused by the environment"
+        );
+        assert_eq!(event.delta, -(fall_damage(FALL_SPEED) as i32));
+
+        assert_eq!(
+            app.world.get::<FallSpeed>(character).unwrap().0,
+            0.0,
+            "Fall speed should be updated after landing"
+        );
+    }
+
+    fn capsule_half_height(world: &World, character: Entity) -> f32 {
+        world
+            .get::<Collider>(character)
+            .unwrap()
+            .raw
+            .as_capsule()
+            .unwrap()
+            .half_height()
     }
 
     struct TestMovementPlugin;
@@ -312,9 +1188,10 @@ mod tests {
     impl Plugin for TestMovementPlugin {
         fn build(&self, app: &mut App) {
             app.add_loopless_state(GameState::InGame)
+                .add_event::<HealthChanged>()
+                .init_resource::<ServerSettings>()
                 .add_plugin(HeadlessRenderPlugin)
                 .add_plugin(ScenePlugin)
-                .add_plugin(InputManagerPlugin::<ControlAction>::default())
                 .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
                 .add_plugin(MovementPlugin);
         }
@@ -341,10 +1218,15 @@ mod tests {
 
     #[derive(Bundle)]
     struct DummyCharacterBundle {
+        base_speed: BaseSpeed,
         speed_modifier: SpeedModifier,
         rigid_body: RigidBody,
         collider: Collider,
         locked_axes: LockedAxes,
+        gravity_scale: GravityScale,
+        friction: Friction,
+        character_controller: CharacterController,
+        stamina: Stamina,
         transform: Transform,
         global_transform: GlobalTransform,
         velocity: Velocity,
@@ -355,10 +1237,18 @@ mod tests {
     impl Default for DummyCharacterBundle {
         fn default() -> Self {
             Self {
+                base_speed: BaseSpeed::default(),
                 speed_modifier: SpeedModifier::default(),
                 rigid_body: RigidBody::Dynamic,
                 collider: Collider::capsule_y(0.5, 0.5),
                 locked_axes: LockedAxes::ROTATION_LOCKED,
+                gravity_scale: GravityScale::default(),
+                friction: Friction {
+                    coefficient: 0.0,
+                    combine_rule: CoefficientCombineRule::Min,
+                },
+                character_controller: CharacterController::default(),
+                stamina: Stamina::default(),
                 transform: Transform::default(),
                 global_transform: GlobalTransform::default(),
                 velocity: Velocity::default(),
@@ -0,0 +1,332 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+use bevy::prelude::*;
+use bevy_renet::renet::RenetServer;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
+
+use super::{
+    client,
+    message::{ClientMessage, MessageReceived},
+    unreliable_message::{NetworkTick, ReceivedServerTick},
+};
+use crate::core::{
+    ability::AbilityRequested, control_actions::ControlAction, hero::Aim, hitscan::PerceivedTick,
+    player::ClientId, Authority,
+};
+
+/// Maximum age of the request in network ticks.
+const MAX_REQUEST_AGE: u32 = 10;
+/// Maximum distance between the aim origin and the character.
+const MAX_AIM_ORIGIN_DISTANCE: f32 = 10.0;
+
+/// Sends ability requests from client and validates them on server.
+/// Valid requests are turned into [`AbilityRequested`] events.
+pub(super) struct AbilityRequestPlugin;
+
+impl Plugin for AbilityRequestPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(Self::send_requests_system.run_if(client::connected))
+            .add_system(Self::receive_requests_system.run_if_resource_exists::<RenetServer>());
+    }
+}
+
+impl AbilityRequestPlugin {
+    fn send_requests_system(
+        mut client_events: EventWriter<ClientMessage>,
+        received_server_tick: Res<ReceivedServerTick>,
+        characters: Query<(&ActionState<ControlAction>, &Aim), With<Authority>>,
+    ) {
+        for (action_state, &aim) in characters.iter() {
+            for action in ControlAction::ABILITIES {
+                if action_state.just_pressed(action) {
+                    client_events.send(ClientMessage::AbilityRequest {
+                        action,
+                        aim,
+                        tick: received_server_tick.0,
+                    });
+                }
+            }
+        }
+    }
+
+    fn receive_requests_system(
+        mut commands: Commands,
+        mut receive_events: EventReader<MessageReceived>,
+        mut request_events: EventWriter<AbilityRequested>,
+        network_tick: Res<NetworkTick>,
+        mut characters: Query<(Entity, &ClientId, &Transform, &mut Aim)>,
+    ) {
+        for event in receive_events.iter() {
+            if let ClientMessage::AbilityRequest { action, aim, tick } = event.message {
+                let (character, _, transform, mut character_aim) = match characters
+                    .iter_mut()
+                    .find(|(_, client_id, ..)| client_id.0 == event.client_id)
+                {
+                    Some(result) => result,
+                    None => {
+                        warn!(
+                            "Received ability request from client {} without character",
+                            event.client_id
+                        );
+                        continue;
+                    }
+                };
+
+                if let Err(error) =
+                    validate_request(aim, transform.translation, tick, network_tick.0)
+                {
+                    warn!(
+                        "Rejected ability request from client {}: {}",
+                        event.client_id, error
+                    );
+                    continue;
+                }
+
+                *character_aim = aim;
+                commands.entity(character).insert(PerceivedTick(tick));
+                request_events.send(AbilityRequested {
+                    caster: character,
+                    action,
+                });
+            }
+        }
+    }
+}
+
+fn validate_request(
+    aim: Aim,
+    character_translation: Vec3,
+    tick: u32,
+    network_tick: u32,
+) -> Result<(), &'static str> {
+    if !aim.direction.is_finite() || (aim.direction.length() - 1.0).abs() > 0.01 {
+        return Err("aim direction isn't normalized");
+    }
+    if !aim.origin.is_finite()
+        || aim.origin.distance(character_translation) > MAX_AIM_ORIGIN_DISTANCE
+    {
+        return Err("aim origin is too far from the character");
+    }
+    if !aim.target_point.is_finite()
+        || aim.origin.distance(aim.target_point) > Aim::MAX_DISTANCE + 0.01
+    {
+        return Err("aim target is out of range");
+    }
+    if tick > network_tick {
+        return Err("tick is from the future");
+    }
+    if network_tick - tick > MAX_REQUEST_AGE {
+        return Err("request is too old");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{ecs::event::Events, input::InputPlugin};
+
+    use super::*;
+    use crate::core::{
+        ability::AbilityPlugin,
+        game_state::GameState,
+        network::tests::{NetworkPreset, TestNetworkPlugin},
+    };
+
+    #[test]
+    fn request_validation() {
+        const NETWORK_TICK: u32 = 20;
+        let aim = Aim::new(Vec3::ZERO, Vec3::X, None);
+        assert!(validate_request(aim, Vec3::ZERO, NETWORK_TICK, NETWORK_TICK).is_ok());
+        assert!(
+            validate_request(
+                Aim {
+                    direction: Vec3::X * 2.0,
+                    ..aim
+                },
+                Vec3::ZERO,
+                NETWORK_TICK,
+                NETWORK_TICK
+            )
+            .is_err(),
+            "Direction should be normalized"
+        );
+        assert!(
+            validate_request(
+                Aim {
+                    direction: Vec3::splat(f32::NAN),
+                    ..aim
+                },
+                Vec3::ZERO,
+                NETWORK_TICK,
+                NETWORK_TICK
+            )
+            .is_err(),
+            "Direction should be finite"
+        );
+        assert!(
+            validate_request(
+                aim,
+                Vec3::X * (MAX_AIM_ORIGIN_DISTANCE + 1.0),
+                NETWORK_TICK,
+                NETWORK_TICK
+            )
+            .is_err(),
+            "Aim origin should be near the character"
+        );
+        assert!(
+            validate_request(
+                Aim {
+                    target_point: Vec3::X * (Aim::MAX_DISTANCE + 1.0),
+                    ..aim
+                },
+                Vec3::ZERO,
+                NETWORK_TICK,
+                NETWORK_TICK
+            )
+            .is_err(),
+            "Aim target should be in range"
+        );
+        assert!(
+            validate_request(aim, Vec3::ZERO, NETWORK_TICK + 1, NETWORK_TICK).is_err(),
+            "Tick shouldn't be from the future"
+        );
+        assert!(
+            validate_request(
+                aim,
+                Vec3::ZERO,
+                NETWORK_TICK - MAX_REQUEST_AGE - 1,
+                NETWORK_TICK
+            )
+            .is_err(),
+            "Tick shouldn't be too old"
+        );
+    }
+
+    #[test]
+    fn request_received() {
+        let mut app = App::new();
+        app.add_plugin(TestAbilityRequestPlugin);
+
+        const CLIENT_ID: u64 = 1;
+        let character = app
+            .world
+            .spawn()
+            .insert(ClientId(CLIENT_ID))
+            .insert(Transform::default())
+            .insert(Aim::default())
+            .id();
+
+        let network_tick = app.world.resource::<NetworkTick>().0;
+        let aim = Aim::new(Vec3::ZERO, Vec3::X, None);
+        let mut receive_events = app.world.resource_mut::<Events<MessageReceived>>();
+        for direction in [aim.direction, Vec3::ZERO] {
+            receive_events.send(MessageReceived {
+                client_id: CLIENT_ID,
+                message: ClientMessage::AbilityRequest {
+                    action: ControlAction::Ability1,
+                    aim: Aim { direction, ..aim },
+                    tick: network_tick,
+                },
+            });
+        }
+
+        app.update();
+
+        let mut request_events = app.world.resource_mut::<Events<AbilityRequested>>();
+        let mut request_events = request_events.drain();
+        let event = request_events
+            .next()
+            .expect("Valid request should be accepted");
+        assert_eq!(event.caster, character, "Caster should match the client");
+        assert_eq!(event.action, ControlAction::Ability1);
+        assert!(
+            request_events.next().is_none(),
+            "Invalid request should be rejected"
+        );
+
+        assert_eq!(
+            *app.world.get::<Aim>(character).unwrap(),
+            aim,
+            "Character aim should be updated from the valid request"
+        );
+        assert_eq!(
+            app.world.get::<PerceivedTick>(character).unwrap().0,
+            network_tick,
+            "Perceived tick should be updated from the valid request"
+        );
+    }
+
+    #[test]
+    fn client_sends_request_without_local_activation() {
+        let mut app = App::new();
+        app.add_plugin(TestNetworkPlugin::new(NetworkPreset::ServerAndClient {
+            connected: true,
+        }))
+        .add_loopless_state(GameState::InGame)
+        .add_plugin(InputPlugin)
+        .add_event::<ClientMessage>()
+        .add_event::<MessageReceived>()
+        .init_resource::<NetworkTick>()
+        .init_resource::<ReceivedServerTick>()
+        .add_plugin(AbilityPlugin)
+        .add_plugin(AbilityRequestPlugin);
+
+        let mut action_state = ActionState::<ControlAction>::default();
+        action_state.press(ControlAction::Ability1);
+        app.world
+            .spawn()
+            .insert(action_state)
+            .insert(Aim::default())
+            .insert(Authority);
+
+        app.update();
+
+        let mut client_events = app.world.resource_mut::<Events<ClientMessage>>();
+        assert!(
+            matches!(
+                client_events.drain().next(),
+                Some(ClientMessage::AbilityRequest {
+                    action: ControlAction::Ability1,
+                    ..
+                })
+            ),
+            "Client should send the request to the server"
+        );
+
+        let mut request_events = app.world.resource_mut::<Events<AbilityRequested>>();
+        assert!(
+            request_events.drain().next().is_none(),
+            "Client shouldn't request local activation"
+        );
+    }
+
+    struct TestAbilityRequestPlugin;
+
+    impl Plugin for TestAbilityRequestPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_event::<MessageReceived>()
+                .add_event::<AbilityRequested>()
+                .init_resource::<NetworkTick>()
+                .add_plugin(TestNetworkPlugin::new(NetworkPreset::Server))
+                .add_plugin(AbilityRequestPlugin);
+        }
+    }
+}
@@ -37,14 +37,15 @@ impl ChatPlugin {
         mut send_events: EventWriter<MessageSent>,
     ) {
         for event in receive_events.iter() {
-            let ClientMessage::ChatMessage(message) = &event.message;
-            send_events.send(MessageSent {
-                kind: SendKind::BroadcastExcept(event.client_id),
-                message: ServerMessage::ChatMessage {
-                    sender_id: event.client_id,
-                    message: message.clone(),
-                },
-            });
+            if let ClientMessage::ChatMessage(message) = &event.message {
+                send_events.send(MessageSent {
+                    kind: SendKind::BroadcastExcept(event.client_id),
+                    message: ServerMessage::ChatMessage {
+                        sender_id: event.client_id,
+                        message: message.clone(),
+                    },
+                });
+            }
         }
     }
 }
@@ -87,7 +88,10 @@ mod tests {
             "The sent message should be broadcast to everyone except the sender"
         );
 
-        let ServerMessage::ChatMessage { sender_id, message } = sent_message.message;
+        let (sender_id, message) = match sent_message.message {
+            ServerMessage::ChatMessage { sender_id, message } => (sender_id, message),
+            _ => panic!("The sent message should be a chat message"),
+        };
         assert_eq!(
             sender_id, SERVER_ID,
             "Chat message should contain the same sender id as the received message"
@@ -0,0 +1,390 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use bevy_renet::renet::{RenetServer, ServerEvent};
+use iyes_loopless::prelude::*;
+use standard_paths::{LocationType, StandardPaths};
+use std::{error::Error, fs, path::PathBuf};
+
+use super::{
+    client,
+    message::{MessagePlugin, MessageSent, SendKind, ServerMessage},
+    SERVER_ID,
+};
+use crate::core::{
+    determinism::{Desync, StateHashed, StateHashes},
+    game_state::GameState,
+    simulation::SimulationTime,
+};
+
+/// Aligns client ticks with server, sends state hashes from server and compares them with local hashes on clients.
+/// Clients start hashing after receiving the first hash from server.
+pub(super) struct DesyncPlugin;
+
+impl Plugin for DesyncPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(
+            GameState::InGame,
+            Self::start_system.run_if_resource_exists::<RenetServer>(),
+        )
+        .add_system(
+            Self::connection_system
+                .run_if_resource_exists::<RenetServer>()
+                .before(MessagePlugin::send_server_message_system),
+        )
+        .add_system(Self::send_hashes_system.run_if_resource_exists::<RenetServer>())
+        .add_system(
+            Self::align_tick_system
+                .run_if(client::connected)
+                .after(MessagePlugin::receive_server_message_system),
+        )
+        .add_system(Self::verify_hashes_system.run_if(client::connected));
+    }
+}
+
+impl DesyncPlugin {
+    /// Counts ticks from the game start on server and on already connected clients.
+    fn start_system(
+        mut simulation_time: ResMut<SimulationTime>,
+        mut send_events: EventWriter<MessageSent>,
+    ) {
+        simulation_time.reset();
+        send_events.send(MessageSent {
+            kind: SendKind::BroadcastExcept(SERVER_ID),
+            message: ServerMessage::SimulationTick(simulation_time.tick()),
+        });
+    }
+
+    /// Sends the current tick to joined clients.
+    fn connection_system(
+        simulation_time: Res<SimulationTime>,
+        mut server_events: EventReader<ServerEvent>,
+        mut send_events: EventWriter<MessageSent>,
+    ) {
+        for event in server_events.iter() {
+            if let ServerEvent::ClientConnected(client_id, _) = *event {
+                send_events.send(MessageSent {
+                    kind: SendKind::Direct(client_id),
+                    message: ServerMessage::SimulationTick(simulation_time.tick()),
+                });
+            }
+        }
+    }
+
+    /// Sends to each client the hash of the state it simulates.
+    fn send_hashes_system(
+        mut hash_events: EventReader<StateHashed>,
+        mut send_events: EventWriter<MessageSent>,
+    ) {
+        for event in hash_events.iter() {
+            for &(client_id, hash) in &event.clients {
+                if client_id != SERVER_ID {
+                    send_events.send(MessageSent {
+                        kind: SendKind::Direct(client_id),
+                        message: ServerMessage::StateHash {
+                            tick: event.tick,
+                            hash,
+                        },
+                    });
+                }
+            }
+        }
+    }
+
+    /// Continues counting ticks from the server tick.
+    /// Runs before the simulation stage, so both peers hash the next tick with the same number.
+    fn align_tick_system(
+        mut server_events: EventReader<ServerMessage>,
+        mut simulation_time: ResMut<SimulationTime>,
+    ) {
+        for message in server_events.iter() {
+            if let ServerMessage::SimulationTick(tick) = *message {
+                simulation_time.set_tick(tick);
+            }
+        }
+    }
+
+    fn verify_hashes_system(
+        mut commands: Commands,
+        mut server_events: EventReader<ServerMessage>,
+        state_hashes: Option<ResMut<StateHashes>>,
+    ) {
+        let mut state_hashes = match state_hashes {
+            Some(state_hashes) => state_hashes,
+            None => {
+                if server_events
+                    .iter()
+                    .any(|message| matches!(message, ServerMessage::StateHash { .. }))
+                {
+                    info!("Server verifies determinism, starting state hashing");
+                    commands.insert_resource(StateHashes::default());
+                }
+                return;
+            }
+        };
+
+        for message in server_events.iter() {
+            if let ServerMessage::StateHash { tick, hash } = message {
+                state_hashes.insert_remote(*tick, *hash);
+            }
+        }
+
+        if let Some(desync) = state_hashes.verify() {
+            error!("Simulation diverged from server at tick {}", desync.tick);
+            match write_report(&desync) {
+                Ok(path) => info!("Desync report written to {}", path.display()),
+                Err(error) => error!("Unable to write desync report: {}", error),
+            }
+        }
+    }
+}
+
+/// Writes the desync report into the application data directory and returns its path.
+fn write_report(desync: &Desync) -> Result<PathBuf, Box<dyn Error>> {
+    let standard_paths = StandardPaths::default();
+    // Use temp directory in tests
+    let mut path = standard_paths.writable_location(if cfg!(test) {
+        LocationType::TempLocation
+    } else {
+        LocationType::AppDataLocation
+    })?;
+    path.push("desync_reports");
+    fs::create_dir_all(&path)?;
+
+    path.push(format!("tick_{}", desync.tick));
+    path.set_extension("json");
+    fs::write(&path, serde_json::to_string_pretty(desync)?)?;
+
+    Ok(path)
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::ecs::event::{Events, ManualEventReader};
+    use bevy_rapier3d::prelude::*;
+    use bevy_renet::{renet::RenetClient, RenetClientPlugin};
+    use std::collections::BTreeMap;
+
+    use super::*;
+    use crate::core::{
+        determinism::{DeterminismPlugin, StateHash},
+        health::Health,
+        network::{
+            client::ConnectionSettings,
+            server::ServerSettings,
+            tests::{NetworkPreset, TestNetworkPlugin},
+        },
+        player::ClientId,
+        simulation::SimulationAppExt,
+        Authority,
+    };
+
+    #[test]
+    fn hashes_sent() {
+        let mut app = App::new();
+        app.add_plugin(TestDesyncPlugin)
+            .add_plugin(TestNetworkPlugin::new(NetworkPreset::Server));
+
+        const TICK: u64 = 1;
+        const CLIENT_ID: u64 = SERVER_ID + 1;
+        let hash = StateHash::default();
+        let mut hash_events = app.world.resource_mut::<Events<StateHashed>>();
+        hash_events.send(StateHashed {
+            tick: TICK,
+            clients: vec![(SERVER_ID, hash), (CLIENT_ID, hash)],
+        });
+
+        app.update();
+
+        let mut send_events = app.world.resource_mut::<Events<MessageSent>>();
+        let mut send_events = send_events.drain();
+        let sent_message = send_events
+            .next()
+            .expect("State hash should be sent to clients");
+        assert!(
+            send_events.next().is_none(),
+            "State hash should be sent only to remote clients"
+        );
+
+        assert!(
+            matches!(sent_message.kind, SendKind::Direct(client_id) if client_id == CLIENT_ID),
+            "State hash should be sent directly to its client"
+        );
+        assert_eq!(
+            sent_message.message,
+            ServerMessage::StateHash { tick: TICK, hash }
+        );
+    }
+
+    #[test]
+    fn late_client_aligned() {
+        let mut server_app = App::new();
+        server_app
+            .add_plugin(TestNetworkPlugin::new(NetworkPreset::Server))
+            .add_plugin(TestPeerPlugin);
+        server_app
+            .world
+            .resource_mut::<ServerSettings>()
+            .verify_determinism = true;
+
+        // Let the game run for a while before the client joins
+        for _ in 0..5 {
+            server_app.update();
+        }
+
+        let mut client_app = App::new();
+        let connection_settings = ConnectionSettings {
+            port: server_app.world.resource::<RenetServer>().addr().port(),
+            ..Default::default()
+        };
+        client_app
+            .add_plugins(MinimalPlugins)
+            .init_resource::<ServerSettings>()
+            .insert_resource(
+                connection_settings
+                    .create_client()
+                    .unwrap_or_else(|error| panic!("Unable to create client: {}", error)),
+            )
+            .add_plugin(RenetClientPlugin)
+            .add_plugin(TestPeerPlugin);
+
+        let client_id = client_app.world.resource::<RenetClient>().client_id();
+        server_app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(client_id));
+        client_app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::new(client_id))
+            .insert(Authority);
+
+        let mut server_reader = ManualEventReader::<StateHashed>::default();
+        let mut client_reader = ManualEventReader::<StateHashed>::default();
+        let mut server_hashes = BTreeMap::new();
+        let mut client_hashes = BTreeMap::new();
+        for _ in 0..10 {
+            server_app.update();
+            client_app.update();
+
+            server_hashes.extend(client_hashes_by_tick(
+                &server_app,
+                &mut server_reader,
+                client_id,
+            ));
+            client_hashes.extend(client_hashes_by_tick(
+                &client_app,
+                &mut client_reader,
+                client_id,
+            ));
+        }
+
+        assert!(
+            !client_hashes.is_empty(),
+            "Client should start hashing after receiving hashes from server"
+        );
+        for (tick, client_hash) in client_hashes {
+            assert_eq!(
+                server_hashes.get(&tick),
+                Some(&client_hash),
+                "Client state at tick {} should match the server state",
+                tick
+            );
+        }
+        assert_eq!(
+            client_app.world.resource::<SimulationTime>().tick(),
+            server_app.world.resource::<SimulationTime>().tick(),
+            "Client ticks should be aligned with server"
+        );
+    }
+
+    fn client_hashes_by_tick(
+        app: &App,
+        reader: &mut ManualEventReader<StateHashed>,
+        client_id: u64,
+    ) -> Vec<(u64, StateHash)> {
+        let hash_events = app.world.resource::<Events<StateHashed>>();
+        reader
+            .iter(hash_events)
+            .flat_map(|event| {
+                event
+                    .clients
+                    .iter()
+                    .filter(|&&(id, _)| id == client_id)
+                    .map(|&(_, hash)| (event.tick, hash))
+            })
+            .collect()
+    }
+
+    /// Moves bodies depending on the tick to make the state diverge on misaligned ticks.
+    fn tick_movement_system(
+        simulation_time: Res<SimulationTime>,
+        mut bodies: Query<&mut Transform, With<RigidBody>>,
+    ) {
+        for mut transform in bodies.iter_mut() {
+            transform.translation.x = simulation_time.tick() as f32;
+        }
+    }
+
+    struct TestDesyncPlugin;
+
+    impl Plugin for TestDesyncPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::Menu)
+                .init_resource::<SimulationTime>()
+                .add_event::<StateHashed>()
+                .add_event::<MessageSent>()
+                .add_event::<ServerMessage>()
+                .add_plugin(DesyncPlugin);
+        }
+    }
+
+    /// Simulates a game on a single peer.
+    struct TestPeerPlugin;
+
+    impl Plugin for TestPeerPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_tick_system(tick_movement_system)
+                .add_plugin(MessagePlugin)
+                .add_plugin(DeterminismPlugin)
+                .add_plugin(DesyncPlugin);
+        }
+    }
+
+    #[derive(Bundle)]
+    struct DummyCharacterBundle {
+        client_id: ClientId,
+        health: Health,
+        rigid_body: RigidBody,
+        transform: Transform,
+    }
+
+    impl DummyCharacterBundle {
+        fn new(client_id: u64) -> Self {
+            Self {
+                client_id: ClientId(client_id),
+                health: Health::default(),
+                rigid_body: RigidBody::Dynamic,
+                transform: Transform::default(),
+            }
+        }
+    }
+}
@@ -0,0 +1,582 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::{prelude::*, utils::HashMap};
+use bevy_renet::renet::RenetServer;
+use iyes_loopless::prelude::*;
+
+use super::{
+    message::{ClientMessage, MessageReceived, MessageSent, SendKind, ServerMessage},
+    server::ServerSettings,
+};
+use crate::core::{
+    game_state::GameState,
+    hero::{hero_definition::HeroDefinitions, HeroKind},
+    player::{ClientId, Player, Team},
+    session::spawn::{HeroPick, RandomHero},
+};
+
+/// Time for picking heroes at the start of the game.
+const PICK_PHASE_SECS: f32 = 30.0;
+
+/// Validates hero picks on server and broadcasts them.
+/// Picks are tracked on all peers in [`HeroPicks`].
+pub(super) struct HeroPickPlugin;
+
+impl Plugin for HeroPickPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<HeroPicks>()
+            .add_enter_system(
+                GameState::InGame,
+                Self::start_pick_phase_system.run_if_resource_exists::<RenetServer>(),
+            )
+            .add_exit_system(GameState::InGame, Self::cleanup_system)
+            .add_system(
+                Self::receive_picks_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<RenetServer>(),
+            )
+            .add_system(
+                Self::pick_phase_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<PickPhase>(),
+            )
+            .add_system(
+                Self::broadcast_heroes_system
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<RenetServer>(),
+            )
+            .add_system(Self::picks_update_system.run_in_state(GameState::InGame));
+    }
+}
+
+impl HeroPickPlugin {
+    fn start_pick_phase_system(
+        mut commands: Commands,
+        mut send_events: EventWriter<MessageSent>,
+        server_settings: Res<ServerSettings>,
+    ) {
+        if server_settings.random_heroes {
+            return;
+        }
+
+        commands.insert_resource(PickPhase(Timer::from_seconds(PICK_PHASE_SECS, false)));
+        send_events.send(MessageSent {
+            kind: SendKind::Broadcast,
+            message: ServerMessage::PickPhaseStarted {
+                secs: PICK_PHASE_SECS,
+            },
+        });
+    }
+
+    fn cleanup_system(mut commands: Commands) {
+        commands.remove_resource::<PickPhase>();
+        commands.insert_resource(HeroPicks::default());
+    }
+
+    fn receive_picks_system(
+        mut commands: Commands,
+        mut receive_events: EventReader<MessageReceived>,
+        mut send_events: EventWriter<MessageSent>,
+        server_settings: Res<ServerSettings>,
+        hero_definitions: Res<HeroDefinitions>,
+        pick_phase: Option<Res<PickPhase>>,
+        players: Query<
+            (
+                Entity,
+                &ClientId,
+                Option<&Team>,
+                Option<&HeroKind>,
+                Option<&HeroPick>,
+                Option<&RandomHero>,
+            ),
+            With<Player>,
+        >,
+    ) {
+        // Picks accepted this frame, components will be inserted only at the end of the stage
+        let mut accepted = HashMap::default();
+        for event in receive_events.iter() {
+            let (player, _, team, hero_kind, hero_pick, random_hero) = match players
+                .iter()
+                .find(|(_, client_id, ..)| client_id.0 == event.client_id)
+            {
+                Some(result) => result,
+                None => {
+                    warn!(
+                        "Received hero pick from client {} without player",
+                        event.client_id
+                    );
+                    continue;
+                }
+            };
+            let locked = hero_kind.is_some() || random_hero.is_some();
+
+            match &event.message {
+                ClientMessage::HeroPick(picked_kind) => {
+                    let mut taken = Vec::new();
+                    if server_settings.unique_team_heroes && team.is_some() {
+                        for (other_player, _, other_team, hero_kind, hero_pick, _) in players.iter()
+                        {
+                            if other_player == player || other_team != team {
+                                continue;
+                            }
+                            taken.extend(hero_kind);
+                            taken.extend(hero_pick.and_then(|hero_pick| hero_pick.0.as_ref()));
+                            taken.extend(accepted.get(&other_player).and_then(Option::as_ref));
+                        }
+                    }
+
+                    if let Err(error) = validate_pick(
+                        picked_kind.as_ref(),
+                        &hero_definitions,
+                        &server_settings,
+                        pick_phase.is_some() && locked,
+                        &taken,
+                    ) {
+                        warn!(
+                            "Rejected hero pick from client {}: {}",
+                            event.client_id, error
+                        );
+                        continue;
+                    }
+
+                    accepted.insert(player, picked_kind.clone());
+                    if pick_phase.is_none() && !locked {
+                        // Player joined after the pick phase
+                        lock_hero(&mut commands, player, picked_kind.clone());
+                    } else {
+                        commands
+                            .entity(player)
+                            .insert(HeroPick(picked_kind.clone()));
+                    }
+                    send_events.send(MessageSent {
+                        kind: SendKind::Broadcast,
+                        message: ServerMessage::HeroPicked {
+                            client_id: event.client_id,
+                            hero_kind: picked_kind.clone(),
+                            locked: false,
+                        },
+                    });
+                }
+                ClientMessage::HeroLock => {
+                    if pick_phase.is_none() || locked {
+                        warn!(
+                            "Rejected hero lock from client {}: hero can't be locked",
+                            event.client_id
+                        );
+                        continue;
+                    }
+
+                    let picked_kind = accepted
+                        .remove(&player)
+                        .unwrap_or_else(|| hero_pick.and_then(|hero_pick| hero_pick.0.clone()));
+                    lock_hero(&mut commands, player, picked_kind);
+                }
+                _ => (),
+            }
+        }
+    }
+
+    /// Locks remaining picks when pick phase ends.
+    /// Players without a pick get a random hero.
+    fn pick_phase_system(
+        mut commands: Commands,
+        time: Res<Time>,
+        mut send_events: EventWriter<MessageSent>,
+        mut pick_phase: ResMut<PickPhase>,
+        players: Query<
+            (Entity, Option<&HeroPick>),
+            (With<Player>, Without<HeroKind>, Without<RandomHero>),
+        >,
+    ) {
+        if !pick_phase.tick(time.delta()).finished() {
+            return;
+        }
+
+        for (player, hero_pick) in players.iter() {
+            lock_hero(
+                &mut commands,
+                player,
+                hero_pick.and_then(|hero_pick| hero_pick.0.clone()),
+            );
+        }
+        commands.remove_resource::<PickPhase>();
+        send_events.send(MessageSent {
+            kind: SendKind::Broadcast,
+            message: ServerMessage::PickPhaseFinished,
+        });
+    }
+
+    /// Broadcasts assigned heroes, including random ones.
+    fn broadcast_heroes_system(
+        mut send_events: EventWriter<MessageSent>,
+        players: Query<(&ClientId, &HeroKind), Changed<HeroKind>>,
+    ) {
+        for (client_id, hero_kind) in players.iter() {
+            send_events.send(MessageSent {
+                kind: SendKind::Broadcast,
+                message: ServerMessage::HeroPicked {
+                    client_id: client_id.0,
+                    hero_kind: Some(hero_kind.clone()),
+                    locked: true,
+                },
+            });
+        }
+    }
+
+    fn picks_update_system(
+        time: Res<Time>,
+        mut server_events: EventReader<ServerMessage>,
+        mut hero_picks: ResMut<HeroPicks>,
+    ) {
+        if let Some(timer) = &mut hero_picks.timer {
+            timer.tick(time.delta());
+        }
+
+        for message in server_events.iter() {
+            match message {
+                ServerMessage::HeroPicked {
+                    client_id,
+                    hero_kind,
+                    locked,
+                } => {
+                    hero_picks.picks.insert(
+                        *client_id,
+                        PickState {
+                            hero_kind: hero_kind.clone(),
+                            locked: *locked,
+                        },
+                    );
+                }
+                ServerMessage::PickPhaseStarted { secs } => {
+                    hero_picks.timer = Some(Timer::from_seconds(*secs, false));
+                    hero_picks.picks.clear();
+                }
+                ServerMessage::PickPhaseFinished => hero_picks.timer = None,
+                ServerMessage::ChatMessage { .. } | ServerMessage::StateHash { .. } => (),
+            }
+        }
+    }
+}
+
+fn lock_hero(commands: &mut Commands, player: Entity, hero_kind: Option<HeroKind>) {
+    let mut entity_commands = commands.entity(player);
+    entity_commands.remove::<HeroPick>();
+    match hero_kind {
+        Some(hero_kind) => entity_commands.insert(hero_kind),
+        None => entity_commands.insert(RandomHero),
+    };
+}
+
+fn validate_pick(
+    hero_kind: Option<&HeroKind>,
+    hero_definitions: &HeroDefinitions,
+    server_settings: &ServerSettings,
+    locked: bool,
+    taken: &[&HeroKind],
+) -> Result<(), &'static str> {
+    if server_settings.random_heroes {
+        return Err("heroes are random on this server");
+    }
+    if locked {
+        return Err("hero is already locked");
+    }
+    if let Some(hero_kind) = hero_kind {
+        if !hero_definitions
+            .iter()
+            .any(|(known_kind, _)| known_kind == hero_kind)
+        {
+            return Err("hero doesn't exist");
+        }
+        if taken.contains(&hero_kind) {
+            return Err("hero is already taken by a teammate");
+        }
+    }
+
+    Ok(())
+}
+
+/// Current pick phase on server.
+#[derive(Deref, DerefMut)]
+struct PickPhase(Timer);
+
+/// Hero picks of all clients received from server.
+#[derive(Default)]
+pub(crate) struct HeroPicks {
+    /// Pick phase time left, [`None`] if the pick phase is over.
+    pub(crate) timer: Option<Timer>,
+    pub(crate) picks: HashMap<u64, PickState>,
+}
+
+pub(crate) struct PickState {
+    /// Picked hero, [`None`] means random hero.
+    pub(crate) hero_kind: Option<HeroKind>,
+    pub(crate) locked: bool,
+}
+
+#[cfg(test)]
+mod tests {
+    use bevy::{asset::AssetPlugin, ecs::event::Events};
+    use bevy_renet::renet::RenetClient;
+
+    use super::*;
+    use crate::core::{
+        headless::HeadlessRenderPlugin,
+        hero::hero_definition::HeroDefinitionPlugin,
+        network::{
+            message::MessagePlugin,
+            tests::{NetworkPreset, TestNetworkPlugin},
+            SERVER_ID,
+        },
+        player::PlayerPlugin,
+    };
+
+    #[test]
+    fn pick_validation() {
+        let mut app = App::new();
+        app.add_plugin(HeadlessRenderPlugin)
+            .add_plugin(HeroDefinitionPlugin);
+
+        let hero_definitions = app.world.resource::<HeroDefinitions>();
+        let server_settings = ServerSettings::default();
+        let (hero_kind, _) = hero_definitions
+            .iter()
+            .next()
+            .expect("Heroes should be loaded from assets");
+        assert!(validate_pick(
+            Some(hero_kind),
+            hero_definitions,
+            &server_settings,
+            false,
+            &[]
+        )
+        .is_ok());
+        assert!(
+            validate_pick(None, hero_definitions, &server_settings, false, &[]).is_ok(),
+            "Random hero should be allowed"
+        );
+        assert!(
+            validate_pick(
+                Some(&HeroKind::from("unknown.hero.json".to_string())),
+                hero_definitions,
+                &server_settings,
+                false,
+                &[]
+            )
+            .is_err(),
+            "Hero should exist"
+        );
+        assert!(
+            validate_pick(
+                Some(hero_kind),
+                hero_definitions,
+                &server_settings,
+                true,
+                &[]
+            )
+            .is_err(),
+            "Locked hero can't be changed"
+        );
+        assert!(
+            validate_pick(
+                Some(hero_kind),
+                hero_definitions,
+                &server_settings,
+                false,
+                &[hero_kind]
+            )
+            .is_err(),
+            "Hero shouldn't be taken by a teammate"
+        );
+        assert!(
+            validate_pick(
+                Some(hero_kind),
+                hero_definitions,
+                &ServerSettings {
+                    random_heroes: true,
+                    ..Default::default()
+                },
+                false,
+                &[]
+            )
+            .is_err(),
+            "Heroes can't be picked when randomized by server"
+        );
+    }
+
+    #[test]
+    fn pick_and_lock() {
+        let mut app = App::new();
+        app.add_plugin(TestHeroPickPlugin);
+
+        let player = app
+            .world
+            .spawn()
+            .insert(Player)
+            .insert(ClientId(SERVER_ID))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.contains_resource::<PickPhase>(),
+            "Pick phase should start on game start"
+        );
+
+        let (hero_kind, _) = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .next()
+            .cloned()
+            .unwrap();
+        let mut receive_events = app.world.resource_mut::<Events<MessageReceived>>();
+        receive_events.send(MessageReceived {
+            client_id: SERVER_ID,
+            message: ClientMessage::HeroPick(Some(hero_kind.clone())),
+        });
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<HeroPick>(player).unwrap().0,
+            Some(hero_kind.clone()),
+            "Hero should be picked"
+        );
+        assert!(
+            !app.world.entity(player).contains::<HeroKind>(),
+            "Hero shouldn't be assigned until lock"
+        );
+        let mut send_events = app.world.resource_mut::<Events<MessageSent>>();
+        let picked = send_events.drain().any(|event| {
+            matches!(
+                event.message,
+                ServerMessage::HeroPicked { locked: false, .. }
+            )
+        });
+        assert!(picked, "Pick should be broadcasted");
+
+        let mut receive_events = app.world.resource_mut::<Events<MessageReceived>>();
+        receive_events.send(MessageReceived {
+            client_id: SERVER_ID,
+            message: ClientMessage::HeroLock,
+        });
+
+        app.update();
+
+        assert_eq!(
+            *app.world.get::<HeroKind>(player).unwrap(),
+            hero_kind,
+            "Picked hero should be assigned after lock"
+        );
+    }
+
+    #[test]
+    fn pick_phase_ends() {
+        let mut app = App::new();
+        app.add_plugin(TestHeroPickPlugin);
+
+        let player = app
+            .world
+            .spawn()
+            .insert(Player)
+            .insert(ClientId(SERVER_ID))
+            .id();
+
+        app.update();
+
+        let mut pick_phase = app.world.resource_mut::<PickPhase>();
+        let duration = pick_phase.duration();
+        pick_phase.set_elapsed(duration);
+
+        app.update();
+
+        assert!(
+            !app.world.contains_resource::<PickPhase>(),
+            "Pick phase should finish"
+        );
+        assert!(
+            app.world.entity(player).contains::<RandomHero>(),
+            "Player without pick should get a random hero"
+        );
+    }
+
+    #[test]
+    fn remote_pick() {
+        let mut app = App::new();
+        app.insert_resource(ServerSettings::default())
+            .add_plugin(PlayerPlugin)
+            .add_plugin(MessagePlugin)
+            .add_plugin(TestNetworkPlugin::new(NetworkPreset::ServerAndClient {
+                connected: true,
+            }))
+            .add_plugin(AssetPlugin::default())
+            .add_plugin(HeroDefinitionPlugin)
+            .add_loopless_state(GameState::InGame)
+            .add_plugin(HeroPickPlugin);
+
+        let (hero_kind, _) = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .next()
+            .cloned()
+            .unwrap();
+        let mut client_events = app.world.resource_mut::<Events<ClientMessage>>();
+        client_events.send(ClientMessage::HeroPick(Some(hero_kind.clone())));
+
+        app.update();
+        app.update();
+        app.update();
+
+        let client_id = app.world.resource::<RenetClient>().client_id();
+        let hero_pick = app
+            .world
+            .query::<(&ClientId, &HeroPick)>()
+            .iter(&app.world)
+            .find(|(id, _)| id.0 == client_id)
+            .map(|(_, hero_pick)| hero_pick.0.clone())
+            .expect("Remote client should pick a hero");
+        assert_eq!(hero_pick, Some(hero_kind));
+    }
+
+    struct TestHeroPickPlugin;
+
+    impl Plugin for TestHeroPickPlugin {
+        fn build(&self, app: &mut App) {
+            let server_settings = ServerSettings {
+                port: 0,
+                ..Default::default()
+            };
+            app.insert_resource(
+                server_settings
+                    .create_server()
+                    .unwrap_or_else(|error| panic!("Unable to create server: {}", error)),
+            )
+            .insert_resource(server_settings)
+            .add_event::<MessageReceived>()
+            .add_event::<MessageSent>()
+            .add_event::<ServerMessage>()
+            .add_loopless_state(GameState::InGame)
+            .add_plugin(HeadlessRenderPlugin)
+            .add_plugin(HeroDefinitionPlugin)
+            .add_plugin(HeroPickPlugin);
+        }
+    }
+}
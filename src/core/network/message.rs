@@ -23,6 +23,11 @@ use iyes_loopless::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use super::{client, Channel, SERVER_ID};
+use crate::core::{
+    control_actions::ControlAction,
+    determinism::StateHash,
+    hero::{Aim, HeroKind},
+};
 
 /// Contains systems that send and recieve reliable messages over the network.
 /// Sending and receiving is done through events:
@@ -52,7 +57,7 @@ impl Plugin for MessagePlugin {
 }
 
 impl MessagePlugin {
-    fn receive_server_message_system(
+    pub(super) fn receive_server_message_system(
         mut server_events: EventWriter<ServerMessage>,
         mut client: ResMut<RenetClient>,
     ) {
@@ -111,7 +116,7 @@ impl MessagePlugin {
         }
     }
 
-    fn send_server_message_system(
+    pub(super) fn send_server_message_system(
         mut send_events: EventReader<MessageSent>,
         mut server_events: EventWriter<ServerMessage>,
         mut server: ResMut<RenetServer>,
@@ -164,10 +169,8 @@ pub(crate) struct MessageSent {
 /// Type of server message sending.
 #[derive(Clone, Copy)]
 pub(crate) enum SendKind {
-    #[allow(dead_code)]
     Broadcast,
     BroadcastExcept(u64),
-    #[allow(dead_code)]
     Direct(u64),
 }
 
@@ -178,7 +181,27 @@ pub(crate) enum SendKind {
 #[derive(Serialize, Deserialize, Clone)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub(crate) enum ServerMessage {
-    ChatMessage { sender_id: u64, message: String },
+    ChatMessage {
+        sender_id: u64,
+        message: String,
+    },
+    /// Hero pick of the client was accepted, [`None`] means random hero.
+    HeroPicked {
+        client_id: u64,
+        hero_kind: Option<HeroKind>,
+        locked: bool,
+    },
+    PickPhaseStarted {
+        secs: f32,
+    },
+    PickPhaseFinished,
+    /// Current simulation tick of the server to align client ticks with it.
+    SimulationTick(u64),
+    /// Simulation state hash of the server at the tick for desync detection.
+    StateHash {
+        tick: u64,
+        hash: StateHash,
+    },
 }
 
 /// A message from client.
@@ -189,6 +212,18 @@ pub(crate) enum ServerMessage {
 #[cfg_attr(test, derive(Debug, PartialEq))]
 pub(crate) enum ClientMessage {
     ChatMessage(String),
+    /// Requests activation of the ability bound to the action.
+    /// Contains aim and the last received server tick at the moment of activation.
+    AbilityRequest {
+        action: ControlAction,
+        aim: Aim,
+        tick: u32,
+    },
+    /// Requests hero selection, [`None`] means random hero.
+    /// After the pick phase the hero will be changed on respawn.
+    HeroPick(Option<HeroKind>),
+    /// Confirms the picked hero during the pick phase.
+    HeroLock,
 }
 
 #[cfg(test)]
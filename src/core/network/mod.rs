@@ -17,8 +17,11 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
+mod ability_request;
 mod chat;
 pub(crate) mod client;
+mod desync;
+pub(crate) mod hero_pick;
 pub(crate) mod message;
 pub(crate) mod server;
 mod unreliable_message;
@@ -28,12 +31,17 @@ use bevy_renet::renet::{
     ChannelConfig, ReliableChannelConfig, UnreliableChannelConfig, NETCODE_KEY_BYTES,
 };
 
+use ability_request::AbilityRequestPlugin;
 use chat::ChatPlugin;
 use client::ClientPlugin;
+use desync::DesyncPlugin;
+use hero_pick::HeroPickPlugin;
 use message::MessagePlugin;
 use server::ServerPlugin;
 use unreliable_message::UnreliableMessagePlugin;
 
+pub(crate) use unreliable_message::NetworkTick;
+
 pub(crate) const DEFAULT_PORT: u16 = 4761;
 pub(crate) const MAX_PORT: u16 = 65535;
 pub(crate) const SERVER_ID: u64 = 0;
@@ -48,7 +56,10 @@ impl Plugin for NetworkPlugin {
             .add_plugin(ClientPlugin)
             .add_plugin(MessagePlugin)
             .add_plugin(UnreliableMessagePlugin)
-            .add_plugin(ChatPlugin);
+            .add_plugin(ChatPlugin)
+            .add_plugin(AbilityRequestPlugin)
+            .add_plugin(HeroPickPlugin)
+            .add_plugin(DesyncPlugin);
     }
 }
 
@@ -120,6 +131,7 @@ pub mod tests {
                         .create_server()
                         .unwrap_or_else(|error| panic!("Unable to create server: {}", error)),
                 )
+                .insert_resource(server_settings)
                 .add_plugin(RenetServerPlugin);
             }
 
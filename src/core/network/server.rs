@@ -31,6 +31,7 @@ use crate::core::{
     cli::{Opts, SubCommand},
     map::Map,
     session::GameMode,
+    simulation::SimulationTime,
 };
 
 pub(super) struct ServerPlugin;
@@ -55,6 +56,10 @@ pub(crate) fn random_heroes(server_settings: Res<ServerSettings>) -> bool {
     server_settings.random_heroes
 }
 
+pub(crate) fn fall_damage(server_settings: Res<ServerSettings>) -> bool {
+    server_settings.fall_damage
+}
+
 #[derive(Args, Clone)]
 #[cfg_attr(test, derive(PartialEq, Debug))]
 pub(crate) struct ServerSettings {
@@ -81,6 +86,35 @@ pub(crate) struct ServerSettings {
     /// Choose heroes randomly.
     #[clap(short, long)]
     pub(crate) random_heroes: bool,
+
+    /// Don't give the same random hero to teammates.
+    #[clap(long)]
+    pub(crate) unique_team_heroes: bool,
+
+    /// Don't give the same random hero twice in a row.
+    #[clap(long)]
+    pub(crate) no_hero_repeats: bool,
+
+    /// Seed for random hero selection, picked randomly if not specified.
+    #[clap(long)]
+    pub(crate) seed: Option<u64>,
+
+    /// Damage characters on hard landings.
+    #[clap(short, long)]
+    pub(crate) fall_damage: bool,
+
+    /// Send state hashes to clients every tick to detect desyncs.
+    #[clap(long)]
+    pub(crate) verify_determinism: bool,
+
+    /// Number of simulation ticks per second.
+    #[clap(
+        short,
+        long,
+        default_value_t = ServerSettings::default().tick_rate,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub(crate) tick_rate: u32,
 }
 
 impl Default for ServerSettings {
@@ -92,6 +126,12 @@ impl Default for ServerSettings {
             game_mode: GameMode::Deathmatch,
             map: Map::SkyRoof,
             random_heroes: false,
+            unique_team_heroes: false,
+            no_hero_repeats: false,
+            seed: None,
+            fall_damage: false,
+            verify_determinism: false,
+            tick_rate: SimulationTime::DEFAULT_TICK_RATE,
         }
     }
 }
@@ -115,6 +155,8 @@ impl ServerSettings {
 
 #[cfg(test)]
 mod tests {
+    use clap::Parser;
+
     use super::*;
 
     #[test]
@@ -156,4 +198,12 @@ mod tests {
             "Server resource should exist"
         );
     }
+
+    #[test]
+    fn zero_tick_rate_rejected() {
+        assert!(
+            Opts::try_parse_from(["gardum", "host", "--tick-rate", "0"]).is_err(),
+            "Simulation can't run without ticks"
+        );
+    }
 }
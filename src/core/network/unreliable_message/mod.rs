@@ -298,7 +298,7 @@ impl UnreliableMessagePlugin {
 
 /// Current network tick
 /// Used on server and clients
-struct NetworkTick(u32);
+pub(crate) struct NetworkTick(pub(crate) u32);
 
 impl Default for NetworkTick {
     fn default() -> Self {
@@ -309,7 +309,7 @@ impl Default for NetworkTick {
 /// Last received tick from server
 /// Used only on clients
 #[derive(Default)]
-struct ReceivedServerTick(u32);
+pub(super) struct ReceivedServerTick(pub(super) u32);
 
 /// Last acknowledged server ticks from all clients
 /// Used only on server
@@ -27,18 +27,32 @@ use bevy_rapier3d::prelude::*;
 use derive_more::From;
 use dolly::prelude::*;
 use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
 
 use super::{
+    control_actions::ControlAction,
     game_state::{GameState, InGameOnly},
-    hero::HeroKind,
+    hero::{Aim, HeroKind},
+    settings::{CameraSettings, Settings, SettingsApplied, Shoulder},
     Authority, CollisionMask,
 };
 
+/// Maximum camera pitch in degrees.
+const MAX_PITCH: f32 = 90.0;
+
+/// Horizontal camera offset from the character center.
+const SHOULDER_OFFSET: f32 = 1.5;
+
 pub(super) struct OrbitCameraPlugin;
 
 impl Plugin for OrbitCameraPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(Self::spawn_system.run_in_state(GameState::InGame))
+            .add_system(
+                Self::load_settings_system
+                    .run_in_state(GameState::InGame)
+                    .run_on_event::<SettingsApplied>(),
+            )
             .add_system(
                 Self::input_system
                     .run_in_state(GameState::InGame)
@@ -49,6 +63,12 @@ impl Plugin for OrbitCameraPlugin {
                 Self::position_system
                     .run_in_state(GameState::InGame)
                     .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                Self::aim_system
+                    .run_in_state(GameState::InGame)
+                    .after(Self::position_system),
             );
     }
 }
@@ -56,11 +76,13 @@ impl Plugin for OrbitCameraPlugin {
 impl OrbitCameraPlugin {
     fn spawn_system(
         mut commands: Commands,
+        settings: Res<Settings>,
         mut active_camera: ResMut<ActiveCamera<Camera3d>>,
         spawned_heroes: Query<(Entity, Option<&Authority>), Added<HeroKind>>,
     ) {
         for (hero, authority) in spawned_heroes.iter() {
-            let mut entity_commands = commands.spawn_bundle(OrbitCameraBundle::new(hero.into()));
+            let mut entity_commands =
+                commands.spawn_bundle(OrbitCameraBundle::new(hero.into(), settings.camera.clone()));
 
             if authority.is_some() {
                 entity_commands.insert(Authority);
@@ -69,18 +91,60 @@ impl OrbitCameraPlugin {
         }
     }
 
+    fn load_settings_system(
+        settings: Res<Settings>,
+        mut cameras: Query<(&mut CameraSettings, &mut PerspectiveProjection)>,
+    ) {
+        for (mut camera_settings, mut projection) in cameras.iter_mut() {
+            *camera_settings = settings.camera.clone();
+            projection.fov = camera_settings.fov.to_radians();
+        }
+    }
+
+    /// Rotates the camera and swaps its shoulder.
+    /// Swapped shoulder is written back to [`Settings`] to survive settings reloading and respawns.
     fn input_system(
+        time: Res<Time>,
+        mut settings: ResMut<Settings>,
+        gamepads: Res<Gamepads>,
+        gamepad_axes: Res<Axis<GamepadAxis>>,
         mut motion_events: EventReader<MouseMotion>,
-        mut orbit_rotations: Query<&mut OrbitRotation, With<Authority>>,
+        characters: Query<&ActionState<ControlAction>>,
+        mut cameras: Query<
+            (&mut OrbitRotation, &mut CameraSettings, &CameraTarget),
+            With<Authority>,
+        >,
     ) {
-        if let Ok(mut orbit_rotation) = orbit_rotations.get_single_mut() {
+        if let Ok((mut orbit_rotation, mut camera_settings, target)) = cameras.get_single_mut() {
+            let mut delta = Vec2::ZERO;
             for event in motion_events.iter() {
-                const SENSETIVITY: f32 = 0.2;
-                orbit_rotation.0 -= event.delta * SENSETIVITY;
+                delta += event.delta * camera_settings.mouse_sensitivity;
+            }
+            for &gamepad in gamepads.iter() {
+                let stick = Vec2::new(
+                    gamepad_axes
+                        .get(GamepadAxis(gamepad, GamepadAxisType::RightStickX))
+                        .unwrap_or_default(),
+                    // Stick Y axis points up unlike mouse motion
+                    -gamepad_axes
+                        .get(GamepadAxis(gamepad, GamepadAxisType::RightStickY))
+                        .unwrap_or_default(),
+                );
+                delta += stick * camera_settings.gamepad_sensitivity * time.delta_seconds();
+            }
+            if camera_settings.invert_y {
+                delta.y = -delta.y;
             }
 
-            const MAX_ROTATION: f32 = 90.0;
-            orbit_rotation.y = orbit_rotation.y.clamp(-MAX_ROTATION, MAX_ROTATION);
+            orbit_rotation.0 -= delta;
+            orbit_rotation.y = orbit_rotation.y.clamp(-MAX_PITCH, MAX_PITCH);
+
+            if let Ok(action_state) = characters.get(target.0) {
+                if action_state.just_pressed(ControlAction::SwapShoulder) {
+                    settings.camera.shoulder = camera_settings.shoulder.swapped();
+                    camera_settings.shoulder = settings.camera.shoulder;
+                }
+            }
         }
     }
 
@@ -88,9 +152,17 @@ impl OrbitCameraPlugin {
         rapier_ctx: Res<RapierContext>,
         time: Res<Time>,
         transforms: Query<&Transform, Without<OrbitRotation>>,
-        mut cameras: Query<(&mut Transform, &mut OrbitRig, &OrbitRotation, &CameraTarget)>,
+        mut cameras: Query<(
+            &mut Transform,
+            &mut OrbitRig,
+            &OrbitRotation,
+            &CameraSettings,
+            &CameraTarget,
+        )>,
     ) {
-        for (mut camera_transform, mut orbit_rig, orbit_rotation, target) in cameras.iter_mut() {
+        for (mut camera_transform, mut orbit_rig, orbit_rotation, camera_settings, target) in
+            cameras.iter_mut()
+        {
             let mut pivot_translation = transforms.get(target.0).unwrap().translation;
             const GROUND_OFFSET: f32 = 1.5;
             pivot_translation.y += GROUND_OFFSET;
@@ -100,7 +172,12 @@ impl OrbitCameraPlugin {
             yaw_pitch.yaw_degrees = orbit_rotation.x;
             yaw_pitch.pitch_degrees = orbit_rotation.y;
 
-            orbit_rig.driver_mut::<Arm>().offset.z = OrbitRig::MAX_DISTANCE;
+            let arm = orbit_rig.driver_mut::<Arm>();
+            arm.offset.x = match camera_settings.shoulder {
+                Shoulder::Left => -SHOULDER_OFFSET,
+                Shoulder::Right => SHOULDER_OFFSET,
+            };
+            arm.offset.z = camera_settings.distance;
             let mut calculated_transform = orbit_rig.update(time.delta_seconds());
 
             let ray_direction = (calculated_transform.position - pivot_translation).normalize();
@@ -125,6 +202,40 @@ impl OrbitCameraPlugin {
             camera_transform.rotation = calculated_transform.rotation;
         }
     }
+
+    /// Casts a ray from the local camera through the crosshair to update the target aim.
+    fn aim_system(
+        rapier_ctx: Res<RapierContext>,
+        cameras: Query<(&Transform, &CameraTarget), With<Authority>>,
+        mut characters: Query<&mut Aim>,
+    ) {
+        for (camera_transform, target) in cameras.iter() {
+            let mut aim = match characters.get_mut(target.0) {
+                Ok(aim) => aim,
+                Err(_) => continue,
+            };
+
+            let direction = camera_transform.rotation * -Vec3::Z;
+            let hit = rapier_ctx.cast_ray(
+                camera_transform.translation,
+                direction,
+                Aim::MAX_DISTANCE,
+                true,
+                QueryFilter::new()
+                    .groups(InteractionGroups::new(
+                        CollisionMask::all().bits(),
+                        (CollisionMask::WORLD | CollisionMask::CHARACTER).bits(),
+                    ))
+                    .exclude_collider(target.0),
+            );
+
+            *aim = Aim::new(
+                camera_transform.translation,
+                direction,
+                hit.map(|(_, distance)| distance),
+            );
+        }
+    }
 }
 
 fn cursor_locked(#[cfg(not(test))] windows: ResMut<Windows>) -> bool {
@@ -140,6 +251,7 @@ struct OrbitCameraBundle {
     camera_target: CameraTarget,
     orbit_rotation: OrbitRotation,
     orbit_rig: OrbitRig,
+    camera_settings: CameraSettings,
     ingame_only: InGameOnly,
 
     #[bundle]
@@ -147,14 +259,18 @@ struct OrbitCameraBundle {
 }
 
 impl OrbitCameraBundle {
-    fn new(camera_target: CameraTarget) -> Self {
+    fn new(camera_target: CameraTarget, camera_settings: CameraSettings) -> Self {
+        let mut camera = PerspectiveCameraBundle::new_3d();
+        camera.perspective_projection.fov = camera_settings.fov.to_radians();
+
         Self {
             name: "Orbit Camera".into(),
             camera_target,
             orbit_rig: OrbitRig::default(),
             orbit_rotation: OrbitRotation::default(),
+            camera_settings,
             ingame_only: InGameOnly,
-            camera: PerspectiveCameraBundle::new_3d(),
+            camera,
         }
     }
 }
@@ -169,17 +285,14 @@ struct OrbitRotation(Vec2);
 #[derive(Component, From, Deref, DerefMut)]
 struct OrbitRig(CameraRig);
 
-impl OrbitRig {
-    const MAX_DISTANCE: f32 = 5.0;
-}
-
 impl Default for OrbitRig {
     fn default() -> Self {
         Self(
             CameraRig::builder()
                 .with(Position::default())
                 .with(YawPitch::default())
-                .with(Arm::new(Vec3::new(1.5, 0.0, Self::MAX_DISTANCE)))
+                // Offset is updated from camera settings each frame
+                .with(Arm::new(Vec3::ZERO))
                 .build(),
         )
     }
@@ -270,6 +383,128 @@ mod tests {
         );
     }
 
+    #[test]
+    fn inverted_input() {
+        let mut app = App::new();
+        app.add_plugin(TestOrbitCameraPlugin);
+        app.world.resource_mut::<Settings>().camera.invert_y = true;
+
+        app.world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default());
+
+        app.update();
+
+        let mut motion_events = app.world.resource_mut::<Events<MouseMotion>>();
+        motion_events.send(MouseMotion { delta: Vec2::Y });
+
+        app.update();
+
+        let orbit_rotation = app
+            .world
+            .query::<&OrbitRotation>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        assert!(
+            orbit_rotation.y > OrbitRotation::default().y,
+            "Vertical rotation should be inverted"
+        );
+    }
+
+    #[test]
+    fn shoulder_swap() {
+        let mut app = App::new();
+        app.add_plugin(TestOrbitCameraPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(ActionState::<ControlAction>::default())
+            .id();
+
+        app.update();
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.press(ControlAction::SwapShoulder);
+
+        app.update();
+
+        let camera_settings = app
+            .world
+            .query::<&CameraSettings>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        let swapped_shoulder = CameraSettings::default().shoulder.swapped();
+        assert_eq!(
+            camera_settings.shoulder, swapped_shoulder,
+            "Shoulder should be swapped on action"
+        );
+
+        let mut action_state = app
+            .world
+            .get_mut::<ActionState<ControlAction>>(character)
+            .unwrap();
+        action_state.release(ControlAction::SwapShoulder);
+        let mut apply_events = app.world.resource_mut::<Events<SettingsApplied>>();
+        apply_events.send(SettingsApplied);
+
+        app.update();
+
+        let camera_settings = app
+            .world
+            .query::<&CameraSettings>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        assert_eq!(
+            camera_settings.shoulder, swapped_shoulder,
+            "Swapped shoulder should be kept after settings reloading"
+        );
+    }
+
+    #[test]
+    fn settings_applies() {
+        let mut app = App::new();
+        app.add_plugin(TestOrbitCameraPlugin);
+
+        app.world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default());
+
+        app.update();
+
+        const FOV: f32 = 90.0;
+        let mut settings = app.world.resource_mut::<Settings>();
+        settings.camera.fov = FOV;
+
+        let mut apply_events = app.world.resource_mut::<Events<SettingsApplied>>();
+        apply_events.send(SettingsApplied);
+
+        app.update();
+
+        let (camera_settings, projection) = app
+            .world
+            .query::<(&CameraSettings, &PerspectiveProjection)>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        assert_eq!(
+            camera_settings.fov, FOV,
+            "Camera settings should be updated on apply event"
+        );
+        assert_eq!(
+            projection.fov,
+            FOV.to_radians(),
+            "Field of view should be applied to the projection"
+        );
+    }
+
     #[test]
     fn position() {
         let mut app = App::new();
@@ -288,32 +523,85 @@ mod tests {
             .next()
             .unwrap(); // TODO 0.8: Use single
 
+        let distance = CameraSettings::default().distance;
         let mut orbit_rig = app.world.get_mut::<OrbitRig>(camera).unwrap();
         assert_eq!(
             orbit_rig.driver_mut::<Arm>().offset.z,
-            OrbitRig::MAX_DISTANCE,
+            distance,
             "Camera should be at the maximum distance when nothing blocks the line of sight"
         );
 
         app.world
             .spawn()
             .insert(Transform::default())
-            .insert(Collider::ball(OrbitRig::MAX_DISTANCE - 1.0));
+            .insert(Collider::ball(distance - 1.0));
 
         app.update();
 
         let mut orbit_rig = app.world.get_mut::<OrbitRig>(camera).unwrap();
         assert!(
-            orbit_rig.driver_mut::<Arm>().offset.z < OrbitRig::MAX_DISTANCE,
+            orbit_rig.driver_mut::<Arm>().offset.z < distance,
             "Camera distance should decrease when there is an obstacle that blocks the line of sight"
         );
     }
 
+    #[test]
+    fn aim() {
+        let mut app = App::new();
+        app.add_plugin(TestOrbitCameraPlugin);
+
+        let character = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(Aim::default())
+            .id();
+
+        app.update();
+        app.update();
+
+        let camera_transform = *app
+            .world
+            .query_filtered::<&Transform, With<OrbitRotation>>()
+            .iter(&app.world)
+            .next()
+            .unwrap(); // TODO 0.8: Use single
+        let aim = *app.world.get::<Aim>(character).unwrap();
+        assert_eq!(
+            aim,
+            Aim::new(
+                camera_transform.translation,
+                camera_transform.rotation * -Vec3::Z,
+                None
+            ),
+            "Aim should follow the camera when nothing is hit"
+        );
+
+        const OBSTACLE_DISTANCE: f32 = 10.0;
+        app.world
+            .spawn()
+            .insert(Transform::from_translation(
+                aim.origin + aim.direction * OBSTACLE_DISTANCE,
+            ))
+            .insert(Collider::ball(1.0));
+
+        app.update();
+        app.update();
+
+        let aim = app.world.get::<Aim>(character).unwrap();
+        assert!(
+            aim.origin.distance(aim.target_point) < OBSTACLE_DISTANCE,
+            "Target point should be on the obstacle"
+        );
+    }
+
     struct TestOrbitCameraPlugin;
 
     impl Plugin for TestOrbitCameraPlugin {
         fn build(&self, app: &mut App) {
             app.add_loopless_state(GameState::InGame)
+                .add_event::<SettingsApplied>()
+                .init_resource::<Settings>()
                 .add_plugin(HeadlessRenderPlugin)
                 .add_plugin(InputPlugin)
                 .add_plugin(ScenePlugin)
@@ -334,7 +622,7 @@ mod tests {
             Self {
                 transform: Transform::default(),
                 authority: Authority,
-                hero_kind: HeroKind::North,
+                hero_kind: String::default().into(),
             }
         }
     }
@@ -25,12 +25,9 @@ use strum::EnumIter;
 
 use super::{
     cooldown::Cooldown,
-    effect::{
-        periodic_effect::{PeriodicEffectTimer, PeriodicHealthChange},
-        EffectTarget, EffectTimer,
-    },
+    effect::{effect_definition::EffectDefinition, EffectTarget},
     game_state::{GameState, InGameOnly},
-    hero::{DamageModifier, HealingModifier, SpeedModifier},
+    simulation::{SimulationAppExt, SimulationTime},
     AssociatedAsset, CollisionMask,
 };
 
@@ -38,9 +35,9 @@ pub(super) struct PickupPlugin;
 
 impl Plugin for PickupPlugin {
     fn build(&self, app: &mut App) {
-        app.add_system(Self::spawn_system.run_in_state(GameState::InGame))
-            .add_system(Self::interaction_system.run_in_state(GameState::InGame))
-            .add_system(Self::cooldown_system.run_in_state(GameState::InGame));
+        app.add_tick_system(Self::spawn_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::interaction_system.run_in_state(GameState::InGame))
+            .add_tick_system(Self::cooldown_system.run_in_state(GameState::InGame));
     }
 }
 
@@ -54,6 +51,7 @@ impl PickupPlugin {
             commands
                 .entity(pickup)
                 .insert_bundle(LocalPickupBundle::default())
+                .insert(PickupEffect(asset_server.load(kind.effect_path())))
                 .with_children(|parent| {
                     parent
                         .spawn_bundle(TransformBundle::from_transform(
@@ -69,13 +67,14 @@ impl PickupPlugin {
 
     fn interaction_system(
         mut commands: Commands,
+        definitions: Res<Assets<EffectDefinition>>,
         children: Query<&Children>,
         mut pickups: Query<
-            (Entity, &PickupKind, &mut Cooldown, &CollidingEntities),
+            (Entity, &PickupEffect, &mut Cooldown, &CollidingEntities),
             Changed<CollidingEntities>,
         >,
     ) {
-        for (pickup, pickup_kind, mut cooldown, collisions) in pickups.iter_mut() {
+        for (pickup, pickup_effect, mut cooldown, collisions) in pickups.iter_mut() {
             let character = match collisions.iter().next() {
                 Some(character) => character,
                 None => continue,
@@ -84,17 +83,16 @@ impl PickupPlugin {
             if !cooldown.finished() {
                 continue;
             }
-            cooldown.reset();
 
-            match pickup_kind {
-                PickupKind::Healing => {
-                    commands.spawn_bundle(HealingEffectBundle::new(character.into()))
-                }
-                PickupKind::Rage => commands.spawn_bundle(RageEffectBundle::new(character.into())),
-                PickupKind::Speed => {
-                    commands.spawn_bundle(SpeedEffectBundle::new(character.into()))
-                }
+            let definition = match definitions.get(&pickup_effect.0) {
+                Some(definition) => definition,
+                None => continue,
             };
+            cooldown.reset();
+
+            let mut effect = commands.spawn();
+            effect.insert(EffectTarget::from(character));
+            definition.insert_into(&mut effect);
 
             let mesh_child = pickup_child_mesh(pickup, &children);
             commands
@@ -104,7 +102,7 @@ impl PickupPlugin {
     }
 
     fn cooldown_system(
-        time: Res<Time>,
+        time: Res<SimulationTime>,
         children: Query<&Children>,
         mut cooldowns: Query<(Entity, &mut Cooldown), With<PickupKind>>,
         mut visibility: Query<&mut Visibility>,
@@ -181,72 +179,9 @@ impl Default for LocalPickupBundle {
     }
 }
 
-/// A component bundle for pickup healing effect.
-/// Will be inserted automatically on pickup interation with `PickupKind::Healing`.
-#[derive(Bundle)]
-struct HealingEffectBundle {
-    name: Name,
-    health_change: PeriodicHealthChange,
-    periodic_timer: PeriodicEffectTimer,
-    timer: EffectTimer,
-    target: EffectTarget,
-}
-
-impl HealingEffectBundle {
-    fn new(target: EffectTarget) -> Self {
-        Self {
-            name: "Healing Effect".into(),
-            health_change: 10.into(),
-            target,
-            timer: Timer::from_seconds(4.0, false).into(),
-            periodic_timer: PeriodicEffectTimer::default(),
-        }
-    }
-}
-
-/// A component bundle for pickup rage effect.
-/// Will be inserted automatically on pickup interation with `PickupKind::Rage`.
-#[derive(Bundle)]
-struct RageEffectBundle {
-    name: Name,
-    damage_modifier: DamageModifier,
-    healing_modifier: HealingModifier,
-    timer: EffectTimer,
-    target: EffectTarget,
-}
-
-impl RageEffectBundle {
-    fn new(target: EffectTarget) -> Self {
-        Self {
-            name: "Rage Effect".into(),
-            damage_modifier: 0.2.into(),
-            healing_modifier: 0.2.into(),
-            timer: Timer::from_seconds(10.0, false).into(),
-            target,
-        }
-    }
-}
-
-#[derive(Bundle)]
-struct SpeedEffectBundle {
-    name: Name,
-    speed_modifier: SpeedModifier,
-    timer: EffectTimer,
-    target: EffectTarget,
-}
-
-/// A component bundle for pickup speed effect.
-/// Will be inserted automatically on pickup interation with `PickupKind::Speed`.
-impl SpeedEffectBundle {
-    fn new(target: EffectTarget) -> Self {
-        Self {
-            name: "Speed Effect".into(),
-            speed_modifier: 0.2.into(),
-            timer: Timer::from_seconds(10.0, false).into(),
-            target,
-        }
-    }
-}
+/// Effect definition that will be applied on pickup interaction.
+#[derive(Component)]
+struct PickupEffect(Handle<EffectDefinition>);
 
 /// Type of pickup
 #[derive(Component, Clone, Copy)]
@@ -267,6 +202,16 @@ impl AssociatedAsset for PickupKind {
     }
 }
 
+impl PickupKind {
+    fn effect_path(&self) -> &str {
+        match self {
+            PickupKind::Speed => "effects/speed.effect.json",
+            PickupKind::Rage => "effects/rage.effect.json",
+            PickupKind::Healing => "effects/healing.effect.json",
+        }
+    }
+}
+
 const PLATFORM_PATH: &str = "pickup/platform.glb#Scene0";
 
 #[cfg(test)]
@@ -278,8 +223,12 @@ mod tests {
 
     use super::*;
     use crate::core::{
+        ability::AbilityPlugin,
+        effect::{
+            effect_definition::EffectDefinitionPlugin, periodic_effect::PeriodicHealthChange,
+        },
         headless::{self, HeadlessRenderPlugin},
-        hero::LocalHeroBundle,
+        hero::{DamageModifier, HealingModifier, LocalHeroBundle, SpeedModifier},
     };
 
     #[test]
@@ -296,6 +245,7 @@ mod tests {
 
             headless::wait_for_asset_loading(&mut app, pickup_kind.asset_path());
             headless::wait_for_asset_loading(&mut app, PLATFORM_PATH);
+            headless::wait_for_asset_loading(&mut app, pickup_kind.effect_path());
 
             let character = app
                 .world
@@ -387,6 +337,32 @@ mod tests {
         assert!(visibility.is_visible, "Pickup mesh should become visible");
     }
 
+    #[test]
+    fn pickup_cooldown_ticks_once() {
+        let mut app = App::new();
+        app.add_plugin(TestPickupPlugin).add_plugin(AbilityPlugin);
+
+        let pickup = app
+            .world
+            .spawn()
+            .insert_bundle(PickupBundle::new(PickupKind::Speed, Vec3::default()))
+            .id();
+
+        app.update();
+
+        let mut cooldown = app.world.get_mut::<Cooldown>(pickup).unwrap();
+        cooldown.reset();
+
+        app.update();
+
+        let cooldown = app.world.get::<Cooldown>(pickup).unwrap();
+        assert_eq!(
+            cooldown.elapsed(),
+            app.world.resource::<SimulationTime>().delta(),
+            "Pickup cooldown shouldn't be ticked by abilities"
+        );
+    }
+
     struct TestPickupPlugin;
 
     impl Plugin for TestPickupPlugin {
@@ -398,6 +374,7 @@ mod tests {
                 .add_plugin(GltfPlugin)
                 .add_plugin(TransformPlugin)
                 .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(EffectDefinitionPlugin)
                 .add_plugin(PickupPlugin);
         }
     }
@@ -18,16 +18,21 @@
  */
 
 use bevy::prelude::*;
-use bevy_renet::renet::{RenetClient, RenetServer};
+use bevy_renet::renet::{RenetClient, RenetServer, ServerEvent};
 use iyes_loopless::prelude::*;
 
-use super::Authority;
+use super::{
+    network::{server::ServerSettings, SERVER_ID},
+    Authority,
+};
 
 pub(super) struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(Self::spawn_player_system.run_if_resource_added::<RenetServer>())
+            .add_system(Self::connection_system.run_if_resource_exists::<RenetServer>())
+            .add_system(Self::team_system.run_if_resource_exists::<RenetServer>())
             .add_system(Self::despawn_players_system.run_if_resource_removed::<RenetServer>())
             .add_system(Self::despawn_players_system.run_if_resource_removed::<RenetClient>());
     }
@@ -37,9 +42,61 @@ impl PlayerPlugin {
     fn spawn_player_system(mut commands: Commands) {
         commands
             .spawn_bundle(PlayerBundle::default())
+            .insert(ClientId(SERVER_ID))
             .insert(Authority);
     }
 
+    /// Spawns and despawns players of remote clients on server.
+    fn connection_system(
+        mut commands: Commands,
+        mut server_events: EventReader<ServerEvent>,
+        players: Query<(Entity, &ClientId)>,
+    ) {
+        for event in server_events.iter() {
+            match *event {
+                ServerEvent::ClientConnected(client_id, _) => {
+                    commands
+                        .spawn_bundle(PlayerBundle::default())
+                        .insert(ClientId(client_id));
+                }
+                ServerEvent::ClientDisconnected(client_id) => {
+                    if let Some((player, _)) = players.iter().find(|(_, id)| id.0 == client_id) {
+                        commands.entity(player).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+
+    /// Assigns new players to the team with the fewest players if the game mode has teams.
+    fn team_system(
+        mut commands: Commands,
+        server_settings: Res<ServerSettings>,
+        new_players: Query<Entity, (Added<Player>, Without<Team>)>,
+        teams: Query<&Team, With<Player>>,
+    ) {
+        let mut players_count = vec![0; server_settings.game_mode.teams_count() as usize];
+        if players_count.is_empty() {
+            return;
+        }
+
+        for team in teams.iter() {
+            if let Some(count) = players_count.get_mut(team.0 as usize) {
+                *count += 1;
+            }
+        }
+
+        for player in new_players.iter() {
+            let (team, count) = players_count
+                .iter_mut()
+                .enumerate()
+                .min_by_key(|(_, count)| **count)
+                .expect("Game mode should have at least one team");
+            *count += 1;
+            commands.entity(player).insert(Team(team as u8));
+        }
+    }
+
     fn despawn_players_system(mut commands: Commands, players: Query<Entity, With<Player>>) {
         for player in players.iter() {
             commands.entity(player).despawn_recursive();
@@ -74,6 +131,14 @@ impl Default for PlayerBundle {
 #[derive(Component, Default)]
 pub(crate) struct Player;
 
+/// Network client that controls the player
+#[derive(Component, Clone, Copy)]
+pub(crate) struct ClientId(pub(crate) u64);
+
+/// Team of the player, characters without a team are enemies to everyone
+#[derive(Component, Clone, Copy, PartialEq, Debug)]
+pub(crate) struct Team(pub(crate) u8);
+
 /// Used to keep statistics of the number of kills
 #[derive(Component, Default, Debug, PartialEq, Deref)]
 pub(crate) struct Kills(pub(crate) u32);
@@ -92,7 +157,10 @@ pub(crate) struct Healing(pub(crate) u32);
 
 #[cfg(test)]
 mod tests {
-    use crate::core::network::tests::{NetworkPreset, TestNetworkPlugin};
+    use crate::core::{
+        network::tests::{NetworkPreset, TestNetworkPlugin},
+        session::GameMode,
+    };
 
     use super::*;
 
@@ -121,6 +189,59 @@ mod tests {
         );
     }
 
+    #[test]
+    fn player_spawns_for_connected_client() {
+        let mut app = App::new();
+        app.add_plugin(PlayerPlugin)
+            .add_plugin(TestNetworkPlugin::new(NetworkPreset::ServerAndClient {
+                connected: true,
+            }));
+
+        app.update();
+
+        let client_id = app.world.resource::<RenetClient>().client_id();
+        assert_ne!(client_id, SERVER_ID);
+        let (_, authority) = app
+            .world
+            .query_filtered::<(&ClientId, Option<&Authority>), With<Player>>()
+            .iter(&app.world)
+            .find(|(id, _)| id.0 == client_id)
+            .expect("Player should be spawned for the connected client");
+        assert!(
+            authority.is_none(),
+            "Player of a remote client shouldn't be controlled by server"
+        );
+    }
+
+    #[test]
+    fn teams_assigned() {
+        let mut app = App::new();
+        app.add_plugin(PlayerPlugin)
+            .add_plugin(TestNetworkPlugin::new(NetworkPreset::Server));
+
+        let mut server_settings = app.world.resource_mut::<ServerSettings>();
+        server_settings.game_mode = GameMode::TeamDeathmatch;
+        for _ in 0..3 {
+            app.world.spawn().insert_bundle(PlayerBundle::default());
+        }
+
+        app.update();
+        app.update();
+
+        let mut teams: Vec<_> = app
+            .world
+            .query_filtered::<&Team, With<Player>>()
+            .iter(&app.world)
+            .map(|team| team.0)
+            .collect();
+        teams.sort_unstable();
+        assert_eq!(
+            teams,
+            [0, 0, 1, 1],
+            "Players should be evenly distributed between teams"
+        );
+    }
+
     #[test]
     fn player_despawns_on_client() {
         let mut app = App::new();
@@ -0,0 +1,189 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use std::ops::DerefMut;
+
+use super::{
+    game_state::GameState,
+    simulation::{SimulationAppExt, SimulationTime},
+};
+
+/// Handles regeneration of character resource pools.
+pub(super) struct PoolPlugin;
+
+impl Plugin for PoolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_tick_system(Self::regeneration_system::<Mana>.run_in_state(GameState::InGame))
+            .add_tick_system(Self::regeneration_system::<Energy>.run_in_state(GameState::InGame))
+            .add_tick_system(
+                Self::regeneration_system::<UltimateCharge>.run_in_state(GameState::InGame),
+            )
+            .add_tick_system(Self::regeneration_system::<Stamina>.run_in_state(GameState::InGame));
+    }
+}
+
+impl PoolPlugin {
+    fn regeneration_system<T: Pool>(time: Res<SimulationTime>, mut pools: Query<&mut T>) {
+        for mut pool in pools.iter_mut() {
+            if pool.regeneration != 0.0 && !pool.is_full() {
+                let delta = pool.regeneration * time.delta_seconds();
+                pool.change(delta);
+            }
+        }
+    }
+}
+
+/// A character resource that can be spent or changed by effects, like mana or energy.
+pub(crate) trait Pool: Component + DerefMut<Target = PoolValue> {}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) struct PoolValue {
+    pub(crate) current: f32,
+    pub(crate) max: f32,
+    /// Amount restored per second.
+    pub(crate) regeneration: f32,
+}
+
+impl PoolValue {
+    /// Changes current value by `delta` within `[0, max]` range.
+    pub(crate) fn change(&mut self, delta: f32) {
+        self.current = (self.current + delta).clamp(0.0, self.max);
+    }
+
+    pub(crate) fn is_full(&self) -> bool {
+        self.current >= self.max
+    }
+}
+
+/// Slowly regenerating resource for abilities.
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct Mana(PoolValue);
+
+impl Default for Mana {
+    fn default() -> Self {
+        Self(PoolValue {
+            current: 100.0,
+            max: 100.0,
+            regeneration: 5.0,
+        })
+    }
+}
+
+impl Pool for Mana {}
+
+/// Quickly regenerating resource for abilities.
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct Energy(PoolValue);
+
+impl Default for Energy {
+    fn default() -> Self {
+        Self(PoolValue {
+            current: 100.0,
+            max: 100.0,
+            regeneration: 20.0,
+        })
+    }
+}
+
+impl Pool for Energy {}
+
+/// Spent on sprinting and dodging.
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct Stamina(PoolValue);
+
+impl Default for Stamina {
+    fn default() -> Self {
+        Self(PoolValue {
+            current: 100.0,
+            max: 100.0,
+            regeneration: 15.0,
+        })
+    }
+}
+
+impl Pool for Stamina {}
+
+/// Charges from damage and healing done, required to activate the ultimate ability.
+#[derive(Component, Deref, DerefMut)]
+pub(crate) struct UltimateCharge(PoolValue);
+
+impl UltimateCharge {
+    /// Charge gained per point of damage or healing.
+    pub(super) const PER_POINT: f32 = 0.5;
+}
+
+impl Default for UltimateCharge {
+    fn default() -> Self {
+        Self(PoolValue {
+            current: 0.0,
+            max: 100.0,
+            regeneration: 0.0,
+        })
+    }
+}
+
+impl Pool for UltimateCharge {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pool_value_clamps() {
+        let mut value = PoolValue {
+            current: 50.0,
+            max: 100.0,
+            regeneration: 0.0,
+        };
+
+        value.change(80.0);
+        assert_eq!(value.current, value.max, "Value shouldn't exceed maximum");
+
+        value.change(-150.0);
+        assert_eq!(value.current, 0.0, "Value shouldn't be negative");
+    }
+
+    #[test]
+    fn pool_regenerates() {
+        let mut app = App::new();
+        app.add_plugin(TestPoolPlugin);
+
+        let mut mana = Mana::default();
+        mana.current = 0.0;
+        let character = app.world.spawn().insert(mana).id();
+
+        app.update();
+        app.update();
+
+        let mana = app.world.get::<Mana>(character).unwrap();
+        assert!(mana.current > 0.0, "Mana should regenerate");
+    }
+
+    struct TestPoolPlugin;
+
+    impl Plugin for TestPoolPlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugins(MinimalPlugins)
+                .add_plugin(PoolPlugin);
+        }
+    }
+}
@@ -0,0 +1,622 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+use bevy::{ecs::system::EntityCommands, prelude::*};
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use super::{
+    game_state::{GameState, InGameOnly},
+    health::Health,
+    player::Team,
+    simulation::{SimulationAppExt, SimulationTime},
+    CollisionMask, Owner,
+};
+
+pub(super) struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_tick_event::<OnHit>()
+            .add_tick_system(Self::steering_system.run_in_state(GameState::InGame))
+            .add_tick_system(
+                Self::hit_system
+                    .run_in_state(GameState::InGame)
+                    .after(Self::steering_system),
+            )
+            .add_system_to_stage(CoreStage::PostUpdate, Self::cleanup_system)
+            .add_exit_system(GameState::InGame, Self::clear_pools_system);
+    }
+}
+
+impl ProjectilePlugin {
+    fn steering_system(
+        time: Res<SimulationTime>,
+        mut projectiles: Query<
+            (&Projectile, &mut Velocity, &mut Transform, Option<&Homing>),
+            Without<Pooled>,
+        >,
+        targets: Query<&Transform, Without<Projectile>>,
+    ) {
+        for (projectile, mut velocity, mut transform, homing) in projectiles.iter_mut() {
+            velocity.linvel.y -= projectile.gravity * time.delta_seconds();
+
+            if let Some(homing) = homing {
+                if let Ok(target_transform) = targets.get(homing.target) {
+                    velocity.linvel = steer(
+                        velocity.linvel,
+                        target_transform.translation - transform.translation,
+                        homing.turn_rate * time.delta_seconds(),
+                    );
+                }
+            }
+
+            if let Some(direction) = velocity.linvel.try_normalize() {
+                transform.rotation = Quat::from_rotation_arc(Vec3::Y, direction);
+            }
+        }
+    }
+
+    /// Sweeps projectile colliders along their velocity to detect hits without tunneling.
+    fn hit_system(
+        mut commands: Commands,
+        time: Res<SimulationTime>,
+        rapier_ctx: Res<RapierContext>,
+        mut hit_events: EventWriter<OnHit>,
+        mut projectiles: Query<
+            (
+                Entity,
+                &Projectile,
+                &mut ProjectileState,
+                &Transform,
+                &mut Velocity,
+                &Collider,
+                Option<&Owner>,
+                Option<&PooledBy>,
+            ),
+            Without<Pooled>,
+        >,
+        characters: Query<Option<&Team>, With<Health>>,
+        mut pools: Query<&mut ProjectilePool>,
+    ) {
+        for (
+            projectile_entity,
+            projectile,
+            mut state,
+            transform,
+            mut velocity,
+            collider,
+            owner,
+            pooled_by,
+        ) in projectiles.iter_mut()
+        {
+            let owner = owner.map(|owner| owner.0);
+            let owner_team = owner
+                .and_then(|owner| characters.get(owner).ok())
+                .flatten()
+                .copied();
+            let distance = velocity.linvel.length() * time.delta_seconds();
+            let direction = velocity.linvel.normalize_or_zero();
+
+            let mut finished = false;
+            loop {
+                let ignored = |entity| {
+                    Some(entity) == owner
+                        || state.hits.contains(&entity)
+                        || (projectile.ignore_allies
+                            && owner_team.is_some()
+                            && characters.get(entity).ok().flatten().copied() == owner_team)
+                };
+                let hit = rapier_ctx.cast_shape(
+                    transform.translation,
+                    transform.rotation,
+                    direction,
+                    collider,
+                    distance,
+                    QueryFilter::new()
+                        .groups(InteractionGroups::new(
+                            CollisionMask::PROJECTILE.bits(),
+                            (CollisionMask::WORLD | CollisionMask::CHARACTER).bits(),
+                        ))
+                        .exclude_collider(projectile_entity)
+                        .predicate(&|entity| !ignored(entity)),
+                );
+
+                let (entity, toi) = match hit {
+                    Some(hit) => hit,
+                    None => break,
+                };
+
+                if characters.get(entity).is_ok() {
+                    hit_events.send(OnHit {
+                        projectile: projectile_entity,
+                        owner,
+                        target: entity,
+                    });
+                    state.hits.push(entity);
+                    if state.hits.len() > projectile.pierce as usize {
+                        finished = true;
+                        break;
+                    }
+                } else {
+                    if state.bounces < projectile.bounces {
+                        state.bounces += 1;
+                        velocity.linvel -= 2.0 * velocity.linvel.dot(toi.normal1) * toi.normal1;
+                    } else {
+                        finished = true;
+                    }
+                    break;
+                }
+            }
+
+            state.traveled += distance;
+            if finished || state.traveled >= projectile.max_range {
+                release(&mut commands, projectile_entity, pooled_by, &mut pools);
+            }
+        }
+    }
+
+    /// Despawns released projectiles of despawned abilities, active ones are despawned on release.
+    fn cleanup_system(
+        mut commands: Commands,
+        removed_pools: RemovedComponents<ProjectilePool>,
+        projectiles: Query<(Entity, &PooledBy), With<Pooled>>,
+    ) {
+        for ability in removed_pools.iter() {
+            for (projectile, _) in projectiles
+                .iter()
+                .filter(|(_, pooled_by)| pooled_by.0 == ability)
+            {
+                commands.entity(projectile).despawn_recursive();
+            }
+        }
+    }
+
+    fn clear_pools_system(mut pools: Query<&mut ProjectilePool>) {
+        for mut pool in pools.iter_mut() {
+            pool.0.clear();
+        }
+    }
+}
+
+/// Returns the projectile to the pool of its ability or despawns it.
+fn release(
+    commands: &mut Commands,
+    projectile: Entity,
+    pooled_by: Option<&PooledBy>,
+    pools: &mut Query<&mut ProjectilePool>,
+) {
+    match pooled_by.and_then(|pooled_by| pools.get_mut(pooled_by.0).ok()) {
+        Some(mut pool) => {
+            pool.0.push(projectile);
+            commands
+                .entity(projectile)
+                .insert(Pooled)
+                .insert(Velocity::default())
+                .insert(Visibility { is_visible: false })
+                .insert(CollisionGroups {
+                    memberships: 0,
+                    filters: 0,
+                })
+                .remove::<Owner>()
+                .remove::<Homing>();
+        }
+        None => commands.entity(projectile).despawn_recursive(),
+    }
+}
+
+/// Rotates `velocity` towards `to_target` by at most `max_angle` radians.
+fn steer(velocity: Vec3, to_target: Vec3, max_angle: f32) -> Vec3 {
+    let (direction, desired_direction) = match (velocity.try_normalize(), to_target.try_normalize())
+    {
+        (Some(direction), Some(desired_direction)) => (direction, desired_direction),
+        _ => return velocity,
+    };
+
+    let angle = direction.angle_between(desired_direction);
+    let fraction = if angle > max_angle {
+        max_angle / angle
+    } else {
+        1.0
+    };
+    let rotation = Quat::from_rotation_arc(direction, desired_direction);
+    Quat::IDENTITY.slerp(rotation, fraction) * velocity
+}
+
+/// Sent when a projectile hits a character.
+pub(super) struct OnHit {
+    pub(super) projectile: Entity,
+    pub(super) owner: Option<Entity>,
+    pub(super) target: Entity,
+}
+
+/// Projectile flight and hit rules.
+#[derive(Component, Clone, Copy)]
+pub(super) struct Projectile {
+    /// Number of characters the projectile passes through before releasing.
+    pub(super) pierce: u8,
+    /// Number of bounces off the world before releasing.
+    pub(super) bounces: u8,
+    /// Travel distance after which the projectile is released.
+    pub(super) max_range: f32,
+    /// Downward acceleration.
+    pub(super) gravity: f32,
+    /// Ignore characters from the owner's team.
+    pub(super) ignore_allies: bool,
+}
+
+impl Default for Projectile {
+    fn default() -> Self {
+        Self {
+            pierce: 0,
+            bounces: 0,
+            max_range: 80.0,
+            gravity: 0.0,
+            ignore_allies: true,
+        }
+    }
+}
+
+#[derive(Component, Default)]
+struct ProjectileState {
+    traveled: f32,
+    bounces: u8,
+    hits: Vec<Entity>,
+}
+
+/// Steers the projectile towards the target.
+#[derive(Component)]
+pub(super) struct Homing {
+    pub(super) target: Entity,
+    /// Maximum turn speed in radians per second.
+    pub(super) turn_rate: f32,
+}
+
+/// Released projectiles of the ability that can be reused.
+#[derive(Component, Default)]
+pub(super) struct ProjectilePool(Vec<Entity>);
+
+impl ProjectilePool {
+    /// Spawns a projectile for the `ability` reusing a released one if available.
+    pub(super) fn spawn<'w, 's, 'a>(
+        &mut self,
+        commands: &'a mut Commands<'w, 's>,
+        ability: Entity,
+        bundle: ProjectileBundle,
+    ) -> EntityCommands<'w, 's, 'a> {
+        let mut entity_commands = match self.0.pop() {
+            Some(projectile) => {
+                let mut entity_commands = commands.entity(projectile);
+                entity_commands.remove::<Pooled>();
+                entity_commands
+            }
+            None => commands.spawn(),
+        };
+        entity_commands
+            .insert_bundle(bundle)
+            .insert(PooledBy(ability));
+        entity_commands
+    }
+}
+
+/// Ability whose pool the projectile returns to.
+#[derive(Component)]
+struct PooledBy(Entity);
+
+/// Indicates that the projectile is inactive and waits for reuse in a pool.
+#[derive(Component)]
+struct Pooled;
+
+#[derive(Bundle)]
+pub(super) struct ProjectileBundle {
+    name: Name,
+    pub(super) projectile: Projectile,
+    state: ProjectileState,
+    rigid_body: RigidBody,
+    collider: Collider,
+    sensor: Sensor,
+    collision_groups: CollisionGroups,
+    pub(super) velocity: Velocity,
+    ingame_only: InGameOnly,
+
+    #[bundle]
+    pub(super) pbr: PbrBundle,
+}
+
+impl Default for ProjectileBundle {
+    fn default() -> Self {
+        Self {
+            name: "Projectile".into(),
+            projectile: Projectile::default(),
+            state: ProjectileState::default(),
+            rigid_body: RigidBody::KinematicVelocityBased,
+            collider: Collider::capsule_y(0.5, 0.5),
+            sensor: Sensor,
+            collision_groups: CollisionGroups {
+                memberships: CollisionMask::PROJECTILE.bits(),
+                filters: (CollisionMask::all() ^ CollisionMask::PROJECTILE).bits(),
+            },
+            velocity: Velocity::default(),
+            ingame_only: InGameOnly,
+            pbr: PbrBundle::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use approx::assert_abs_diff_eq;
+    use bevy::{
+        ecs::{
+            event::{Events, ManualEventReader},
+            system::CommandQueue,
+        },
+        scene::ScenePlugin,
+    };
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+    use crate::core::headless::HeadlessRenderPlugin;
+
+    #[test]
+    fn hits_ignore_owner_and_allies() {
+        let mut app = App::new();
+        app.add_plugin(TestProjectilePlugin);
+
+        const TEAM: Team = Team(0);
+        let owner = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(TEAM)
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .insert(TEAM);
+        let enemy = app
+            .world
+            .spawn()
+            .insert_bundle(DummyCharacterBundle::default())
+            .id();
+        app.world
+            .spawn()
+            .insert_bundle(ProjectileBundle {
+                projectile: Projectile {
+                    pierce: u8::MAX,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(Owner(owner));
+
+        let hits = update_and_collect_hits(&mut app);
+        assert_eq!(hits, [enemy], "Only the enemy should be hit");
+    }
+
+    #[test]
+    fn pierce() {
+        for pierce in 0..2 {
+            let mut app = App::new();
+            app.add_plugin(TestProjectilePlugin);
+
+            for _ in 0..2 {
+                app.world
+                    .spawn()
+                    .insert_bundle(DummyCharacterBundle::default());
+            }
+            let projectile = app
+                .world
+                .spawn()
+                .insert_bundle(ProjectileBundle {
+                    projectile: Projectile {
+                        pierce,
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                })
+                .id();
+
+            let hits = update_and_collect_hits(&mut app);
+            assert_eq!(
+                hits.len(),
+                pierce as usize + 1,
+                "Projectile should pierce {pierce} characters"
+            );
+            assert!(
+                app.world.get_entity(projectile).is_none(),
+                "Projectile should be despawned after exhausting pierce"
+            );
+        }
+    }
+
+    #[test]
+    fn pooling() {
+        let mut app = App::new();
+        app.add_plugin(TestProjectilePlugin);
+
+        let ability = app.world.spawn().insert(ProjectilePool::default()).id();
+        let projectile = app
+            .world
+            .spawn()
+            .insert_bundle(ProjectileBundle {
+                projectile: Projectile {
+                    max_range: 0.0,
+                    ..Default::default()
+                },
+                ..Default::default()
+            })
+            .insert(PooledBy(ability))
+            .id();
+
+        app.update();
+
+        assert!(
+            app.world.entity(projectile).contains::<Pooled>(),
+            "Projectile should be returned to the pool after reaching max range"
+        );
+        let mut pool = app
+            .world
+            .entity_mut(ability)
+            .remove::<ProjectilePool>()
+            .unwrap();
+        assert_eq!(pool.0, [projectile]);
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &app.world);
+        let reused = pool
+            .spawn(&mut commands, ability, ProjectileBundle::default())
+            .id();
+        queue.apply(&mut app.world);
+
+        assert_eq!(reused, projectile, "Pooled projectile should be reused");
+        assert!(
+            !app.world.entity(projectile).contains::<Pooled>(),
+            "Reused projectile should be active"
+        );
+    }
+
+    #[test]
+    fn pool_cleanup() {
+        let mut app = App::new();
+        app.add_plugin(TestProjectilePlugin);
+
+        let ability = app.world.spawn().insert(ProjectilePool::default()).id();
+        let projectile = app
+            .world
+            .spawn()
+            .insert_bundle(ProjectileBundle::default())
+            .insert(PooledBy(ability))
+            .insert(Pooled)
+            .id();
+        app.world
+            .get_mut::<ProjectilePool>(ability)
+            .unwrap()
+            .0
+            .push(projectile);
+
+        app.update();
+
+        app.world.despawn(ability);
+
+        app.update();
+
+        assert!(
+            app.world.get_entity(projectile).is_none(),
+            "Pooled projectile should be despawned with its ability"
+        );
+    }
+
+    #[test]
+    fn homing() {
+        let mut app = App::new();
+        app.add_plugin(TestProjectilePlugin);
+
+        let target = app
+            .world
+            .spawn()
+            .insert(Transform::from_translation(Vec3::Z * 50.0))
+            .id();
+        let projectile = app
+            .world
+            .spawn()
+            .insert_bundle(ProjectileBundle {
+                velocity: Velocity::linear(Vec3::X),
+                ..Default::default()
+            })
+            .insert(Homing {
+                target,
+                turn_rate: FRAC_PI_2,
+            })
+            .id();
+
+        app.update();
+
+        let velocity = app.world.get::<Velocity>(projectile).unwrap();
+        assert!(
+            velocity.linvel.z > 0.0,
+            "Projectile should turn towards the target"
+        );
+        assert_abs_diff_eq!(velocity.linvel.length(), 1.0, epsilon = 0.0001);
+    }
+
+    #[test]
+    fn steering() {
+        assert_abs_diff_eq!(
+            steer(Vec3::X, Vec3::Z, FRAC_PI_2),
+            Vec3::Z,
+            epsilon = 0.0001
+        );
+
+        let velocity = steer(Vec3::X * 2.0, Vec3::Z, FRAC_PI_2 / 2.0);
+        assert_abs_diff_eq!(velocity.length(), 2.0, epsilon = 0.0001);
+        assert_abs_diff_eq!(
+            velocity.angle_between(Vec3::X),
+            FRAC_PI_2 / 2.0,
+            epsilon = 0.0001
+        );
+    }
+
+    /// Runs several updates and returns targets of all [`OnHit`] events.
+    fn update_and_collect_hits(app: &mut App) -> Vec<Entity> {
+        let mut event_reader = ManualEventReader::<OnHit>::default();
+        let mut hits = Vec::new();
+        for _ in 0..3 {
+            app.update();
+            let hit_events = app.world.resource::<Events<OnHit>>();
+            hits.extend(event_reader.iter(hit_events).map(|event| event.target));
+        }
+        hits
+    }
+
+    struct TestProjectilePlugin;
+
+    impl Plugin for TestProjectilePlugin {
+        fn build(&self, app: &mut App) {
+            app.add_loopless_state(GameState::InGame)
+                .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(ScenePlugin)
+                .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+                .add_plugin(ProjectilePlugin);
+        }
+    }
+
+    #[derive(Bundle)]
+    struct DummyCharacterBundle {
+        health: Health,
+        transform: Transform,
+        global_transform: GlobalTransform,
+        collider: Collider,
+        collision_groups: CollisionGroups,
+    }
+
+    impl Default for DummyCharacterBundle {
+        fn default() -> Self {
+            Self {
+                health: Health::default(),
+                transform: Transform::default(),
+                global_transform: GlobalTransform::default(),
+                collider: Collider::ball(1.0),
+                collision_groups: CollisionGroups {
+                    memberships: CollisionMask::CHARACTER.bits(),
+                    filters: CollisionMask::all().bits(),
+                },
+            }
+        }
+    }
+}
@@ -17,7 +17,7 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-pub(super) mod spawn;
+pub(crate) mod spawn;
 
 use bevy::prelude::*;
 use strum::{Display, EnumIter, EnumString};
@@ -35,12 +35,21 @@ impl Plugin for SessionPlugin {
 #[derive(Debug, Display, Clone, Copy, Eq, PartialEq, Hash, EnumIter, EnumString)]
 pub(crate) enum GameMode {
     Deathmatch,
+    TeamDeathmatch,
 }
 
 impl GameMode {
     pub(crate) const fn slots_count(self) -> u8 {
         match self {
-            GameMode::Deathmatch => 10,
+            GameMode::Deathmatch | GameMode::TeamDeathmatch => 10,
+        }
+    }
+
+    /// Number of teams, players without a team are enemies to everyone.
+    pub(crate) const fn teams_count(self) -> u8 {
+        match self {
+            GameMode::Deathmatch => 0,
+            GameMode::TeamDeathmatch => 2,
         }
     }
 }
@@ -17,16 +17,19 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use derive_more::From;
 use iyes_loopless::prelude::*;
+use rand::seq::SliceRandom;
 
 use crate::core::{
+    determinism::GameRng,
     game_state::{GameState, InGameOnly},
     health::Death,
-    hero::{HeroBundle, HeroKind},
-    network::server,
-    player::Player,
+    hero::{hero_definition::HeroDefinitions, HeroBundle, HeroKind},
+    network::server::{self, ServerSettings},
+    player::{Player, Team},
+    simulation::{SimulationAppExt, SimulationTime},
 };
 
 pub(super) struct SpawnPlugin;
@@ -35,18 +38,32 @@ impl Plugin for SpawnPlugin {
     fn build(&self, app: &mut App) {
         app.add_enter_system(
             GameState::InGame,
-            Self::randomize_heroes_system.run_if(server::random_heroes),
+            Self::random_heroes_system.run_if(server::random_heroes),
         )
-        .add_system(Self::spawn_system.run_in_state(GameState::InGame))
-        .add_system(Self::assign_respawn_timer_system.run_in_state(GameState::InGame))
-        .add_system(Self::respawn_system.run_in_state(GameState::InGame));
+        .add_tick_system(Self::randomize_heroes_system.run_in_state(GameState::InGame))
+        .add_tick_system(Self::spawn_system.run_in_state(GameState::InGame))
+        .add_tick_system(Self::assign_respawn_timer_system.run_in_state(GameState::InGame))
+        .add_tick_system(Self::respawn_system.run_in_state(GameState::InGame));
     }
 }
 
 impl SpawnPlugin {
-    fn randomize_heroes_system(mut commands: Commands, players: Query<Entity, Added<Player>>) {
+    fn random_heroes_system(mut commands: Commands, players: Query<Entity, Added<Player>>) {
         for player in players.iter() {
-            commands.entity(player).insert(HeroKind::North); // TODO: Implement random selection when there are more than one hero
+            commands.entity(player).insert(RandomHero);
+        }
+    }
+
+    fn randomize_heroes_system(
+        mut commands: Commands,
+        mut hero_randomizer: HeroRandomizer,
+        players: Query<Entity, Added<RandomHero>>,
+    ) {
+        let mut picked = Vec::new();
+        for player in players.iter() {
+            let hero_kind = hero_randomizer.pick(player, &picked);
+            commands.entity(player).insert(hero_kind.clone());
+            picked.push((player, hero_kind));
         }
     }
 
@@ -55,7 +72,7 @@ impl SpawnPlugin {
         spawn_points: Query<&SpawnPoint>,
         players: Query<(Entity, &HeroKind), Added<HeroKind>>,
     ) {
-        for (player, &hero_kind) in players.iter() {
+        for (player, hero_kind) in players.iter() {
             // TODO: determine best spawn position based on other characters location
             let spawn_point = spawn_points
                 .iter()
@@ -64,7 +81,7 @@ impl SpawnPlugin {
 
             commands
                 .entity(player)
-                .insert_bundle(HeroBundle::new(hero_kind, spawn_point.0));
+                .insert_bundle(HeroBundle::new(hero_kind.clone(), spawn_point.0));
         }
     }
 
@@ -77,16 +94,48 @@ impl SpawnPlugin {
         }
     }
 
+    /// Moves players to a spawn point when respawn timer finishes.
+    /// Applies hero picked while the player was alive, players with random heroes get a new one.
     fn respawn_system(
         mut commands: Commands,
-        time: Res<Time>,
+        time: Res<SimulationTime>,
+        mut hero_randomizer: HeroRandomizer,
         spawn_points: Query<&SpawnPoint>,
-        mut dead_players: Query<(Entity, &mut Transform, &mut RespawnTimer)>,
+        mut dead_players: Query<(
+            Entity,
+            &mut Transform,
+            &mut RespawnTimer,
+            Option<&HeroPick>,
+            Option<&RandomHero>,
+        )>,
     ) {
-        for (player, mut transform, mut respawn_timer) in dead_players.iter_mut() {
+        let mut picked = Vec::new();
+        for (player, mut transform, mut respawn_timer, hero_pick, random_hero) in
+            dead_players.iter_mut()
+        {
             respawn_timer.tick(time.delta());
             if respawn_timer.just_finished() {
-                commands.entity(player).remove::<RespawnTimer>();
+                commands
+                    .entity(player)
+                    .remove::<RespawnTimer>()
+                    .remove::<HeroPick>();
+                match hero_pick.map(|hero_pick| &hero_pick.0) {
+                    Some(Some(hero_kind)) => {
+                        commands
+                            .entity(player)
+                            .insert(hero_kind.clone())
+                            .remove::<RandomHero>();
+                    }
+                    Some(None) if random_hero.is_none() => {
+                        commands.entity(player).insert(RandomHero);
+                    }
+                    _ if random_hero.is_some() => {
+                        let hero_kind = hero_randomizer.pick(player, &picked);
+                        commands.entity(player).insert(hero_kind.clone());
+                        picked.push((player, hero_kind));
+                    }
+                    _ => (),
+                }
                 // TODO: determine best spawn position based on other characters location
                 let spawn_point = spawn_points
                     .iter()
@@ -99,6 +148,75 @@ impl SpawnPlugin {
     }
 }
 
+/// Picks random heroes according to the server settings.
+#[derive(SystemParam)]
+struct HeroRandomizer<'w, 's> {
+    rng: ResMut<'w, GameRng>,
+    server_settings: Res<'w, ServerSettings>,
+    hero_definitions: Res<'w, HeroDefinitions>,
+    players:
+        Query<'w, 's, (Entity, Option<&'static Team>, Option<&'static HeroKind>), With<Player>>,
+}
+
+impl HeroRandomizer<'_, '_> {
+    /// Returns a random hero for the player.
+    /// `picked` contains heroes that were assigned, but not yet inserted.
+    fn pick(&mut self, player: Entity, picked: &[(Entity, HeroKind)]) -> HeroKind {
+        let (_, team, previous_kind) = self.players.get(player).unwrap_or((player, None, None));
+        let mut taken = Vec::new();
+        if self.server_settings.no_hero_repeats {
+            taken.extend(previous_kind);
+        }
+        if self.server_settings.unique_team_heroes && team.is_some() {
+            let is_teammate = |other_player: Entity| {
+                let other_team = self
+                    .players
+                    .get(other_player)
+                    .ok()
+                    .and_then(|(_, team, _)| team);
+                other_player != player && other_team == team
+            };
+            for (other_player, _, hero_kind) in self.players.iter() {
+                if is_teammate(other_player) {
+                    taken.extend(hero_kind);
+                }
+            }
+            for (other_player, hero_kind) in picked {
+                if is_teammate(*other_player) {
+                    taken.push(hero_kind);
+                }
+            }
+        }
+
+        let hero_kinds: Vec<_> = self
+            .hero_definitions
+            .iter()
+            .map(|(hero_kind, _)| hero_kind)
+            .collect();
+        let available: Vec<_> = hero_kinds
+            .iter()
+            .copied()
+            .filter(|hero_kind| !taken.contains(hero_kind))
+            .collect();
+
+        // Fallback to any hero if all heroes are taken
+        let hero_kind = available
+            .choose(&mut self.rng.0)
+            .or_else(|| hero_kinds.choose(&mut self.rng.0))
+            .expect("Unable to find any heroes");
+
+        (*hero_kind).clone()
+    }
+}
+
+/// Hero that will be assigned to the player on the next respawn, [`None`] means random hero.
+#[derive(Component)]
+pub(crate) struct HeroPick(pub(crate) Option<HeroKind>);
+
+/// Indicates that the player gets a random hero on spawn and on each respawn.
+#[derive(Component)]
+pub(crate) struct RandomHero;
+
 #[derive(Component, Deref, DerefMut)]
 struct RespawnTimer(Timer);
 
@@ -129,11 +247,11 @@ impl SpawnPointBundle {
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
-    use strum::IntoEnumIterator;
 
     use super::*;
     use crate::core::{
-        game_state::GameState, headless::HeadlessRenderPlugin, network::server::ServerSettings,
+        determinism::DeterminismPlugin, game_state::GameState, headless::HeadlessRenderPlugin,
+        hero::hero_definition::HeroDefinitionPlugin, network::server::ServerSettings,
     };
 
     #[test]
@@ -157,6 +275,153 @@ mod tests {
         );
     }
 
+    #[test]
+    fn seeded_heroes_randomization() {
+        const PLAYERS_COUNT: usize = 10;
+        let mut picks = Vec::new();
+        for _ in 0..2 {
+            let mut app = App::new();
+            app.add_plugin(TestSpawnPlugin);
+
+            app.insert_resource(ServerSettings {
+                random_heroes: true,
+                seed: Some(0),
+                ..ServerSettings::default()
+            });
+            app.world.spawn().insert(SpawnPoint(Vec3::ZERO));
+            let players: Vec<_> = (0..PLAYERS_COUNT)
+                .map(|_| app.world.spawn().insert(Player).id())
+                .collect();
+
+            app.update();
+
+            let hero_kinds: Vec<_> = players
+                .iter()
+                .map(|&player| app.world.get::<HeroKind>(player).unwrap().clone())
+                .collect();
+            picks.push(hero_kinds);
+        }
+
+        assert_eq!(
+            picks[0], picks[1],
+            "Heroes should be randomized the same way with the same seed"
+        );
+    }
+
+    #[test]
+    fn unique_team_heroes() {
+        let mut app = App::new();
+        app.add_plugin(TestSpawnPlugin);
+
+        app.insert_resource(ServerSettings {
+            random_heroes: true,
+            unique_team_heroes: true,
+            ..ServerSettings::default()
+        });
+        app.world.spawn().insert(SpawnPoint(Vec3::ZERO));
+
+        let heroes_count = app.world.resource::<HeroDefinitions>().iter().count();
+        let players: Vec<_> = (0..heroes_count)
+            .map(|_| app.world.spawn().insert(Player).insert(Team(0)).id())
+            .collect();
+
+        app.update();
+
+        let mut hero_kinds: Vec<_> = Vec::new();
+        for player in players {
+            let hero_kind = app.world.get::<HeroKind>(player).unwrap();
+            assert!(
+                !hero_kinds.contains(hero_kind),
+                "Teammates shouldn't have the same hero"
+            );
+            hero_kinds.push(hero_kind.clone());
+        }
+    }
+
+    #[test]
+    fn no_hero_repeats() {
+        let mut app = App::new();
+        app.add_plugin(TestSpawnPlugin);
+
+        app.insert_resource(ServerSettings {
+            no_hero_repeats: true,
+            ..ServerSettings::default()
+        });
+        let player = app
+            .world
+            .spawn()
+            .insert(Player)
+            .insert(RandomHero)
+            .insert(Transform::default())
+            .id();
+        app.world.spawn().insert(SpawnPoint(Vec3::ZERO));
+
+        app.update();
+
+        for _ in 0..5 {
+            let previous_kind = app.world.get::<HeroKind>(player).unwrap().clone();
+            let mut respawn_timer = RespawnTimer::default();
+            let duration = respawn_timer.duration();
+            respawn_timer.tick(duration - Duration::from_nanos(1));
+            app.world.entity_mut(player).insert(respawn_timer);
+
+            app.update();
+
+            assert_ne!(
+                *app.world.get::<HeroKind>(player).unwrap(),
+                previous_kind,
+                "Random hero shouldn't repeat after respawn"
+            );
+        }
+    }
+
+    #[test]
+    fn hero_pick_on_respawn() {
+        let mut app = App::new();
+        app.add_plugin(TestSpawnPlugin);
+
+        let hero_kinds: Vec<_> = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .map(|(hero_kind, _)| hero_kind.clone())
+            .collect();
+        let player = app
+            .world
+            .spawn()
+            .insert(Player)
+            .insert(hero_kinds[0].clone())
+            .insert(HeroPick(Some(hero_kinds[1].clone())))
+            .insert(Transform::default())
+            .id();
+        app.world.spawn().insert(SpawnPoint(Vec3::ZERO));
+
+        app.update();
+
+        assert_eq!(
+            *app.world.get::<HeroKind>(player).unwrap(),
+            hero_kinds[0],
+            "Hero shouldn't change until respawn"
+        );
+
+        let mut respawn_timer = RespawnTimer::default();
+        let duration = respawn_timer.duration();
+        respawn_timer.tick(duration - Duration::from_nanos(1));
+        app.world.entity_mut(player).insert(respawn_timer);
+
+        app.update();
+
+        assert_eq!(
+            *app.world.get::<HeroKind>(player).unwrap(),
+            hero_kinds[1],
+            "Picked hero should be assigned on respawn"
+        );
+        assert!(
+            !app.world.entity(player).contains::<HeroPick>(),
+            "Hero pick should be removed after respawn"
+        );
+    }
+
     #[test]
     fn hero_spawns() {
         let mut app = App::new();
@@ -165,7 +430,17 @@ mod tests {
         const SPAWN_POINT: Vec3 = Vec3::ONE;
         app.world.spawn().insert(SpawnPoint(SPAWN_POINT));
 
-        for hero_kind in HeroKind::iter() {
+        let hero_kinds: Vec<_> = app
+            .world
+            .resource::<HeroDefinitions>()
+            .iter()
+            .map(|(hero_kind, _)| hero_kind.clone())
+            .collect();
+        assert!(
+            !hero_kinds.is_empty(),
+            "Heroes should be loaded from assets"
+        );
+        for hero_kind in hero_kinds {
             let player = app.world.spawn().insert(hero_kind).id();
 
             app.update();
@@ -261,6 +536,8 @@ mod tests {
             app.init_resource::<ServerSettings>()
                 .add_loopless_state(GameState::InGame)
                 .add_plugin(HeadlessRenderPlugin)
+                .add_plugin(HeroDefinitionPlugin)
+                .add_plugin(DeterminismPlugin)
                 .add_plugin(SpawnPlugin);
         }
     }
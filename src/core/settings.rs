@@ -23,6 +23,7 @@ use leafwing_input_manager::prelude::*;
 use serde::{Deserialize, Serialize};
 use standard_paths::{LocationType, StandardPaths};
 use std::{fs, path::PathBuf};
+use strum::{Display, EnumIter};
 
 use super::control_actions::ControlAction;
 
@@ -51,6 +52,7 @@ pub(crate) struct SettingsApplied;
 pub(crate) struct Settings {
     pub(crate) video: VideoSettings,
     pub(crate) controls: ControlsSettings,
+    pub(crate) camera: CameraSettings,
     #[cfg(feature = "developer")]
     pub(crate) developer: DeveloperSettings,
 }
@@ -125,6 +127,10 @@ impl Default for ControlsSettings {
             .insert(KeyCode::A, ControlAction::Left)
             .insert(KeyCode::D, ControlAction::Right)
             .insert(KeyCode::Space, ControlAction::Jump)
+            .insert(KeyCode::LAlt, ControlAction::Sprint)
+            .insert(KeyCode::C, ControlAction::Crouch)
+            .insert(KeyCode::V, ControlAction::Dodge)
+            .insert(KeyCode::X, ControlAction::SwapShoulder)
             .insert(MouseButton::Left, ControlAction::BaseAttack)
             .insert(KeyCode::Q, ControlAction::Ability1)
             .insert(KeyCode::E, ControlAction::Ability2)
@@ -135,6 +141,51 @@ impl Default for ControlsSettings {
     }
 }
 
+#[derive(Component, Deserialize, Serialize, Clone)]
+#[cfg_attr(test, derive(Debug, PartialEq))]
+#[serde(default)]
+pub(crate) struct CameraSettings {
+    /// Degrees per pixel of mouse motion.
+    pub(crate) mouse_sensitivity: f32,
+    /// Degrees per second at full stick deflection.
+    pub(crate) gamepad_sensitivity: f32,
+    pub(crate) invert_y: bool,
+    /// Vertical field of view in degrees.
+    pub(crate) fov: f32,
+    pub(crate) distance: f32,
+    pub(crate) shoulder: Shoulder,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            mouse_sensitivity: 0.2,
+            gamepad_sensitivity: 180.0,
+            invert_y: false,
+            fov: 45.0,
+            distance: 5.0,
+            shoulder: Shoulder::Right,
+        }
+    }
+}
+
+/// Side of the character the camera looks over.
+#[derive(Deserialize, Serialize, Clone, Copy, PartialEq, Display, EnumIter)]
+#[cfg_attr(test, derive(Debug))]
+pub(crate) enum Shoulder {
+    Left,
+    Right,
+}
+
+impl Shoulder {
+    pub(crate) fn swapped(self) -> Self {
+        match self {
+            Shoulder::Left => Shoulder::Right,
+            Shoulder::Right => Shoulder::Left,
+        }
+    }
+}
+
 #[cfg(feature = "developer")]
 #[derive(Default, Deserialize, Serialize, Clone)]
 #[cfg_attr(test, derive(Debug, PartialEq))]
@@ -0,0 +1,493 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+use bevy::{
+    ecs::{event::Events, schedule::IntoSystemDescriptor},
+    prelude::*,
+};
+use bevy_rapier3d::prelude::*;
+use iyes_loopless::prelude::*;
+use std::time::Duration;
+
+use super::network::server::ServerSettings;
+
+/// Limits the number of ticks per frame to avoid spiraling on slow frames.
+const MAX_TICKS_PER_UPDATE: u32 = 8;
+/// Index of the physics substage that writes simulated positions back to transforms.
+const PHYSICS_WRITEBACK: usize = 2;
+
+/// Runs gameplay and physics at a fixed tick rate and interpolates rendered bodies between ticks.
+pub(super) struct SimulationPlugin;
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        let tick_rate = app
+            .world
+            .get_resource::<ServerSettings>()
+            .map_or(SimulationTime::DEFAULT_TICK_RATE, |settings| {
+                settings.tick_rate
+            });
+        app.insert_resource(SimulationTime::new(tick_rate))
+            .add_simulation_stage()
+            .stage(SimulationStageLabel, |stage: &mut SimulationStage| {
+                for (substage, physics_stage) in stage.physics.iter_mut().zip([
+                    PhysicsStages::SyncBackend,
+                    PhysicsStages::StepSimulation,
+                    PhysicsStages::Writeback,
+                    PhysicsStages::DetectDespawn,
+                ]) {
+                    substage.add_system_set(RapierPhysicsPlugin::<NoUserData>::get_systems(
+                        physics_stage,
+                    ));
+                }
+                stage.physics[PHYSICS_WRITEBACK].add_system(Self::record_system);
+                stage
+            })
+            .add_startup_system(Self::physics_timestep_system)
+            .add_system(Self::tick_rate_system.run_if_resource_exists::<ServerSettings>())
+            .add_system(Self::tick_transforms_system)
+            .add_system_to_stage(CoreStage::First, Self::restore_system)
+            .add_system_to_stage(CoreStage::Last, Self::interpolation_system);
+    }
+}
+
+impl SimulationPlugin {
+    fn physics_timestep_system(
+        simulation_time: Res<SimulationTime>,
+        mut rapier_config: ResMut<RapierConfiguration>,
+    ) {
+        rapier_config.timestep_mode = physics_timestep(&simulation_time);
+    }
+
+    fn tick_rate_system(
+        server_settings: Res<ServerSettings>,
+        mut simulation_time: ResMut<SimulationTime>,
+        mut rapier_config: ResMut<RapierConfiguration>,
+    ) {
+        if server_settings.is_changed() {
+            simulation_time.set_tick_rate(server_settings.tick_rate);
+            rapier_config.timestep_mode = physics_timestep(&simulation_time);
+        }
+    }
+
+    /// Enables interpolation of rendered transforms for moving bodies.
+    fn tick_transforms_system(
+        mut commands: Commands,
+        bodies: Query<
+            (Entity, &RigidBody, &Transform),
+            (Added<RigidBody>, Without<TickTransforms>),
+        >,
+    ) {
+        for (entity, &rigid_body, &transform) in bodies.iter() {
+            if rigid_body != RigidBody::Fixed {
+                commands.entity(entity).insert(TickTransforms {
+                    previous: transform,
+                    current: transform,
+                });
+            }
+        }
+    }
+
+    /// Remembers body transforms after each physics step.
+    fn record_system(mut bodies: Query<(&Transform, &mut TickTransforms)>) {
+        for (&transform, mut tick_transforms) in bodies.iter_mut() {
+            tick_transforms.previous = tick_transforms.current;
+            tick_transforms.current = transform;
+        }
+    }
+
+    /// Returns global transforms of bodies to their simulated values before the next ticks.
+    fn restore_system(
+        mut bodies: Query<
+            (&Transform, &mut GlobalTransform),
+            (With<TickTransforms>, Without<Parent>),
+        >,
+    ) {
+        for (&transform, mut global_transform) in bodies.iter_mut() {
+            *global_transform = transform.into();
+        }
+    }
+
+    /// Interpolates global transforms of bodies and their children between the last two ticks.
+    /// Runs after all gameplay systems, so only rendering sees the interpolated values.
+    fn interpolation_system(
+        simulation_time: Res<SimulationTime>,
+        mut bodies: Query<
+            (&TickTransforms, &mut GlobalTransform, Option<&Children>),
+            Without<Parent>,
+        >,
+        mut transforms: Query<(&Transform, &mut GlobalTransform), Without<TickTransforms>>,
+        children: Query<&Children>,
+    ) {
+        let alpha = simulation_time.overstep_fraction();
+        for (tick_transforms, mut global_transform, body_children) in bodies.iter_mut() {
+            *global_transform = tick_transforms.interpolate(alpha).into();
+            for &child in body_children
+                .into_iter()
+                .flat_map(|children| children.iter())
+            {
+                propagate_visual(child, &global_transform, &mut transforms, &children);
+            }
+        }
+    }
+}
+
+/// Updates global transforms of the entity and its descendants from the interpolated parent.
+fn propagate_visual(
+    entity: Entity,
+    parent: &GlobalTransform,
+    transforms: &mut Query<(&Transform, &mut GlobalTransform), Without<TickTransforms>>,
+    children: &Query<&Children>,
+) {
+    let global_transform = match transforms.get_mut(entity) {
+        Ok((&transform, mut global_transform)) => {
+            *global_transform = parent.mul_transform(transform);
+            *global_transform
+        }
+        Err(_) => return,
+    };
+
+    if let Ok(entity_children) = children.get(entity) {
+        for &child in entity_children.iter() {
+            propagate_visual(child, &global_transform, transforms, children);
+        }
+    }
+}
+
+/// Steps physics once per tick with the same fixed timestep as the simulation.
+fn physics_timestep(simulation_time: &SimulationTime) -> TimestepMode {
+    TimestepMode::Fixed {
+        dt: simulation_time.delta_seconds(),
+        substeps: 1,
+    }
+}
+
+/// Transforms of a moving body at the end of the last two ticks.
+/// The [`Transform`] itself always stays at the simulated value.
+#[derive(Component)]
+struct TickTransforms {
+    previous: Transform,
+    current: Transform,
+}
+
+impl TickTransforms {
+    fn interpolate(&self, alpha: f32) -> Transform {
+        Transform {
+            translation: self
+                .previous
+                .translation
+                .lerp(self.current.translation, alpha),
+            rotation: self.previous.rotation.slerp(self.current.rotation, alpha),
+            scale: self.current.scale,
+        }
+    }
+}
+
+/// Clock of the fixed-rate simulation.
+/// Gameplay systems should advance their timers using this resource instead of [`Time`].
+pub(crate) struct SimulationTime {
+    step: Duration,
+    accumulator: Duration,
+    tick: u64,
+    /// Runs the specified number of ticks per update instead of accumulating frame time.
+    fixed_ticks: Option<u32>,
+}
+
+impl SimulationTime {
+    pub(crate) const DEFAULT_TICK_RATE: u32 = 60;
+
+    fn new(tick_rate: u32) -> Self {
+        Self {
+            step: Duration::from_secs(1) / tick_rate,
+            accumulator: Duration::ZERO,
+            tick: 0,
+            // Run a single tick per update to make tests independent from the real time
+            fixed_ticks: if cfg!(test) { Some(1) } else { None },
+        }
+    }
+
+    fn set_tick_rate(&mut self, tick_rate: u32) {
+        self.step = Duration::from_secs(1) / tick_rate;
+    }
+
+    /// Duration of a single tick.
+    pub(crate) fn delta(&self) -> Duration {
+        self.step
+    }
+
+    pub(crate) fn delta_seconds(&self) -> f32 {
+        self.step.as_secs_f32()
+    }
+
+    /// Number of the current tick.
+    pub(crate) fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Fraction of the step accumulated since the last tick.
+    fn overstep_fraction(&self) -> f32 {
+        self.accumulator.as_secs_f32() / self.step.as_secs_f32()
+    }
+
+    /// Starts counting ticks from zero, used when a game starts.
+    pub(super) fn reset(&mut self) {
+        self.accumulator = Duration::ZERO;
+        self.tick = 0;
+    }
+
+    /// Continues counting ticks from the specified one, used to align ticks with server.
+    pub(super) fn set_tick(&mut self, tick: u64) {
+        self.tick = tick;
+    }
+
+    /// Accumulates frame time and returns the number of ticks to run.
+    fn accumulate(&mut self, delta: Duration) -> u32 {
+        self.accumulator += delta;
+        let mut ticks = 0;
+        while self.accumulator >= self.step {
+            self.accumulator -= self.step;
+            ticks += 1;
+        }
+
+        if ticks > MAX_TICKS_PER_UPDATE {
+            warn!(
+                "Simulation is running behind, skipping {} ticks",
+                ticks - MAX_TICKS_PER_UPDATE
+            );
+            ticks = MAX_TICKS_PER_UPDATE;
+        }
+
+        ticks
+    }
+}
+
+impl Default for SimulationTime {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_TICK_RATE)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, StageLabel)]
+struct SimulationStageLabel;
+
+/// Runs its substages once per simulation tick.
+/// Substages are single-threaded to run systems in the same order on all peers.
+struct SimulationStage {
+    /// Updates events that should live for ticks instead of frames.
+    first: SystemStage,
+    update: SystemStage,
+    /// Runs rapier stages in order, physics is stepped exactly once per tick.
+    /// Added by [`SimulationPlugin`], so tests can keep the default rapier setup.
+    physics: [SystemStage; 4],
+    /// Sees commands applied by the update substage.
+    post_update: SystemStage,
+}
+
+impl Default for SimulationStage {
+    fn default() -> Self {
+        Self {
+            first: SystemStage::single_threaded(),
+            update: SystemStage::single_threaded(),
+            physics: [
+                SystemStage::single_threaded(),
+                SystemStage::single_threaded(),
+                SystemStage::single_threaded(),
+                SystemStage::single_threaded(),
+            ],
+            post_update: SystemStage::single_threaded(),
+        }
+    }
+}
+
+impl Stage for SimulationStage {
+    fn run(&mut self, world: &mut World) {
+        let ticks = match world.resource::<SimulationTime>().fixed_ticks {
+            Some(ticks) => ticks,
+            None => {
+                let delta = world.resource::<Time>().delta();
+                world.resource_mut::<SimulationTime>().accumulate(delta)
+            }
+        };
+
+        for _ in 0..ticks {
+            world.resource_mut::<SimulationTime>().tick += 1;
+            self.first.run(world);
+            self.update.run(world);
+            for physics in &mut self.physics {
+                physics.run(world);
+            }
+            self.post_update.run(world);
+        }
+    }
+}
+
+/// Registers gameplay systems and events in the fixed-rate simulation.
+pub(super) trait SimulationAppExt {
+    /// Adds the simulation stage after [`CoreStage::Update`] if it doesn't exist.
+    fn add_simulation_stage(&mut self) -> &mut Self;
+
+    /// Adds system that runs every simulation tick.
+    fn add_tick_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self;
+
+    /// Adds system that runs every simulation tick after commands of tick systems are applied.
+    fn add_post_tick_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self;
+
+    /// Adds event that is kept for two simulation ticks instead of two frames.
+    fn add_tick_event<T: Resource>(&mut self) -> &mut Self;
+}
+
+impl SimulationAppExt for App {
+    fn add_simulation_stage(&mut self) -> &mut Self {
+        if self
+            .schedule
+            .get_stage::<SimulationStage>(&SimulationStageLabel)
+            .is_none()
+        {
+            self.init_resource::<SimulationTime>().add_stage_after(
+                CoreStage::Update,
+                SimulationStageLabel,
+                SimulationStage::default(),
+            );
+        }
+        self
+    }
+
+    fn add_tick_system<Params>(&mut self, system: impl IntoSystemDescriptor<Params>) -> &mut Self {
+        self.add_simulation_stage()
+            .stage(SimulationStageLabel, |stage: &mut SimulationStage| {
+                stage.update.add_system(system);
+                stage
+            })
+    }
+
+    fn add_post_tick_system<Params>(
+        &mut self,
+        system: impl IntoSystemDescriptor<Params>,
+    ) -> &mut Self {
+        self.add_simulation_stage()
+            .stage(SimulationStageLabel, |stage: &mut SimulationStage| {
+                stage.post_update.add_system(system);
+                stage
+            })
+    }
+
+    fn add_tick_event<T: Resource>(&mut self) -> &mut Self {
+        self.init_resource::<Events<T>>()
+            .add_simulation_stage()
+            .stage(SimulationStageLabel, |stage: &mut SimulationStage| {
+                stage.first.add_system(Events::<T>::update_system);
+                stage
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::thread;
+
+    use super::*;
+
+    #[test]
+    fn ticks_accumulation() {
+        let mut simulation_time = SimulationTime::new(10);
+
+        assert_eq!(
+            simulation_time.accumulate(Duration::from_millis(50)),
+            0,
+            "Tick shouldn't run before the step is accumulated"
+        );
+        assert_eq!(simulation_time.accumulate(Duration::from_millis(60)), 1);
+        assert_eq!(
+            simulation_time.accumulate(Duration::from_millis(290)),
+            3,
+            "Remainder should be kept for the next ticks"
+        );
+        assert_eq!(
+            simulation_time.accumulate(Duration::from_secs(10)),
+            MAX_TICKS_PER_UPDATE,
+            "Ticks per update should be limited"
+        );
+    }
+
+    #[test]
+    fn tick_transforms_interpolation() {
+        let tick_transforms = TickTransforms {
+            previous: Transform::from_xyz(0.0, 0.0, 0.0),
+            current: Transform::from_xyz(2.0, 0.0, 0.0),
+        };
+
+        assert_eq!(tick_transforms.interpolate(0.0).translation, Vec3::ZERO);
+        assert_eq!(
+            tick_transforms.interpolate(0.5).translation,
+            Vec3::new(1.0, 0.0, 0.0)
+        );
+        assert_eq!(
+            tick_transforms.interpolate(1.0).translation,
+            Vec3::new(2.0, 0.0, 0.0)
+        );
+    }
+
+    #[test]
+    fn ticks_from_frame_time() {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .add_tick_system(increment_system);
+        let mut simulation_time = app.world.resource_mut::<SimulationTime>();
+        simulation_time.fixed_ticks = None;
+        let step = simulation_time.delta();
+
+        app.update();
+
+        assert_eq!(
+            app.world.resource::<SimulationTime>().tick(),
+            0,
+            "Tick shouldn't run without elapsed time"
+        );
+
+        let mut time = app.world.resource_mut::<Time>();
+        time.update();
+        thread::sleep(step * 2);
+        time.update();
+
+        app.update();
+
+        let tick = app.world.resource::<SimulationTime>().tick();
+        assert!(tick >= 2, "Ticks should run for the elapsed frame time");
+        assert_eq!(*app.world.resource::<u32>(), tick as u32);
+    }
+
+    #[test]
+    fn tick_systems_run() {
+        let mut app = App::new();
+        app.add_tick_system(increment_system);
+
+        app.update();
+        app.update();
+
+        assert_eq!(app.world.resource::<SimulationTime>().tick(), 2);
+        assert_eq!(*app.world.resource::<u32>(), 2);
+    }
+
+    fn increment_system(mut counter: Local<u32>, mut commands: Commands) {
+        *counter += 1;
+        commands.insert_resource(*counter);
+    }
+}
@@ -42,17 +42,23 @@ use {
 };
 
 #[cfg(feature = "developer")]
-use bevy_inspector_egui::prelude::*;
+use {bevy::asset::AssetServerSettings, bevy_inspector_egui::prelude::*};
 
 fn main() {
     let mut app = App::new();
+    #[cfg(feature = "developer")]
+    app.insert_resource(AssetServerSettings {
+        watch_for_changes: true,
+        ..Default::default()
+    });
+
     if cfg!(feature = "client") {
         app.add_plugins(DefaultPlugins);
     } else {
         app.add_plugins_with(DefaultPlugins, |group| group.disable::<WinitPlugin>());
     }
 
-    app.add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+    app.add_plugin(RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false))
         .add_plugin(CorePlugin)
         .add_plugin(RenetServerPlugin);
 
@@ -87,7 +93,9 @@ mod tests {
         let mut app = App::new();
         app.add_plugin(HeadlessRenderPlugin)
             .add_plugin(InputPlugin)
-            .add_plugin(RapierPhysicsPlugin::<NoUserData>::default())
+            .add_plugin(
+                RapierPhysicsPlugin::<NoUserData>::default().with_default_system_setup(false),
+            )
             .add_plugin(ScenePlugin)
             .add_plugin(CorePlugin);
     }
@@ -141,9 +141,10 @@ impl ChatWindowPlugin {
         mut chat: ResMut<Chat>,
     ) {
         for event in message_events.iter() {
-            let ServerMessage::ChatMessage { sender_id, message } = event;
-            // TODO: Get player name from response
-            chat.add_player_message(sender_id.to_string(), message);
+            if let ServerMessage::ChatMessage { sender_id, message } = event {
+                // TODO: Get player name from response
+                chat.add_player_message(sender_id.to_string(), message);
+            }
         }
     }
 
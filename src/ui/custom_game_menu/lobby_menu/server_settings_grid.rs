@@ -54,6 +54,12 @@ impl GameSettingsGrid<'_> {
                 DragValue::new(&mut self.server_settings.port),
             );
             ui.end_row();
+            ui.label("Tick rate:");
+            ui.add_enabled(
+                self.editable,
+                DragValue::new(&mut self.server_settings.tick_rate).clamp_range(10..=128),
+            );
+            ui.end_row();
             ui.label("Game mode:");
             ui.add_enabled_ui(self.editable, |ui| {
                 ComboBox::from_id_source("Game mode")
@@ -89,6 +95,27 @@ impl GameSettingsGrid<'_> {
                 Checkbox::new(&mut self.server_settings.random_heroes, "Random heroes:"),
             );
             ui.end_row();
+            ui.add_enabled(
+                self.editable,
+                Checkbox::new(
+                    &mut self.server_settings.unique_team_heroes,
+                    "Unique team heroes:",
+                ),
+            );
+            ui.end_row();
+            ui.add_enabled(
+                self.editable,
+                Checkbox::new(
+                    &mut self.server_settings.no_hero_repeats,
+                    "No hero repeats:",
+                ),
+            );
+            ui.end_row();
+            ui.add_enabled(
+                self.editable,
+                Checkbox::new(&mut self.server_settings.fall_damage, "Fall damage:"),
+            );
+            ui.end_row();
         });
     }
 }
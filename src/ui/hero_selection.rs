@@ -17,18 +17,24 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_egui::{
-    egui::{epaint::WHITE_UV, vec2, Align2, Area, ImageButton, Rect, TextureId, Window},
+    egui::{vec2, Align2, Area, ImageButton, TextureId, Window},
     EguiContext,
 };
+use bevy_renet::renet::RenetClient;
 use iyes_loopless::prelude::*;
-use strum::IntoEnumIterator;
 
 use super::{ui_state::UiState, UI_MARGIN};
 use crate::core::{
-    game_state::GameState, hero::HeroKind, network::server::ServerSettings, player::Player,
-    Authority,
+    game_state::GameState,
+    hero::hero_definition::{HeroDefinition, HeroDefinitions},
+    network::{
+        hero_pick::HeroPicks,
+        message::{ClientMessage, ServerMessage},
+        server::ServerSettings,
+        SERVER_ID,
+    },
 };
 
 pub struct HeroSelectionPlugin;
@@ -36,24 +42,57 @@ pub struct HeroSelectionPlugin;
 impl Plugin for HeroSelectionPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(Self::hero_selection_system.run_in_state(UiState::HeroSelection))
+            .add_system(Self::pick_phase_end_system.run_in_state(UiState::HeroSelection))
             .add_enter_system(GameState::InGame, Self::show_hero_selection_system);
     }
 }
 
 impl HeroSelectionPlugin {
+    #[allow(clippy::too_many_arguments)]
     fn hero_selection_system(
         mut commands: Commands,
+        mut hero_icons: Local<HashMap<Handle<Image>, TextureId>>,
+        mut client_events: EventWriter<ClientMessage>,
+        asset_server: Res<AssetServer>,
         mut egui: ResMut<EguiContext>,
-        mut local_player: Query<(Entity, Option<&mut HeroKind>), (With<Authority>, With<Player>)>,
+        client: Option<Res<RenetClient>>,
+        hero_picks: Res<HeroPicks>,
+        hero_definitions: Res<HeroDefinitions>,
+        definitions: Res<Assets<HeroDefinition>>,
     ) {
-        let (player, current_hero_kind) = local_player.single_mut();
+        let client_id = client.map_or(SERVER_ID, |client| client.client_id());
+        let pick_state = hero_picks.picks.get(&client_id);
+        let locked = pick_state.map_or(false, |pick_state| pick_state.locked);
+        if locked && hero_picks.timer.is_some() {
+            commands.insert_resource(NextState(UiState::Hud));
+            return;
+        }
+
+        let mut heroes = Vec::new();
+        for (hero_kind, handle) in hero_definitions.iter() {
+            if let Some(definition) = definitions.get(handle) {
+                let image = asset_server.load(definition.icon.as_str());
+                let texture_id = egui.add_image(image.as_weak());
+                hero_icons.insert(image, texture_id);
+                heroes.push((hero_kind, &definition.name, texture_id));
+            }
+        }
 
         Area::new("Confirm area")
             .anchor(Align2::CENTER_BOTTOM, (0.0, -UI_MARGIN))
             .show(egui.ctx_mut(), |ui| {
-                ui.add_enabled_ui(current_hero_kind.is_some(), |ui| {
+                if let Some(timer) = &hero_picks.timer {
+                    let secs_left = timer.duration().as_secs_f32() - timer.elapsed_secs();
+                    ui.label(format!("Time left: {:.0}", secs_left.ceil()));
+                }
+                ui.add_enabled_ui(pick_state.is_some(), |ui| {
                     if ui.button("Confirm").clicked() {
-                        commands.insert_resource(NextState(UiState::Hud));
+                        if hero_picks.timer.is_some() {
+                            client_events.send(ClientMessage::HeroLock);
+                        } else {
+                            // Hero will be changed on respawn
+                            commands.insert_resource(NextState(UiState::Hud));
+                        }
                     }
                 })
             });
@@ -63,32 +102,36 @@ impl HeroSelectionPlugin {
             .collapsible(false)
             .resizable(false)
             .show(egui.ctx_mut(), |ui| {
-                if let Some(mut current_hero_kind) = current_hero_kind {
-                    for kind in HeroKind::iter() {
-                        let selected = *current_hero_kind == kind;
-                        // TODO: Add hero icon
-                        let button = ImageButton::new(TextureId::Managed(0), vec2(32.0, 32.0))
-                            .uv(Rect::from_two_pos(WHITE_UV, WHITE_UV))
-                            .selected(selected);
+                let picked_kind = pick_state.map(|pick_state| pick_state.hero_kind.as_ref());
+                for (hero_kind, name, texture_id) in heroes {
+                    let selected = picked_kind == Some(Some(hero_kind));
+                    let button = ImageButton::new(texture_id, vec2(32.0, 32.0)).selected(selected);
 
-                        if ui.add(button).clicked() && selected {
-                            *current_hero_kind = kind;
-                        };
-                    }
-                } else {
-                    for hero_kind in HeroKind::iter() {
-                        // TODO: Add hero icon
-                        let button = ImageButton::new(TextureId::Managed(0), vec2(32.0, 32.0))
-                            .uv(Rect::from_two_pos(WHITE_UV, WHITE_UV));
+                    if ui.add(button).on_hover_text(name).clicked() && !selected {
+                        client_events.send(ClientMessage::HeroPick(Some(hero_kind.clone())));
+                    };
+                }
 
-                        if ui.add(button).clicked() {
-                            commands.entity(player).insert(hero_kind);
-                        };
-                    }
+                let random_selected = picked_kind == Some(None);
+                if ui.selectable_label(random_selected, "Random").clicked() && !random_selected {
+                    client_events.send(ClientMessage::HeroPick(None));
                 }
             });
     }
 
+    /// Closes hero selection when heroes are locked automatically.
+    fn pick_phase_end_system(
+        mut commands: Commands,
+        mut server_events: EventReader<ServerMessage>,
+    ) {
+        if server_events
+            .iter()
+            .any(|message| matches!(message, ServerMessage::PickPhaseFinished))
+        {
+            commands.insert_resource(NextState(UiState::Hud));
+        }
+    }
+
     fn show_hero_selection_system(mut commands: Commands, server_settings: Res<ServerSettings>) {
         if server_settings.random_heroes {
             // Skip hero selection
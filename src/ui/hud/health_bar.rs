@@ -20,21 +20,36 @@
 use bevy_egui::egui::*;
 
 /// A simple health bar.
+/// Also used to display other character resources with a different color.
 pub(super) struct HealthBar {
     current: u32,
     max: u32,
+    color: Color32,
 }
 
 impl HealthBar {
     /// `current` shouldn't be bigger then `max`.
     pub(super) fn new(current: u32, max: u32) -> Self {
-        Self { current, max }
+        Self {
+            current,
+            max,
+            color: Color32::DARK_GREEN,
+        }
+    }
+
+    pub(super) fn color(mut self, color: Color32) -> Self {
+        self.color = color;
+        self
     }
 }
 
 impl Widget for HealthBar {
     fn ui(self, ui: &mut Ui) -> Response {
-        let HealthBar { current, max } = self;
+        let HealthBar {
+            current,
+            max,
+            color,
+        } = self;
 
         let height = ui.spacing().interact_size.y;
         let (outer_rect, response) = ui.allocate_exact_size(
@@ -57,8 +72,7 @@ impl Widget for HealthBar {
                 outer_rect.height(),
             ),
         );
-        ui.painter()
-            .rect(inner_rect, 0.0, Color32::DARK_GREEN, Stroke::none());
+        ui.painter().rect(inner_rect, 0.0, color, Stroke::none());
 
         let text: WidgetText = format!("{current} / {max}").into();
         let galley = text.into_galley(ui, Some(false), f32::INFINITY, TextStyle::Button);
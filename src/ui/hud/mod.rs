@@ -23,7 +23,7 @@ mod health_bar;
 
 use bevy::{prelude::*, utils::HashMap};
 use bevy_egui::{
-    egui::{Align2, Area, TextureId},
+    egui::{Align2, Area, Color32, ProgressBar, TextureId},
     EguiContext,
 };
 use iyes_loopless::prelude::*;
@@ -33,10 +33,12 @@ use self::crosshair::Crosshair;
 
 use super::{chat_window::ChatWindowPlugin, ui_actions::UiAction, ui_state::UiState, UI_MARGIN};
 use crate::core::{
-    ability::{Abilities, IconPath},
+    ability::{Abilities, AbilityFailReason, AbilityFailed, Casting, IconPath},
     control_actions::ControlAction,
     cooldown::Cooldown,
+    effect::stacking::ActiveEffects,
     health::Health,
+    pool::{Energy, Mana, PoolValue, Stamina, UltimateCharge},
     Authority,
 };
 use ability_icon::AbilityIcon;
@@ -56,6 +58,7 @@ impl Plugin for HudPlugin {
                     .run_in_state(UiState::Hud)
                     .after(ChatWindowPlugin::chat_system),
             )
+            .add_system(Self::ability_failure_system.run_in_state(UiState::Hud))
             .add_system(Self::crosshair_system.run_in_state(UiState::Hud))
             .add_enter_system(UiState::Hud, Self::enable_control_actions_system)
             .add_enter_system(UiState::Hud, Self::hide_cursor_system)
@@ -69,18 +72,32 @@ impl HudPlugin {
         mut ability_icons: Local<HashMap<Handle<Image>, TextureId>>,
         asset_server: Res<AssetServer>,
         mut egui: ResMut<EguiContext>,
-        local_character: Query<(&Abilities, &Health), With<Authority>>,
+        local_character: Query<
+            (
+                Entity,
+                &Abilities,
+                &Health,
+                Option<&Mana>,
+                Option<&Energy>,
+                Option<&Stamina>,
+                Option<&UltimateCharge>,
+                Option<&Casting>,
+            ),
+            With<Authority>,
+        >,
         cooldowns: Query<&Cooldown>,
         icon_paths: Query<&IconPath>,
+        active_effects: ActiveEffects,
     ) {
-        let (abilities, health) = match local_character.get_single() {
-            Ok(result) => result,
-            Err(_) => return,
-        };
+        let (character, abilities, health, mana, energy, stamina, ultimate_charge, casting) =
+            match local_character.get_single() {
+                Ok(result) => result,
+                Err(_) => return,
+            };
 
         for ability in abilities.iter() {
             let icon_path = icon_paths.get(*ability).unwrap();
-            let image = asset_server.load(icon_path.0);
+            let image = asset_server.load(icon_path.0.as_str());
             let texture_id = egui.add_image(image.as_weak());
             ability_icons.insert(image, texture_id);
         }
@@ -89,7 +106,41 @@ impl HudPlugin {
             .anchor(Align2::CENTER_BOTTOM, (0.0, -UI_MARGIN))
             .show(egui.ctx_mut(), |ui| {
                 ui.set_width(300.0);
+                ui.horizontal(|ui| {
+                    for effect in active_effects.on(character) {
+                        let mut text = effect.kind.to_string();
+                        if effect.stacks > 1 {
+                            text += &format!(" x{}", effect.stacks);
+                        }
+                        if let Some(remaining) = effect.remaining {
+                            text += &format!(" {:.0}s", remaining.as_secs_f32().ceil());
+                        }
+                        ui.label(text);
+                    }
+                });
+                if let Some(casting) = casting {
+                    ui.add(ProgressBar::new(casting.progress()));
+                }
                 ui.add(HealthBar::new(health.current, health.max));
+                for (pool, color) in [
+                    (mana.map(|mana| **mana), Color32::from_rgb(30, 60, 150)),
+                    (
+                        energy.map(|energy| **energy),
+                        Color32::from_rgb(150, 130, 20),
+                    ),
+                    (
+                        stamina.map(|stamina| **stamina),
+                        Color32::from_rgb(40, 130, 60),
+                    ),
+                    (
+                        ultimate_charge.map(|ultimate_charge| **ultimate_charge),
+                        Color32::from_rgb(110, 40, 140),
+                    ),
+                ] {
+                    if let Some(PoolValue { current, max, .. }) = pool {
+                        ui.add(HealthBar::new(current as u32, max as u32).color(color));
+                    }
+                }
                 ui.horizontal(|ui| {
                     for (ability, texture_id) in abilities.iter().zip(ability_icons.values()) {
                         let cooldown = cooldowns.get(*ability).ok();
@@ -99,6 +150,36 @@ impl HudPlugin {
             });
     }
 
+    /// Displays the last ability activation failure of the local character for a short time.
+    fn ability_failure_system(
+        mut last_failure: Local<Option<(AbilityFailReason, Timer)>>,
+        time: Res<Time>,
+        mut failed_events: EventReader<AbilityFailed>,
+        mut egui: ResMut<EguiContext>,
+        local_character: Query<Entity, With<Authority>>,
+    ) {
+        if let Ok(character) = local_character.get_single() {
+            if let Some(event) = failed_events
+                .iter()
+                .filter(|event| event.caster == character)
+                .last()
+            {
+                *last_failure = Some((event.reason, Timer::from_seconds(1.5, false)));
+            }
+        }
+
+        if let Some((reason, timer)) = &mut *last_failure {
+            if timer.tick(time.delta()).finished() {
+                *last_failure = None;
+                return;
+            }
+
+            Area::new("Ability failure")
+                .anchor(Align2::CENTER_CENTER, (0.0, 100.0))
+                .show(egui.ctx_mut(), |ui| ui.label(reason.to_string()));
+        }
+    }
+
     fn crosshair_system(mut egui: ResMut<EguiContext>) {
         Area::new("Crosshair")
             .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
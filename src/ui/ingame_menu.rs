@@ -25,7 +25,7 @@ use bevy_egui::{
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::prelude::*;
 
-use crate::core::game_state::GameState;
+use crate::core::{game_state::GameState, network::server::ServerSettings};
 
 use super::{chat_window::ChatWindowPlugin, ui_actions::UiAction, ui_state::UiState};
 
@@ -47,6 +47,7 @@ impl InGameMenuPlugin {
         mut commands: Commands,
         mut exit_events: EventWriter<AppExit>,
         mut egui: ResMut<EguiContext>,
+        server_settings: Res<ServerSettings>,
     ) {
         Area::new("Main Menu")
             .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
@@ -54,6 +55,9 @@ impl InGameMenuPlugin {
                 if ui.button("Resume").clicked() {
                     commands.insert_resource(NextState(UiState::Hud));
                 }
+                if !server_settings.random_heroes && ui.button("Change hero").clicked() {
+                    commands.insert_resource(NextState(UiState::HeroSelection));
+                }
                 if ui.button("Settings").clicked() {
                     commands.insert_resource(NextState(UiState::SettingsMenu));
                 }
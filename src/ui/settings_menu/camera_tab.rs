@@ -0,0 +1,61 @@
+/*
+ *  Copyright © 2021-2022 Hennadii Chernyshchyk <genaloner@gmail.com>
+ *
+ *  This file is part of Gardum.
+ *
+ *  Gardum is free software: you can redistribute it and/or modify
+ *  it under the terms of the GNU Affero General Public License as
+ *  published by the Free Software Foundation, either version 3 of the
+ *  License, or (at your option) any later version.
+ *
+ *  Gardum is distributed in the hope that it will be useful,
+ *  but WITHOUT ANY WARRANTY; without even the implied warranty of
+ *  MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE. See the
+ *  GNU Affero General Public License for more details.
+ *
+ *  You should have received a copy of the GNU Affero General Public License
+ *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
+ */
+
+use bevy_egui::egui::{ComboBox, Slider, Ui};
+use strum::IntoEnumIterator;
+
+use crate::core::settings::{CameraSettings, Shoulder};
+
+pub(super) struct CameraTab<'a> {
+    camera_settings: &'a mut CameraSettings,
+}
+
+impl<'a> CameraTab<'a> {
+    #[must_use]
+    pub(super) fn new(camera_settings: &'a mut CameraSettings) -> Self {
+        Self { camera_settings }
+    }
+}
+
+impl CameraTab<'_> {
+    pub(super) fn show(self, ui: &mut Ui) {
+        ui.add(
+            Slider::new(&mut self.camera_settings.mouse_sensitivity, 0.01..=1.0)
+                .text("Mouse sensitivity"),
+        );
+        ui.add(
+            Slider::new(&mut self.camera_settings.gamepad_sensitivity, 30.0..=360.0)
+                .text("Gamepad sensitivity"),
+        );
+        ui.checkbox(&mut self.camera_settings.invert_y, "Invert Y axis");
+        ui.add(Slider::new(&mut self.camera_settings.fov, 30.0..=120.0).text("Field of view"));
+        ui.add(Slider::new(&mut self.camera_settings.distance, 2.0..=10.0).text("Distance"));
+        ComboBox::from_label("Shoulder")
+            .selected_text(self.camera_settings.shoulder.to_string())
+            .show_ui(ui, |ui| {
+                for shoulder in Shoulder::iter() {
+                    ui.selectable_value(
+                        &mut self.camera_settings.shoulder,
+                        shoulder,
+                        shoulder.to_string(),
+                    );
+                }
+            });
+    }
+}
@@ -17,6 +17,7 @@
  *  along with Gardum. If not, see <https://www.gnu.org/licenses/>.
  */
 
+mod camera_tab;
 mod controls_tab;
 #[cfg(feature = "developer")]
 mod developer_tab;
@@ -41,6 +42,7 @@ use crate::core::{
     game_state::GameState,
     settings::{Settings, SettingsApplied},
 };
+use camera_tab::CameraTab;
 use controls_tab::ControlsTab;
 #[cfg(feature = "developer")]
 use developer_tab::DeveloperTab;
@@ -93,6 +95,7 @@ impl SettingsMenuPlugin {
                     SettingsTab::Control => {
                         ControlsTab::new(&mut settings.controls).show(ui, &mut commands)
                     }
+                    SettingsTab::Camera => CameraTab::new(&mut settings.camera).show(ui),
                     #[cfg(feature = "developer")]
                     SettingsTab::Developer => DeveloperTab::new(&mut settings.developer).show(ui),
                 };
@@ -240,6 +243,7 @@ struct BindingConflict {
 enum SettingsTab {
     Video,
     Control,
+    Camera,
     #[cfg(feature = "developer")]
     Developer,
 }